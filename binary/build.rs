@@ -1,15 +1,51 @@
-use std::{fs::File, io::Write};
+use std::{fs::File, io::Write, path::Path};
+
+use interface::host::fami;
 
 fn main() {
+    generate_music();
+    generate_symbols();
     if true {
         return;
     }
     generate_tile_maps();
-    generate_music();
 }
 
+/// The symbol table of the last build for `interface::panic`, `$OUT_DIR/symbols.bin`. It sits
+/// behind everything else in the image so it can't move the code it names, the second build
+/// after a change has the right one (see `build.sh`).
+fn generate_symbols() {
+    const ELF: &str = "../mips/bin/com.o";
+    println!("cargo:rerun-if-changed={ELF}");
+
+    let table = match std::fs::read(ELF) {
+        Ok(elf) => interface::host::symbols::table_from_elf(&elf).unwrap_or_else(|err| {
+            println!("cargo:warning={ELF}: {err}, backtraces won't have names");
+            Vec::new()
+        }),
+        Err(_) => Vec::new(),
+    };
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    std::fs::write(Path::new(&out_dir).join("symbols.bin"), table)
+        .expect("Error while writting to file");
+}
+
+/// FamiTracker text exports in `res/sound`, compiled to `$OUT_DIR/<name>.song`.
+const SONGS: &[&str] = &["tetris_gb"];
+
 fn generate_music() {
-    //rustc_fami::parser::read_text("res/sound/tetris_gb.txt".into()).unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+    for name in SONGS {
+        let path = format!("res/sound/{name}.txt");
+        println!("cargo:rerun-if-changed={path}");
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let module = fami::parse_text(&text).unwrap_or_else(|err| panic!("{path}: {err}"));
+        let song = fami::compile(&module).unwrap_or_else(|err| panic!("{path}: {err}"));
+
+        let mut file = File::create(Path::new(&out_dir).join(format!("{name}.song"))).unwrap();
+        file.write_all(&song).expect("Error while writting to file");
+    }
 }
 
 fn generate_tile_maps() {
@@ -2,11 +2,18 @@
 #![no_main]
 #![feature(const_for)]
 #![feature(strict_provenance)]
-#![feature(default_alloc_error_handler)]
 
 extern crate alloc;
 
-use util::display::Color;
+use interface::{
+    audio::{
+        sfx::{self, Effect, SfxMixer, Step},
+        Duty,
+    },
+    color::{Color, Hsv},
+    input::{Gamepad, GamepadButton},
+    log::{self, LevelFilter, MemorySink},
+};
 
 use crate::util::display::draw_chacater;
 
@@ -15,180 +22,36 @@ extern crate interface;
 pub mod tetris;
 pub mod util;
 
-pub mod allocater {
-    use core::{
-        alloc::{GlobalAlloc, Layout},
-        cell::UnsafeCell,
-        fmt::Debug,
-        mem::{align_of, size_of},
-        ptr::{self, NonNull},
-    };
+#[global_allocator]
+static ALLOCATOR: interface::heap::Heap = interface::heap::Heap::new();
 
-    #[repr(C)]
-    struct AllocatedMemory {
-        size: usize,
-        align: usize,
-        data: *mut u8,
-        next: Option<NonNull<AllocatedMemory>>,
-    }
-    impl Debug for AllocatedMemory {
-        fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
-            f.debug_struct("AllocatedMemory")
-                .field("_start_", &(self as *const Self))
-                .field("size", &self.size)
-                .field("align", &self.align)
-                .field("data", &self.data)
-                .field("next", &self.next)
-                .field("_end_", unsafe { &self.alligned_end() })
-                .finish()
-        }
-    }
-
-    impl AllocatedMemory {
-        unsafe fn alligned_end(&self) -> *mut u8 {
-            self.data.map_addr(|mut end| {
-                end += self.size;
-                end += align_of::<&Self>() - 1;
-                end &= !(align_of::<&Self>() - 1);
-                end
-            })
-        }
-
-        #[allow(unused)]
-        unsafe fn calc_free_space_ahead(&self) -> usize {
-            if let Option::Some(next) = self.next {
-                next.as_ptr().addr() - self.alligned_end().addr()
-            } else {
-                usize::MAX - (self.data.addr() + self.size)
-            }
-        }
-    }
-    struct AllocatedMemoryIterator {
-        current: Option<NonNull<AllocatedMemory>>,
-    }
-    impl Iterator for AllocatedMemoryIterator {
-        type Item = NonNull<AllocatedMemory>;
+static MENU_MOVE: Effect = Effect {
+    steps: &[
+        Step::note(2, 76, 8).with_duty(Duty::Eighth),
+        Step::note(3, 81, 6)
+            .with_duty(Duty::Eighth)
+            .with_volume_slide(-32),
+    ],
+    channels: sfx::PULSE,
+    priority: 1,
+};
 
-        fn next(&mut self) -> Option<Self::Item> {
-            unsafe {
-                let curr = self.current;
-                if let Option::Some(curr) = curr {
-                    self.current = (*curr.as_ptr()).next;
-                }
-                curr
-            }
-        }
-    }
-
-    struct SimpleAccocator {
-        head: UnsafeCell<*mut AllocatedMemory>,
-    }
-    #[global_allocator]
-    static ALLOCATOR: SimpleAccocator = SimpleAccocator {
-        head: UnsafeCell::new(0 as *mut AllocatedMemory),
-    };
-    unsafe impl Send for SimpleAccocator {}
-    unsafe impl Sync for SimpleAccocator {}
-
-    unsafe impl GlobalAlloc for SimpleAccocator {
-        unsafe fn alloc(&self, layout: core::alloc::Layout) -> *mut u8 {
-            if (*self.head.get()).is_null() {
-                *self.head.get() = core::mem::transmute(interface::heap_address());
-                let head = *self.head.get();
-                Self::add_new(head, &layout);
-                (*head).data
-            } else {
-                let iter = AllocatedMemoryIterator {
-                    current: Option::Some(NonNull::new_unchecked(*self.head.get())),
-                };
-                for alloc in iter {
-                    let mut start = (*alloc.as_ptr()).alligned_end().addr();
-                    start += size_of::<AllocatedMemory>();
-                    start += layout.align() - 1;
-                    start &= !(layout.align() - 1);
-                    if let Option::Some(next) = (*alloc.as_ptr()).next {
-                        if start < next.addr().into() {
-                            let new = (*alloc.as_ptr()).alligned_end();
-                            let new: *mut AllocatedMemory = core::mem::transmute(new);
-                            Self::add_new(new, &layout);
-                            (*new).next = (*alloc.as_ptr()).next;
-                            (*alloc.as_ptr()).next = Option::Some(NonNull::new_unchecked(new));
-                            return (*new).data;
-                        } else {
-                            continue;
-                        }
-                    } else {
-                        let new = (*alloc.as_ptr()).alligned_end();
-                        let new: *mut AllocatedMemory = core::mem::transmute(new);
-                        Self::add_new(new, &layout);
-                        (*alloc.as_ptr()).next = Option::Some(NonNull::new_unchecked(new));
-                        return (*new).data;
-                    }
-                }
-                ptr::null_mut()
-            }
-        }
-
-        unsafe fn dealloc(&self, ptr: *mut u8, _layout: core::alloc::Layout) {
-            if (*self.head.get()).is_null() {
-                panic!();
-            } else {
-                let mut iter = AllocatedMemoryIterator {
-                    current: Option::Some(NonNull::new_unchecked(*self.head.get())),
-                };
-                let mut last = iter.next().unwrap();
-                if (*last.as_ptr()).data == ptr {
-                    (*self.head.get()) = match (*last.as_ptr()).next {
-                        Some(some) => some.as_ptr(),
-                        None => ptr::null_mut(),
-                    };
-                    return;
-                }
-                for alloc in iter {
-                    if (*alloc.as_ptr()).data == ptr {
-                        (*last.as_ptr()).next = (*alloc.as_ptr()).next;
-                        return;
-                    } else {
-                        last = alloc;
-                    }
-                }
-            }
-            panic!("to free: {:?}\nhead: {:?}", ptr, (**self.head.get()));
-        }
-    }
-    impl SimpleAccocator {
-        unsafe fn add_new(alloc: *mut AllocatedMemory, layout: &Layout) {
-            (*alloc).align = layout.align();
-            (*alloc).size = layout.size();
-            (*alloc).next = Option::None;
-            (*alloc).data = core::mem::transmute(alloc.map_addr(|mut add| {
-                let align_mask_to_round_down = !(layout.align() - 1);
-                add += size_of::<AllocatedMemory>();
-                add += layout.align() - 1;
-                add & align_mask_to_round_down
-            }));
-        }
-        #[allow(unused)]
-        unsafe fn count_allocations(&self) -> usize {
-            if self.head.get().is_null() {
-                0
-            } else {
-                let iter = AllocatedMemoryIterator {
-                    current: Option::Some(NonNull::new_unchecked(*self.head.get())),
-                };
-                iter.count()
-            }
-        }
-    }
-}
+/// Last few log lines, drawn at the bottom of the menu.
+static LOG_OVERLAY: MemorySink<6, 32> = MemorySink::new();
 
 struct MenuScreen {
     scroll_index: usize,
+    gamepad: Gamepad,
+    sfx: SfxMixer,
 }
 
 impl MenuScreen {
     pub fn new() -> Self {
-        let mut s = Self { scroll_index: 0 };
+        let mut s = Self {
+            scroll_index: 0,
+            gamepad: Gamepad::new(0),
+            sfx: SfxMixer::new(),
+        };
         s.init();
         s
     }
@@ -196,11 +59,17 @@ impl MenuScreen {
         interface::sys::init_screen(
             crate::tetris::renderer::WIDTH,
             crate::tetris::renderer::HEIGHT,
-        );
+        )
+        .expect("screen size rejected by the host");
     }
     pub fn update(&mut self) -> bool {
-        interface::sys::fill_screen(Color::from_rgb(50, 50, 50).into());
+        self.gamepad.update();
+        let _ = interface::sys::fill_screen(Color::from_rgb(50, 50, 50).into());
         draw_wiggly_text();
+        crate::util::display::draw_log_overlay(
+            &LOG_OVERLAY,
+            [0, crate::tetris::renderer::HEIGHT / 8 - 6],
+        );
 
         // let mut vec = alloc::vec::Vec::new();
         // for i in 0..interface::sys::rand_range(30, 300){
@@ -209,38 +78,69 @@ impl MenuScreen {
         // drop(vec);
 
         self.update_demo_selection();
-        interface::sys::update_screen_vsync();
+        self.sfx.tick(None);
+        let _ = interface::sys::update_screen_vsync();
 
-        !interface::sys::is_key_pressed('\x08')
+        !self.gamepad.is_down(GamepadButton::Back)
     }
 
     pub fn update_demo_selection(&mut self) {
         let items = [("Tetris", crate::tetris::run_tetris)];
 
-        if interface::sys::is_key_pressed('\n') {
+        if self.gamepad.just_pressed(GamepadButton::Start) {
+            self.sfx.stop_all();
+            log::info!("starting {}", items[self.scroll_index].0);
             items[self.scroll_index].1();
-            while interface::sys::is_key_pressed('\x08') {
+            loop {
+                self.gamepad.update();
+                if !self.gamepad.is_down(GamepadButton::Back) {
+                    break;
+                }
                 interface::sys::sleep_mills(1);
             }
-            interface::sys::init_screen(
-                crate::tetris::renderer::WIDTH,
-                crate::tetris::renderer::HEIGHT,
-            );
+            self.init();
+        }
+        if self.gamepad.auto_repeat(GamepadButton::DPadDown, 20, 8) {
+            self.scroll_index = (self.scroll_index + 1) % items.len();
+            self.sfx.play(&MENU_MOVE);
+        }
+        if self.gamepad.auto_repeat(GamepadButton::DPadUp, 20, 8) {
+            self.scroll_index = (self.scroll_index + items.len() - 1) % items.len();
+            self.sfx.play(&MENU_MOVE);
         }
-        if interface::sys::is_key_pressed('s') {}
-        if interface::sys::is_key_pressed('w') {}
     }
 }
 
 #[no_mangle]
 pub fn main() {
+    interface::stack::paint();
+    interface::stack::set_guard(true);
+    interface::trap::install();
+    log::init(log::Config::new(LevelFilter::Info).with_timestamps(true));
+    log::add_sink(&log::CONSOLE);
+    log::add_sink(&LOG_OVERLAY);
+
+    ALLOCATOR.track_callers(true);
+
     let mut menu = MenuScreen::new();
     while menu.update() {
         //interface::sys::sleep_delta_mills(16);
         if interface::sys::is_key_pressed('p') {
-            interface::sys::set_pixel_index(u32::MAX, 0)
+            if let Err(err) = interface::sys::set_pixel_index(u32::MAX, 0) {
+                log::warn!("set_pixel_index(u32::MAX): {}", err);
+            }
+        }
+        if interface::sys::is_key_pressed('m') {
+            crate::util::bench::run_memory_benchmark();
         }
+        if interface::sys::is_key_pressed('t') {
+            crate::util::tasks::run_task_smoke_test();
+        }
+    }
+    if ALLOCATOR.check_leaks() != 0 {
+        log::warn!("heap: {}", ALLOCATOR.stats());
     }
+    log::info!("{}", interface::stack::usage());
 }
 
 pub const fn convert_str<const S: usize>(str: &[u8; S]) -> [u8; S] {
@@ -268,12 +168,11 @@ fn draw_wiggly_text() {
     //let text = [33,34,35,36,37,38,39];Multi
     let micros = interface::sys::get_micros();
     for (i, c) in TEXT.iter().enumerate() {
-        let init = Color::from_rgb(
-            (((micros as u32 / 10000).wrapping_sub(i as u32 * 16)) % 255) as u8,
-            255,
-            255,
-        );
-        let color = interface::sys::hsv_to_rgb(init.into()).into();
+        let color = Color::from_hsv(Hsv {
+            h: (((micros as u32 / 10000).wrapping_sub(i as u32 * 16)) % 255) as u8,
+            s: 255,
+            v: 255,
+        });
         draw_chacater(
             [
                 i as u32 * 8,
@@ -287,10 +186,26 @@ fn draw_wiggly_text() {
     }
 }
 
+interface::embed_symbols!(concat!(env!("OUT_DIR"), "/symbols.bin"));
+
 #[panic_handler]
 #[no_mangle]
 fn panic(info: &core::panic::PanicInfo) -> ! {
-    interface::println!("{}", info);
+    if !interface::panic::first_panic() {
+        interface::println!("{}", info);
+        interface::println!("PANICKED WHILE PANICKING, STOPPING");
+        interface::sys::halt();
+    }
+    let report = interface::panic::Report::capture(info);
+    // the crash screen updates the screen, which would check the guard again
+    interface::stack::set_guard(false);
+    interface::println!("{}", report);
+    log::flush();
+    crate::util::display::draw_crash_screen(
+        &report,
+        crate::tetris::renderer::WIDTH,
+        crate::tetris::renderer::HEIGHT,
+    );
     interface::println!("STOPPING");
     interface::sys::halt();
 }
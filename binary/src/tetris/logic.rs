@@ -1,3 +1,9 @@
+use interface::{
+    log,
+    rand::{self, Pcg32, Rng},
+    time::Instant,
+};
+
 use crate::tetris::InterfaceTrait;
 
 use self::{game::TetrisGame, input::TetrisInput, renderer::TetrisRenderer, sound::TetrisSound};
@@ -10,9 +16,15 @@ pub struct Tetris {
     sound: TetrisSound,
     debug: Option<DebugInfo>,
     frame_counter: u32,
+    rng: Pcg32,
     pub interface: crate::tetris::platform::Interface,
 }
 
+/// Microseconds from `start` to `end`, for the debug overlay.
+fn micros(start: Instant, end: Instant) -> u64 {
+    (end - start).as_micros() as u64
+}
+
 #[derive(Clone, Copy)]
 struct FrameTimes {
     input_time: u64,
@@ -38,18 +50,14 @@ struct DebugInfo {
 
 impl Tetris {
     #[inline(always)]
-    pub fn rand_num(&self, min: usize, max: usize) -> usize {
-        let mut x = self.frame_counter;
-        x = ((x >> 16) ^ x).wrapping_mul(0x45d9f3b_u32);
-        x = ((x >> 16) ^ x).wrapping_mul(0x45d9f3b_u32);
-        x = (x >> 16) ^ x;
-        let x = (x >> 1) as usize;
-
-        let dif = (max + 1).wrapping_sub(min);
-        (x % dif) + min
+    pub fn rand_num(&mut self, min: usize, max: usize) -> usize {
+        self.rng.range(min..=max)
     }
 
     pub fn new(mut interface: crate::tetris::platform::Interface) -> Self {
+        // pieces only depend on this, the same seed deals the same ones
+        let seed = rand::time_seed();
+        log::info!("tetris seed {:#018x}", seed);
         let mut t = Tetris {
             renderer: TetrisRenderer::init(&mut interface),
             game: TetrisGame::init(),
@@ -57,33 +65,61 @@ impl Tetris {
             sound: TetrisSound::init(),
             debug: Default::default(),
             frame_counter: 0,
+            rng: Pcg32::new(seed),
             interface,
         };
         t.init();
         t
     }
-    pub fn run_frame(&mut self) -> bool {
-        let t1 = self.interface.time_micros();
+    /// One fixed step of input, sound and the game, `false` once quit is pressed.
+    pub fn update(&mut self) -> bool {
+        let t1 = self.interface.now();
         self.update_input();
-        let t2 = self.interface.time_micros();
-        self.update_sound();
-        let t3 = self.interface.time_micros();
+        let t2 = self.interface.now();
         self.update_game();
-        let t4 = self.interface.time_micros();
-        self.render_frame();
-        let t5 = self.interface.time_micros();
+        let t3 = self.interface.now();
+        // after the game, so its events play the same step
+        self.update_sound();
+        let t4 = self.interface.now();
+        interface::binlog!(
+            Trace,
+            "step {}: input {} game {} sound {}",
+            self.frame_counter,
+            micros(t1, t2),
+            micros(t2, t3),
+            micros(t3, t4),
+        );
         if let Some(debug) = &mut self.debug {
+            // `render` fills in the rest
             debug.frame_times = Option::Some(FrameTimes {
-                input_time: t1.abs_diff(t2),
-                sound_time: t2.abs_diff(t3),
-                game_time: t3.abs_diff(t4),
-                render_time: t4.abs_diff(t5),
-                total_time: t1.abs_diff(t5),
+                input_time: micros(t1, t2),
+                game_time: micros(t2, t3),
+                sound_time: micros(t3, t4),
+                render_time: 0,
+                total_time: 0,
             });
         }
         self.frame_counter += 1;
 
-        !self.interface.key_down('\x08')
+        !self.input.quit_pressed()
+    }
+
+    /// Draws the state after the last [`Tetris::update`], once per displayed frame.
+    pub fn render(&mut self) {
+        let t1 = self.interface.now();
+        self.render_frame();
+        let t2 = self.interface.now();
+        interface::binlog!(Trace, "render {}", micros(t1, t2));
+        if let Some(times) = self
+            .debug
+            .as_mut()
+            .and_then(|debug| debug.frame_times.as_mut())
+        {
+            // frames without a step draw the last one again
+            times.render_time = micros(t1, t2);
+            times.total_time =
+                times.input_time + times.sound_time + times.game_time + times.render_time;
+        }
     }
     fn init(&mut self) {
         self.init_renderer();
@@ -161,6 +197,19 @@ mod game {
         pub lines_cleared: u32,
         pub combo_count: u32,
         pub piece_stats: [u32; 8],
+        pub events: GameEvents,
+    }
+
+    /// What happened during the last `update_game`, for the sound to react to.
+    #[derive(Default, Clone, Copy)]
+    pub struct GameEvents {
+        pub moved: bool,
+        pub rotated: bool,
+        pub hard_dropped: bool,
+        pub locked: bool,
+        pub lines_cleared: u32,
+        pub level_up: bool,
+        pub game_over: bool,
     }
 
     impl TetrisGame {
@@ -236,6 +285,27 @@ mod game {
             }
             false
         }
+        fn is_row_full(row: u32) -> bool {
+            (0..10).all(|x| (row >> (x * 3)) & 7 != 0)
+        }
+        /// Removes full rows, moving everything above them down, and returns how many there were.
+        fn clear_full_lines(&mut self) -> u32 {
+            let mut cleared = 0;
+            let mut dst = self.data.len();
+            for src in (0..self.data.len()).rev() {
+                let row = self.data[src];
+                if Self::is_row_full(row) {
+                    cleared += 1;
+                    continue;
+                }
+                dst -= 1;
+                self.data[dst] = row;
+            }
+            for row in &mut self.data[..dst] {
+                *row = 0;
+            }
+            cleared
+        }
         #[inline(always)]
         fn is_empty(&self, coord: Coord) -> bool {
             self.data_at_coord(coord) == 0
@@ -268,6 +338,7 @@ mod game {
                 lines_cleared: 0,
                 combo_count: 0,
                 piece_stats: [0; 8],
+                events: GameEvents::default(),
             }
         }
     }
@@ -416,6 +487,7 @@ mod game {
     impl Tetris {
         pub fn update_game(&mut self) {
             let mut new = false;
+            self.game.events = GameEvents::default();
 
             if self.input.drop_down_pressed() {
                 if let Some(dropped) = self.game.get_dropped_piece() {
@@ -423,6 +495,7 @@ mod game {
                         self.game.board.set_data_at_coord(dropped.0 + 1, coord);
                     }
                     self.game.score += dropped.2 as u32 * 2;
+                    self.game.events.hard_dropped = true;
                     new = true;
                 }
             } else {
@@ -455,18 +528,24 @@ mod game {
                             piece.coords = piece.coords - [1i16, 0].into();
                             if self.game.board.is_any_intersecting(&piece.get_coords()) {
                                 piece.coords = piece.coords + [1i16, 0].into();
+                            } else {
+                                self.game.events.moved = true;
                             }
                         }
                         if self.input.right_pressed() {
                             piece.coords = piece.coords + [1i16, 0].into();
                             if self.game.board.is_any_intersecting(&piece.get_coords()) {
                                 piece.coords = piece.coords - [1i16, 0].into();
+                            } else {
+                                self.game.events.moved = true;
                             }
                         }
                         if self.input.up_pressed() {
                             piece.rotation = (piece.rotation + 1) & 3;
                             if self.game.board.is_any_intersecting(&piece.get_coords()) {
                                 piece.rotation = piece.rotation.wrapping_sub(1) & 3;
+                            } else {
+                                self.game.events.rotated = true;
                             }
                         }
                     }
@@ -480,16 +559,51 @@ mod game {
                 if let Some(piece) = self.game.piece {
                     self.game.piece_stats[piece.piece_type.as_num() as usize] += 1;
                     self.game.piece_stats[7] += 1;
+                    self.game.events.locked = true;
+                    self.clear_lines();
                 }
-                self.game.piece = Option::Some(FallingPiece {
+                let piece = FallingPiece {
                     piece_type: Tetrominoes::from_num(self.rand_num(0, 6) as u8),
                     frames_since_last_fall: 0,
                     rotation: 0,
                     coords: [5, 22i16].into(),
-                });
+                };
+                if self.game.board.is_any_intersecting(&piece.get_coords()) {
+                    self.game_over();
+                }
+                self.game.piece = Option::Some(piece);
             }
         }
 
+        fn clear_lines(&mut self) {
+            const LINE_SCORES: [u32; 5] = [0, 40, 100, 300, 1200];
+
+            let cleared = self.game.board.clear_full_lines();
+            self.game.events.lines_cleared = cleared;
+            if cleared == 0 {
+                self.game.combo_count = 0;
+                return;
+            }
+            self.game.combo_count += 1;
+            self.game.score += LINE_SCORES[cleared.min(4) as usize] * (self.game.level + 1);
+            self.game.lines_cleared += cleared;
+            let level = self.game.lines_cleared / 10;
+            if level > self.game.level {
+                self.game.level = level;
+                self.game.events.level_up = true;
+            }
+        }
+
+        /// Starts over on an empty board, so the next piece spawns and this only fires once.
+        fn game_over(&mut self) {
+            let events = self.game.events;
+            self.game = TetrisGame::init();
+            self.game.events = GameEvents {
+                game_over: true,
+                ..events
+            };
+        }
+
         //fn drop_piece(&mut self, piece: FallingPiece) {}
     }
 }
@@ -610,9 +724,9 @@ pub mod renderer {
         }
 
         pub fn render_frame(&mut self) {
-            let t1 = self.interface.time_micros(); //background
+            let t1 = self.interface.now(); //background
 
-            let t2 = self.interface.time_micros(); //game board
+            let t2 = self.interface.now(); //game board
 
             let curr = self.game.get_curr_piece();
             if self.renderer.piece != curr {
@@ -651,7 +765,7 @@ pub mod renderer {
                 self.renderer.piece = curr;
             }
 
-            let t3 = self.interface.time_micros(); //pieces
+            let t3 = self.interface.now(); //pieces
 
             if let Option::Some(old_board) = self.renderer.board {
                 for y in 0u8..20 {
@@ -681,7 +795,7 @@ pub mod renderer {
             }
             self.renderer.board = Option::Some(self.game.board);
 
-            let t4 = self.interface.time_micros(); //text
+            let t4 = self.interface.now(); //text
 
             self.update_debug_info(
                 [13i16, 10].into(),
@@ -689,14 +803,14 @@ pub mod renderer {
                 BACKGROUND_COLOR,
             );
 
-            let t5 = self.interface.time_micros(); //update screen
+            let t5 = self.interface.now(); //update screen
 
             if let Some(debug) = &mut self.debug {
                 debug.render_times = Option::Some(super::RenderTimes {
-                    background_time: t1.abs_diff(t2),
-                    board_time: t3.abs_diff(t4),
-                    pieces_time: t2.abs_diff(t3),
-                    text_time: t4.abs_diff(t5),
+                    background_time: super::micros(t1, t2),
+                    board_time: super::micros(t3, t4),
+                    pieces_time: super::micros(t2, t3),
+                    text_time: super::micros(t4, t5),
                 });
             }
         }
@@ -793,6 +907,11 @@ pub mod renderer {
                 draw_string("Pieces       us", pos, forground, background);
                 pos.y += 1;
                 draw_string("Text         us", pos, forground, background);
+                pos.x -= 1;
+                pos.y += 1;
+                draw_string("Heap         KB", pos, forground, background);
+                pos.y += 1;
+                draw_string("Allocs", pos, forground, background);
             }
             self.update_debug_info(pos, forground, background);
         }
@@ -801,6 +920,7 @@ pub mod renderer {
             if self.debug.is_none() {
                 return;
             }
+            self.display_heap_usage(pos + [0i16, 11].into(), forground, background);
             {
                 let mut pos = pos + [12i16, 1].into();
                 display_number(self.interface.fps(), pos, 5, forground, background);
@@ -938,6 +1058,42 @@ pub mod renderer {
             }
         }
 
+        fn display_heap_usage(&mut self, mut pos: Coord, forground: Color, background: Color) {
+            let stats = crate::ALLOCATOR.stats();
+            display_number(
+                (stats.used_bytes / 1024) as u32,
+                pos + [12i16, 0].into(),
+                5,
+                forground,
+                background,
+            );
+            if stats.heap_bytes >= 1024 {
+                display_percentage::<2>(
+                    (stats.used_bytes / 1024) as u32,
+                    (stats.heap_bytes / 1024) as u32,
+                    pos + [15i16, 0].into(),
+                    forground,
+                    background,
+                );
+            }
+            pos.y += 1;
+            display_number(
+                stats.allocations as u32,
+                pos + [12i16, 0].into(),
+                5,
+                forground,
+                background,
+            );
+            // how much of the free memory is split off from the largest block
+            display_percentage::<2>(
+                stats.fragmentation_percent(),
+                100,
+                pos + [15i16, 0].into(),
+                forground,
+                background,
+            );
+        }
+
         fn display_cpu_usage(&mut self, mut pos: Coord, forground: Color, background: Color) {
             let usage = self.interface.cpu_usage() as u32;
             draw_tiled_character(pos, 5, forground, background);
@@ -955,129 +1111,185 @@ pub mod renderer {
 }
 
 mod input {
-    use crate::tetris::InterfaceTrait;
+    use interface::input::{Gamepad, GamepadButton};
 
     use super::Tetris;
 
     pub struct TetrisInput {
-        up: KeyState,
-        left: KeyState,
-        right: KeyState,
-        down: KeyState,
-        drop_down: KeyState,
-        save: KeyState,
-    }
-
-    struct KeyState {
-        key_down: bool,
-        frames_down: usize,
-        key_pressed: bool,
-    }
-
-    impl KeyState {
-        fn new() -> Self {
-            Self {
-                key_down: false,
-                frames_down: 0,
-                key_pressed: true,
-            }
-        }
-
-        fn update(&mut self, down: bool) {
-            self.key_pressed = false;
-
-            match (down, self.key_down) {
-                (true, true) => {
-                    self.frames_down += 1;
-                }
-                (true, false) => {
-                    self.key_down = true;
-                    self.frames_down = 0;
-                    self.key_pressed = true;
-                }
-                (false, true) => {
-                    self.key_down = false;
-                    self.frames_down = 0;
-                }
-                (false, false) => {}
-            }
-        }
+        gamepad: Gamepad,
     }
 
     impl TetrisInput {
         pub fn init() -> Self {
             Self {
-                up: KeyState::new(),
-                left: KeyState::new(),
-                right: KeyState::new(),
-                down: KeyState::new(),
-                drop_down: KeyState::new(),
-                save: KeyState::new(),
+                gamepad: Gamepad::new(0),
             }
         }
 
         pub fn up_pressed(&self) -> bool {
-            self.up.key_pressed
+            self.gamepad.just_pressed(GamepadButton::DPadUp)
         }
         pub fn left_pressed(&self) -> bool {
-            self.left.key_pressed
+            self.gamepad.auto_repeat(GamepadButton::DPadLeft, 20, 8)
         }
         pub fn down_pressed(&self) -> bool {
-            self.down.key_pressed
+            self.gamepad.is_down(GamepadButton::DPadDown)
         }
         pub fn right_pressed(&self) -> bool {
-            self.right.key_pressed
+            self.gamepad.auto_repeat(GamepadButton::DPadRight, 20, 8)
         }
         #[allow(unused)]
         pub fn save_pressed(&self) -> bool {
-            self.save.key_pressed
+            self.gamepad.just_pressed(GamepadButton::X)
         }
         pub fn drop_down_pressed(&self) -> bool {
-            self.drop_down.key_pressed
+            self.gamepad.just_pressed(GamepadButton::A)
+        }
+        pub fn quit_pressed(&self) -> bool {
+            self.gamepad.is_down(GamepadButton::Back)
         }
     }
 
     impl Tetris {
         pub fn update_input(&mut self) {
-            self.input
-                .up
-                .update(self.interface.key_down('w') | self.interface.key_down('\x26'));
-            self.input
-                .left
-                .update(self.interface.key_down('a') | self.interface.key_down('\x25'));
-            self.input
-                .down
-                .update(self.interface.key_down('s') | self.interface.key_down('\x28'));
-            self.input
-                .right
-                .update(self.interface.key_down('d') | self.interface.key_down('\x27'));
-            self.input.save.update(self.interface.key_down('c'));
-            self.input.drop_down.update(self.interface.key_down(' '));
-
-            self.input.down.key_pressed = self.input.down.key_down;
-
-            if self.input.left.frames_down > 20 {
-                self.input.left.key_pressed = self.input.left.frames_down % 8 == 0;
-            }
-            if self.input.right.frames_down > 20 {
-                self.input.right.key_pressed = self.input.right.frames_down % 8 == 0;
-            }
+            self.input.gamepad.update();
         }
     }
 }
 
 mod sound {
+    use interface::audio::{
+        music::{MusicPlayer, Song},
+        sfx::{self, Effect, SfxMixer, Step},
+        Duty,
+    };
+
     use super::Tetris;
 
-    pub struct TetrisSound {}
+    static SONG: &[u8] = include_bytes!(concat!(env!("OUT_DIR"), "/tetris_gb.song"));
+    const THEME_TRACK: usize = 0;
+
+    /// Noise LFSR rate for a low thud.
+    const THUD: u32 = 7046 << 8;
+
+    static MOVE: Effect = Effect {
+        steps: &[Step::note(2, 84, 5).with_duty(Duty::Eighth)],
+        channels: sfx::PULSE,
+        priority: 1,
+    };
+    static ROTATE: Effect = Effect {
+        steps: &[
+            Step::note(2, 79, 7).with_duty(Duty::Quarter),
+            Step::note(2, 86, 7).with_duty(Duty::Quarter),
+        ],
+        channels: sfx::PULSE,
+        priority: 2,
+    };
+    static LOCK: Effect = Effect {
+        steps: &[Step::tone(8, THUD, 10).with_volume_slide(-20)],
+        channels: sfx::NOISE,
+        priority: 3,
+    };
+    static HARD_DROP: Effect = Effect {
+        steps: &[Step::tone(12, THUD >> 1, 15).with_volume_slide(-20)],
+        channels: sfx::NOISE,
+        priority: 4,
+    };
+    static LINE_CLEAR: Effect = Effect {
+        steps: &[
+            Step::note(3, 72, 12),
+            Step::note(3, 76, 12),
+            Step::note(3, 79, 12),
+            Step::note(12, 84, 12).with_volume_slide(-16),
+        ],
+        channels: sfx::PULSE,
+        priority: 5,
+    };
+    static TETRIS: Effect = Effect {
+        steps: &[
+            Step::note(3, 72, 14),
+            Step::note(3, 79, 14),
+            Step::note(3, 84, 14),
+            Step::note(3, 76, 14),
+            Step::note(3, 83, 14),
+            Step::note(3, 88, 14),
+            Step::note(20, 96, 14).with_volume_slide(-11),
+        ],
+        channels: sfx::PULSE,
+        priority: 6,
+    };
+    static LEVEL_UP: Effect = Effect {
+        steps: &[
+            Step::note(4, 79, 12).with_duty(Duty::Quarter),
+            Step::rest(2),
+            Step::note(4, 79, 12).with_duty(Duty::Quarter),
+            Step::note(4, 84, 12).with_duty(Duty::Quarter),
+            Step::note(24, 91, 12)
+                .with_duty(Duty::Quarter)
+                .with_volume_slide(-8),
+        ],
+        channels: sfx::PULSE,
+        priority: 7,
+    };
+    static GAME_OVER: Effect = Effect {
+        steps: &[Step::note(90, 67, 15)
+            .with_volume_slide(-2)
+            .with_pitch_slide(-(1 << 8))],
+        channels: sfx::PULSE,
+        priority: 8,
+    };
+
+    pub struct TetrisSound {
+        music: MusicPlayer<'static>,
+        sfx: SfxMixer,
+    }
 
     impl TetrisSound {
         pub fn init() -> Self {
-            Self {}
+            let song = Song::parse(SONG).expect("tetris_gb.song is generated by build.rs");
+            let mut music = MusicPlayer::new(song);
+            let _ = music.play(THEME_TRACK);
+            Self {
+                music,
+                sfx: SfxMixer::new(),
+            }
+        }
+    }
+
+    impl Drop for TetrisSound {
+        fn drop(&mut self) {
+            self.sfx.stop_all();
+            self.music.stop();
         }
     }
 
     impl Tetris {
-        pub fn update_sound(&self) {}
+        /// Plays effects for the events of the last game update, then advances the music.
+        pub fn update_sound(&mut self) {
+            let events = self.game.events;
+            let sound = &mut self.sound;
+            if events.game_over {
+                sound.sfx.play(&GAME_OVER);
+            } else if events.level_up {
+                sound.sfx.play(&LEVEL_UP);
+            } else if events.lines_cleared >= 4 {
+                sound.sfx.play(&TETRIS);
+            } else if events.lines_cleared > 0 {
+                sound.sfx.play(&LINE_CLEAR);
+            }
+            if events.hard_dropped {
+                sound.sfx.play(&HARD_DROP);
+            } else if events.locked {
+                sound.sfx.play(&LOCK);
+            }
+            if events.rotated {
+                sound.sfx.play(&ROTATE);
+            } else if events.moved {
+                sound.sfx.play(&MOVE);
+            }
+
+            sound.sfx.tick(Some(&mut sound.music));
+            sound.music.tick();
+        }
     }
 }
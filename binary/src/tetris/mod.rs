@@ -1,4 +1,4 @@
-use crate::util::display::Color;
+use interface::{color::Color, time::Instant};
 
 pub use logic::*;
 mod logic;
@@ -11,11 +11,14 @@ pub fn run_tetris() {
     let mut tetris = logic::Tetris::new(interface);
 
     loop {
-        if tetris.run_frame() {
-            tetris.interface.update_screen();
-        } else {
-            break;
+        // the game only moves in whole steps, so `alpha` has nothing to draw in between
+        for _ in 0..tetris.interface.steps() {
+            if !tetris.update() {
+                return;
+            }
         }
+        tetris.render();
+        tetris.interface.update_screen();
     }
 }
 trait InterfaceTrait {
@@ -27,84 +30,68 @@ trait InterfaceTrait {
     fn key_down(&mut self, key: char) -> bool;
     fn cpu_usage(&mut self) -> u32;
     fn fps(&mut self) -> u32;
-    fn time_micros(&mut self) -> u64;
+    fn now(&mut self) -> Instant;
 }
 
 pub mod platform {
-    use crate::util::display::Color;
+    use interface::{
+        color::Color,
+        time::{FixedTimestep, Instant},
+    };
 
     use super::InterfaceTrait;
 
     pub struct Interface {
-        cpu_time_start: Option<u64>,
-        index: usize,
-        cpu_usage: [u32; 64],
-        fps: [u32; 64],
+        timestep: FixedTimestep,
     }
-    impl InterfaceTrait for Interface {
-        fn update_screen(&mut self) {
-            interface::sys::update_screen();
-            let start = self.time_micros();
 
-            if !self.key_down('e') {
-                interface::sys::sleep_delta_mills(17);
+    impl Interface {
+        /// Frames the game is behind, holding `e` fast forwards a frame every time.
+        pub fn steps(&mut self) -> u32 {
+            let steps = self.timestep.advance();
+            if self.key_down('e') {
+                steps.max(1)
+            } else {
+                steps
             }
+        }
+    }
 
-            let end = self.time_micros();
-
-            let diff = (end.wrapping_sub(start) as i64) as u32;
-            if let Option::Some(start_stuff) = self.cpu_time_start {
-                let sleep_time = diff;
-                let cpu_time = (start.wrapping_sub(start_stuff) as i32) as u32;
-                self.cpu_usage[self.index] =
-                    (((cpu_time as u64) * 10000) / (sleep_time as u64 + cpu_time as u64)) as u32;
-                self.fps[self.index] = sleep_time + cpu_time;
-                self.index += 1;
-                self.index %= 64;
+    impl InterfaceTrait for Interface {
+        fn update_screen(&mut self) {
+            let _ = interface::sys::update_screen();
+            if !self.key_down('e') {
+                self.timestep.sleep();
             }
-            self.cpu_time_start = Option::Some(end);
         }
         fn set_pixel(&mut self, x: usize, y: usize, color: Color) {
-            interface::sys::set_pixel_coords(x as u32, y as u32, color.into());
+            // pixels off the screen are clipped
+            let _ = interface::sys::set_pixel_coords(x as u32, y as u32, color.into());
         }
         fn clear_screen(&mut self, color: Color) {
-            interface::sys::fill_screen(color.into());
+            let _ = interface::sys::fill_screen(color.into());
         }
         fn key_down(&mut self, key: char) -> bool {
             interface::sys::is_key_pressed(key)
         }
         fn initialize_screen(&mut self, height: u32, width: u32) {
-            interface::sys::init_screen(width, height)
+            interface::sys::init_screen(width, height).expect("screen size rejected by the host")
         }
         fn cpu_usage(&mut self) -> u32 {
-            //interface::println!("{:?}", self.stuff2);
-            let mut sum = 0;
-            for item in self.cpu_usage {
-                sum += item;
-            }
-            (sum + 1) / self.cpu_usage.len() as u32
+            self.timestep.stats().cpu_usage()
         }
         fn new() -> Self {
             Self {
-                cpu_time_start: Option::None,
-                index: 0,
-                cpu_usage: [0; 64],
-                fps: [0; 64],
+                timestep: FixedTimestep::from_hz(60),
             }
         }
 
-        fn time_micros(&mut self) -> u64 {
-            interface::sys::get_nanos()
+        fn now(&mut self) -> Instant {
+            Instant::now()
         }
 
         fn fps(&mut self) -> u32 {
-            let mut sum = 0;
-            for item in self.fps {
-                sum += item / 1000;
-            }
-            10000000u32
-                .checked_div((sum) / self.fps.len() as u32)
-                .unwrap_or_default()
+            self.timestep.stats().fps()
         }
     }
 }
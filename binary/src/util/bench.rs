@@ -0,0 +1,59 @@
+//! Times the memory functions against a byte at a time loop, `m` in the menu runs it and logs
+//! microseconds per size and alignment.
+
+use alloc::vec;
+use interface::{black_box, log, sys};
+
+const ROUNDS: u32 = 32;
+const SIZES: [usize; 3] = [64, 1024, 16 * 1024];
+/// Destination and source offsets, the same alignment and a different one.
+const OFFSETS: [(usize, usize); 2] = [(0, 0), (1, 3)];
+
+fn time(mut f: impl FnMut()) -> u64 {
+    let start = sys::get_micros();
+    for _ in 0..ROUNDS {
+        f();
+    }
+    sys::get_micros() - start
+}
+
+/// What the compiler used to get for every copy, volatile so it can't be turned into a
+/// `memcpy` call.
+fn copy_bytes(dest: &mut [u8], src: &[u8]) {
+    for (dest, src) in dest.iter_mut().zip(src) {
+        unsafe { core::ptr::write_volatile(dest, core::ptr::read_volatile(src)) };
+    }
+}
+
+pub fn run_memory_benchmark() {
+    let max = SIZES[SIZES.len() - 1] + 4;
+    let mut src = vec![0u8; max];
+    let mut dest = vec![0u8; max];
+    for (i, byte) in src.iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+
+    log::info!("memory benchmark, {} rounds each", ROUNDS);
+    for size in SIZES {
+        for (dest_at, src_at) in OFFSETS {
+            let src = &src[src_at..src_at + size];
+            let dest = &mut dest[dest_at..dest_at + size];
+            let bytes = time(|| copy_bytes(dest, src));
+            let copy = time(|| black_box(&mut *dest).copy_from_slice(black_box(src)));
+            let compare = time(|| _ = black_box(black_box(&*dest) == black_box(src)));
+            let moved = time(|| black_box(&mut *dest).copy_within(1.., 0));
+            let set = time(|| black_box(&mut *dest).fill(0x5a));
+            log::info!(
+                "{} +{}/+{}: bytes {} memcpy {} memcmp {} memmove {} memset {} us",
+                size,
+                dest_at,
+                src_at,
+                bytes,
+                copy,
+                compare,
+                moved,
+                set
+            );
+        }
+    }
+}
@@ -1,56 +1,4 @@
-#[allow(dead_code)]
-#[derive(Copy, Clone, Debug)]
-pub struct Color([u8; 4]);
-
-impl Color {
-    #[inline(always)]
-    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
-        Self([r, g, b, 255])
-    }
-    #[inline(always)]
-    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
-        Self([r, g, b, a])
-    }
-
-    #[inline(always)]
-    pub const fn from_rgb_additive(r: u8, g: u8, b: u8) -> Self {
-        Self([r, g, b, 0])
-    }
-
-    #[inline(always)]
-    pub const fn clear() -> Self {
-        Self([0, 0, 0, 0])
-    }
-
-    pub fn get_inner(&self) -> &[u8; 4] {
-        &self.0
-    }
-
-    #[inline(always)]
-    pub fn is_opaque(&self) -> bool {
-        self.0[3] == 255
-    }
-    #[inline(always)]
-    pub fn get_alpha(&self) -> u8 {
-        self.0[3]
-    }
-}
-
-impl From<Color> for u32 {
-    fn from(color: Color) -> Self {
-        (color.0[0] as u32) | ((color.0[1] as u32) << 8) | ((color.0[2] as u32) << 16)
-    }
-}
-
-impl From<u32> for Color {
-    fn from(color: u32) -> Self {
-        Color::from_rgb(
-            (color & 255) as u8,
-            ((color >> 8) & 255) as u8,
-            ((color >> 16) & 255) as u8,
-        )
-    }
-}
+use interface::color::Color;
 
 pub fn display_number(
     mut num: u32,
@@ -136,6 +84,113 @@ pub fn draw_string(
     }
 }
 
+/// Draws the lines of `log` from the tile at `location` downwards, oldest first. Errors and
+/// warnings are colored, characters missing from the tile set are left blank.
+pub fn draw_log_overlay<const LINES: usize, const WIDTH: usize>(
+    log: &interface::log::MemorySink<LINES, WIDTH>,
+    location: impl Into<[u32; 2]>,
+) {
+    use interface::log::Level;
+
+    let mut location = location.into();
+    log.for_each_line(|level, line| {
+        let forground = match level {
+            Level::Error => Color::from_rgb(255, 80, 80),
+            Level::Warn => Color::from_rgb(255, 200, 60),
+            _ => Color::from_rgb(200, 200, 200),
+        };
+        for (i, char) in line.chars().enumerate() {
+            // the tile set is printable ascii starting at the space
+            if let ' '..='~' = char {
+                draw_tiled_character(
+                    [location[0] + i as u32, location[1]],
+                    char as u32 - ' ' as u32,
+                    forground,
+                    Color::clear(),
+                );
+            }
+        }
+        location[1] += 1;
+    });
+}
+
+/// Writes text into the tile grid from `location`, wrapping at `columns` and dropping
+/// everything past `rows`. Characters missing from the tile set are left blank.
+pub struct TileWriter {
+    location: [u32; 2],
+    columns: u32,
+    rows: u32,
+    column: u32,
+    row: u32,
+    pub forground: Color,
+    pub background: Color,
+}
+
+impl TileWriter {
+    pub fn new(location: impl Into<[u32; 2]>, columns: u32, rows: u32, forground: Color) -> Self {
+        Self {
+            location: location.into(),
+            columns,
+            rows,
+            column: 0,
+            row: 0,
+            forground,
+            background: Color::clear(),
+        }
+    }
+
+    pub fn new_line(&mut self) {
+        self.column = 0;
+        self.row += 1;
+    }
+}
+
+impl core::fmt::Write for TileWriter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        for char in s.chars() {
+            if char == '\n' {
+                self.new_line();
+                continue;
+            }
+            if self.column == self.columns {
+                self.new_line();
+            }
+            if self.row < self.rows {
+                if let ' '..='~' = char {
+                    draw_tiled_character(
+                        [self.location[0] + self.column, self.location[1] + self.row],
+                        char as u32 - ' ' as u32,
+                        self.forground,
+                        self.background,
+                    );
+                }
+            }
+            self.column += 1;
+        }
+        Ok(())
+    }
+}
+
+/// Covers the screen with `report`, for the panic handler. The screen has to be `width` by
+/// `height` pixels already.
+pub fn draw_crash_screen(report: &interface::panic::Report, width: u32, height: u32) {
+    use core::fmt::Write;
+
+    let _ = interface::sys::fill_screen(Color::from_rgb(90, 0, 0).into());
+    let mut writer = TileWriter::new(
+        [0, 0],
+        width / 8,
+        height / 8,
+        Color::from_rgb(255, 255, 255),
+    );
+    let _ = writeln!(writer, "{}", report.info);
+    writer.forground = Color::from_rgb(255, 200, 60);
+    let _ = writeln!(writer, "{}", report.registers);
+    writer.forground = Color::from_rgb(200, 200, 200);
+    let _ = write!(writer, "{}", report.backtrace);
+    let _ = interface::sys::update_screen();
+}
+
 const CHACATER_SET: &[u8; 768] = include_bytes!("../../res/character-tile-set.comp");
 
 pub fn draw_tiled_character(
@@ -183,7 +238,7 @@ pub fn draw_chacater(
             if color.is_opaque() {
                 //interface::sys::old_breakpoint();
                 //unsafe { interface::sys::syscall_1_0::<0xFF>(color.into());}
-                interface::sys::set_pixel_coords(
+                let _ = interface::sys::set_pixel_coords(
                     x + (location[0]),
                     (y as u32) + (location[1]),
                     color.into(),
@@ -207,22 +262,22 @@ pub fn draw_chacater_x2(location: [u32; 2], char: u32, forground: Color, backgro
                     color = background;
                 }
                 if color.is_opaque() {
-                    interface::sys::set_pixel_coords(
+                    let _ = interface::sys::set_pixel_coords(
                         ($x << 1) + (location[0]),
                         ((y as u32) << 1) + (location[1]),
                         color.into(),
                     );
-                    interface::sys::set_pixel_coords(
+                    let _ = interface::sys::set_pixel_coords(
                         ($x << 1) + 1 + (location[0]),
                         ((y as u32) << 1) + (location[1]),
                         color.into(),
                     );
-                    interface::sys::set_pixel_coords(
+                    let _ = interface::sys::set_pixel_coords(
                         ($x << 1) + (location[0]),
                         1 + ((y as u32) << 1) + (location[1]),
                         color.into(),
                     );
-                    interface::sys::set_pixel_coords(
+                    let _ = interface::sys::set_pixel_coords(
                         ($x << 1) + 1 + (location[0]),
                         1 + ((y as u32) << 1) + (location[1]),
                         color.into(),
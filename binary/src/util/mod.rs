@@ -1 +1,3 @@
+pub mod bench;
 pub mod display;
+pub mod tasks;
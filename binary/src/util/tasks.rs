@@ -0,0 +1,63 @@
+//! Switches between a few tasks to check that `interface::task` works on the emulator, `t` in the
+//! menu runs it and logs what happened.
+
+use alloc::{rc::Rc, vec::Vec};
+use core::cell::RefCell;
+
+use interface::{
+    log,
+    task::{self, TaskId},
+    time::{Duration, Instant},
+};
+
+const STEPS: u32 = 3;
+const SLEEP: Duration = Duration::from_millis(5);
+
+pub fn run_task_smoke_test() {
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let spawn = |name: &'static str| {
+        let order = order.clone();
+        task::Builder::new()
+            .name(name)
+            .stack_size(4 * 1024)
+            .spawn(move || {
+                for step in 0..STEPS {
+                    order.borrow_mut().push((name, step));
+                    task::yield_now();
+                }
+                task::current()
+            })
+    };
+    let a = spawn("a");
+    let b = spawn("b");
+    let sleeper = task::spawn(|| {
+        let start = Instant::now();
+        task::sleep(SLEEP);
+        start.elapsed()
+    });
+
+    let ids = (a.id(), b.id());
+    let ran_as = (a.join(), b.join());
+    let slept = sleeper.join();
+
+    let order = order.borrow();
+    // round robin, `a` and `b` take turns
+    let interleaved =
+        order.len() == 2 * STEPS as usize && order.windows(2).all(|pair| pair[0].0 != pair[1].0);
+    let ok = interleaved && ran_as == ids && slept >= SLEEP && task::current() == TaskId::MAIN;
+    if ok {
+        log::info!(
+            "tasks: ok, slept {} us, {} left",
+            slept.as_micros(),
+            task::count()
+        );
+    } else {
+        log::warn!(
+            "tasks: failed, order {:?}, ran as {:?} expected {:?}, slept {} us",
+            order,
+            ran_as,
+            ids,
+            slept.as_micros()
+        );
+    }
+}
@@ -1,4 +1,7 @@
 cargo build
 mkdir -p ./mips/bin
 cp ./target/mips/debug/binary ./mips/bin/com.o
+# again, now that build.rs can take the symbol table for backtraces from com.o
+cargo build
+cp ./target/mips/debug/binary ./mips/bin/com.o
 mips-linux-gnu-objcopy -O binary -I elf32-tradbigmips ./mips/bin/com.o ./mips/bin/tmp.bin
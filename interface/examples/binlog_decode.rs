@@ -0,0 +1,52 @@
+//! Prints the messages in a `binlog` frame log, decoded against the guest ELF.
+//!
+//! The repo's cargo config builds everything for mips, so run this from outside of it:
+//!
+//! ```text
+//! cargo run --manifest-path interface/Cargo.toml --features host --example binlog_decode -- \
+//!     mips/bin/com.o frames.bin
+//! ```
+//!
+//! Frames are read from stdin when no frame log is given.
+
+use std::{
+    fs::File,
+    io::{self, BufReader, Read},
+    process::ExitCode,
+};
+
+use interface::host::binlog::{read_frame, Table};
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let (elf, frames) = match args.as_slice() {
+        [elf] => (elf, None),
+        [elf, frames] => (elf, Some(frames)),
+        _ => {
+            eprintln!("usage: binlog_decode <elf> [frame log]");
+            return ExitCode::FAILURE;
+        }
+    };
+    match run(elf, frames) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("binlog_decode: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(elf: &str, frames: Option<&String>) -> Result<(), Box<dyn std::error::Error>> {
+    let table = Table::from_elf(&std::fs::read(elf)?)?;
+    let mut input: Box<dyn Read> = match frames {
+        Some(path) => Box::new(BufReader::new(File::open(path)?)),
+        None => Box::new(io::stdin().lock()),
+    };
+    while let Some(frame) = read_frame(&mut input)? {
+        match table.decode(&frame) {
+            Ok(message) => println!("{}", message),
+            Err(err) => println!("<{}>", err),
+        }
+    }
+    Ok(())
+}
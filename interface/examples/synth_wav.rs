@@ -0,0 +1,78 @@
+//! Renders each tone channel and a PCM sweep through `host::audio::Synth` into a `.wav`, to hear
+//! what the guest's audio sounds like without the emulator.
+//!
+//! The repo's cargo config builds everything for mips, so run this from outside of it:
+//!
+//! ```text
+//! cargo run --manifest-path interface/Cargo.toml --features host --example synth_wav -- out.wav
+//! ```
+
+use std::{fs::File, io::BufWriter, process::ExitCode};
+
+use interface::{
+    audio::{note_frequency, Channel, ChannelRegs, Duty, OUTPUT_SAMPLE_RATE},
+    host::audio::{Synth, WavWriter},
+};
+
+/// Half a second.
+const PART: usize = OUTPUT_SAMPLE_RATE as usize / 2;
+
+fn main() -> ExitCode {
+    let Some(path) = std::env::args().nth(1) else {
+        eprintln!("usage: synth_wav <out.wav>");
+        return ExitCode::FAILURE;
+    };
+    if let Err(err) = run(&path) {
+        eprintln!("{path}: {err}");
+        return ExitCode::FAILURE;
+    }
+    ExitCode::SUCCESS
+}
+
+fn run(path: &str) -> std::io::Result<()> {
+    let mut wav = WavWriter::new(BufWriter::new(File::create(path)?), OUTPUT_SAMPLE_RATE, 1)?;
+    let mut synth = Synth::new();
+    let mut buf = vec![0; PART];
+
+    let a4 = note_frequency(69);
+    let mut wave = [0; 32];
+    for (i, step) in wave.iter_mut().enumerate() {
+        *step = (i / 2) as u8;
+    }
+    synth.set_wave(&wave);
+    let parts = [
+        (
+            Channel::Pulse1,
+            ChannelRegs::tone(a4, 12).with_duty(Duty::Eighth),
+        ),
+        (Channel::Pulse2, ChannelRegs::tone(a4, 12).with_sweep(4, -3)),
+        (Channel::Triangle, ChannelRegs::tone(a4 / 2, 15)),
+        (Channel::Noise, ChannelRegs::tone(4000 << 8, 10)),
+        (
+            Channel::Noise,
+            ChannelRegs::tone(4000 << 8, 10).with_short_noise(true),
+        ),
+        (Channel::Wave, ChannelRegs::tone(a4, 15)),
+    ];
+    for (channel, regs) in parts {
+        synth.write_channel(channel as u32, regs);
+        synth.render(&mut buf);
+        wav.write_samples(&buf)?;
+        synth.write_channel(channel as u32, ChannelRegs::OFF);
+    }
+
+    // a rising tone at 8 kHz, resampled to the output rate
+    synth.pcm_configure(8000, 1);
+    let pcm: Vec<i16> = (0..4000)
+        .map(|i| {
+            let t = i as f32 / 8000.0;
+            ((t * (200.0 + 800.0 * t) * std::f32::consts::TAU).sin() * 8000.0) as i16
+        })
+        .collect();
+    synth.pcm_write(&pcm);
+    synth.render(&mut buf);
+    wav.write_samples(&buf)?;
+
+    wav.finish()?;
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Regenerates the syscall reference, `interface/syscalls.md` and `interface/syscalls.json`, from
+//! the table in `interface::sys`.
+//!
+//! The repo's cargo config builds everything for mips, so run this from outside of it:
+//!
+//! ```text
+//! cargo run --manifest-path interface/Cargo.toml --features host --example syscall_reference
+//! ```
+//!
+//! The `syscalls` host test fails while the committed files are out of date.
+
+use std::{fs, io, path::Path};
+
+use interface::sys::{
+    table::{write_json, write_markdown},
+    SYSCALLS,
+};
+
+fn main() -> io::Result<()> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+
+    let mut markdown = String::new();
+    write_markdown(&mut markdown, SYSCALLS).expect("writing to a String can't fail");
+    fs::write(dir.join("syscalls.md"), markdown)?;
+
+    let mut json = String::new();
+    write_json(&mut json, SYSCALLS).expect("writing to a String can't fail");
+    fs::write(dir.join("syscalls.json"), json)
+}
@@ -0,0 +1,220 @@
+pub mod music;
+pub mod sfx;
+
+/// Sample rate the host mixes the tone channels at, PCM streams are resampled to this.
+pub const OUTPUT_SAMPLE_RATE: u32 = 44100;
+
+/// Sweep units are clocked at this rate, see [`ChannelRegs::sweep_period`].
+pub const SWEEP_RATE: u32 = 128;
+
+pub const NUM_CHANNELS: usize = 5;
+
+/// The programmable tone channels. Every channel has the same register layout, registers that
+/// don't apply to a channel (duty on the noise channel..) are ignored.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Channel {
+    Pulse1 = 0,
+    Pulse2 = 1,
+    Triangle = 2,
+    Noise = 3,
+    /// Plays the 32 step table set with [`set_wave`].
+    Wave = 4,
+}
+
+impl Channel {
+    pub const ALL: [Channel; NUM_CHANNELS] = [
+        Channel::Pulse1,
+        Channel::Pulse2,
+        Channel::Triangle,
+        Channel::Noise,
+        Channel::Wave,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+}
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Duty {
+    Eighth = 0,
+    Quarter = 1,
+    #[default]
+    Half = 2,
+    ThreeQuarters = 3,
+}
+
+/// Register block for one channel, the host copies it out on every [`write_channel`].
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ChannelRegs {
+    /// Frequency in Hz with 8 fractional bits (`440 << 8` is A4). For the noise channel this is
+    /// the rate the LFSR is clocked at.
+    pub frequency: u32,
+    /// `0..=15`, `0` is silent.
+    pub volume: u8,
+    /// A [`Duty`], pulse channels only.
+    pub duty: u8,
+    /// Sweep steps happen every `sweep_period` ticks of [`SWEEP_RATE`], `0` disables the sweep.
+    pub sweep_period: u8,
+    /// Each sweep step moves the frequency by `frequency >> sweep_shift`.
+    pub sweep_shift: u8,
+    /// Non zero sweeps down instead of up.
+    pub sweep_negate: u8,
+    /// Noise channel only, non zero selects the short (7 bit) LFSR for metallic tones.
+    pub mode: u8,
+    pub enabled: u8,
+    pub(crate) _reserved: u8,
+}
+
+impl ChannelRegs {
+    pub const OFF: Self = Self {
+        frequency: 0,
+        volume: 0,
+        duty: 0,
+        sweep_period: 0,
+        sweep_shift: 0,
+        sweep_negate: 0,
+        mode: 0,
+        enabled: 0,
+        _reserved: 0,
+    };
+
+    /// An enabled channel playing `frequency` (8 fractional bits) at `volume`.
+    pub const fn tone(frequency: u32, volume: u8) -> Self {
+        Self {
+            frequency,
+            volume,
+            enabled: 1,
+            ..Self::OFF
+        }
+    }
+
+    pub const fn with_duty(mut self, duty: Duty) -> Self {
+        self.duty = duty as u8;
+        self
+    }
+
+    /// Negative `shift` sweeps down.
+    pub const fn with_sweep(mut self, period: u8, shift: i8) -> Self {
+        self.sweep_period = period;
+        self.sweep_shift = shift.unsigned_abs();
+        self.sweep_negate = (shift < 0) as u8;
+        self
+    }
+
+    pub const fn with_short_noise(mut self, short: bool) -> Self {
+        self.mode = short as u8;
+        self
+    }
+
+    #[inline(always)]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled != 0 && self.volume != 0
+    }
+}
+
+/// Sets all registers of `channel`. The sweep restarts from `regs.frequency`.
+#[inline(always)]
+pub fn write_channel(channel: Channel, regs: &ChannelRegs) {
+    // can't fail, `Channel` only holds valid channels
+    let _ = crate::sys::audio_write_channel(channel as u32, regs);
+}
+
+/// Silences a channel.
+#[inline(always)]
+pub fn stop_channel(channel: Channel) {
+    write_channel(channel, &ChannelRegs::OFF);
+}
+
+pub fn stop_all() {
+    for channel in Channel::ALL {
+        stop_channel(channel);
+    }
+}
+
+/// Sets the wave channel table, 32 samples of `0..=15`.
+#[inline(always)]
+pub fn set_wave(table: &[u8; 32]) {
+    crate::sys::audio_set_wave(table);
+}
+
+/// `0..=15`, applied after all channels and the PCM stream are mixed.
+#[inline(always)]
+pub fn set_master_volume(volume: u8) {
+    crate::sys::audio_set_master_volume(volume as u32);
+}
+
+/// Frequency register value for a MIDI style note number, `69` is A4 at 440Hz.
+pub const fn note_frequency(note: u8) -> u32 {
+    // A4 octave, 8 fractional bits, semitones above A
+    const OCTAVE: [u32; 12] = [
+        112640, 119338, 126434, 133952, 141918, 150356, 159297, 168769, 178805, 189437, 200702,
+        212636,
+    ];
+    let semis = note as i32 - 69;
+    let octave = semis.div_euclid(12);
+    let base = OCTAVE[semis.rem_euclid(12) as usize];
+    if octave >= 0 {
+        base << octave
+    } else {
+        base >> -octave
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Signed 16 bit samples pushed into the host ring buffer. The host mixes the stream in on top
+/// of the tone channels, interleaved left/right when `channels == 2`.
+pub struct PcmStream {
+    sample_rate: u32,
+    channels: u32,
+}
+
+impl PcmStream {
+    /// Configures the host side stream, dropping anything still queued from before.
+    /// [`crate::sys::SysError::InvalidArgument`] unless `channels` is 1 or 2 and `sample_rate`
+    /// isn't zero.
+    pub fn open(sample_rate: u32, channels: u32) -> crate::sys::SysResult<Self> {
+        crate::sys::audio_pcm_configure(sample_rate, channels)?;
+        Ok(Self {
+            sample_rate,
+            channels,
+        })
+    }
+
+    /// Queues as many samples as fit and returns how many were taken.
+    #[inline(always)]
+    pub fn write(&mut self, samples: &[i16]) -> usize {
+        crate::sys::audio_pcm_write(samples) as usize
+    }
+
+    /// Queues all of `samples`, sleeping while the ring buffer is full.
+    pub fn write_all(&mut self, mut samples: &[i16]) {
+        while !samples.is_empty() {
+            let written = self.write(samples);
+            samples = &samples[written..];
+            if !samples.is_empty() {
+                crate::sys::sleep_mills(1);
+            }
+        }
+    }
+
+    /// Free space in the host ring buffer, in samples.
+    #[inline(always)]
+    pub fn available(&self) -> usize {
+        crate::sys::audio_pcm_available() as usize
+    }
+
+    #[inline(always)]
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    #[inline(always)]
+    pub fn channels(&self) -> u32 {
+        self.channels
+    }
+}
@@ -0,0 +1,587 @@
+//! Tick driven player for songs compiled from FamiTracker text exports.
+//!
+//! The songs are produced at build time (see `binary/build.rs`) in the following format, all
+//! multi byte values little endian:
+//!
+//! ```text
+//! "FTS1"
+//! u8 num_channels, u8 target[num_channels]        target is an `audio::Channel` index
+//! u8 num_macros,   { u8 len, u8 loop, u8 release, i8 values[len] }[num_macros]
+//! u8 num_instruments, { u8 volume, u8 arpeggio, u8 pitch, u8 hi_pitch, u8 duty }[..]
+//! u8 num_tracks,   u16 track_offset[num_tracks]
+//!
+//! track:
+//!   u8 speed, u8 tempo, u16 rows, u8 num_orders, u8 num_patterns,
+//!   u16 pattern_offset[num_patterns], u8 order[num_orders][num_channels]
+//! ```
+//!
+//! `loop`, `release` and instrument macro ids use `0xFF` for "none". Offsets are from the start
+//! of the song. Patterns are a byte stream of commands, a row ends with a wait command:
+//!
+//! ```text
+//! 0x00..=0x7F        note on, MIDI note number (noise: period index 0..=15, higher is higher)
+//! 0x80               note cut
+//! 0x81               note release
+//! 0x82 ii            instrument
+//! 0x83 vv            volume column 0..=15
+//! 0x84 ee pp         effect, `ee` is the FamiTracker effect letter: `Bxx` jumps to order `xx`,
+//!                    `Cxx` halts, `Dxx` skips to row `xx` of the next order and `Fxx` sets the
+//!                    speed below 0x20, the tempo from there
+//! 0xC0 | n           end of row, followed by `n` empty rows
+//! 0xFF               end of pattern, the remaining rows are empty
+//! ```
+
+use super::{Channel, ChannelRegs, Duty};
+
+pub const MAGIC: &[u8; 4] = b"FTS1";
+pub const NONE: u8 = 0xFF;
+
+pub const CMD_NOTE_CUT: u8 = 0x80;
+pub const CMD_RELEASE: u8 = 0x81;
+pub const CMD_INSTRUMENT: u8 = 0x82;
+pub const CMD_VOLUME: u8 = 0x83;
+pub const CMD_EFFECT: u8 = 0x84;
+pub const CMD_END_ROW: u8 = 0xC0;
+pub const CMD_END_PATTERN: u8 = 0xFF;
+
+pub const MAX_CHANNELS: usize = super::NUM_CHANNELS;
+
+/// The player is meant to be ticked at this rate, once per frame.
+pub const TICK_RATE: u32 = 60;
+
+/// NES noise periods in CPU cycles, index 0 is the highest pitch.
+const NOISE_PERIODS: [u32; 16] = [
+    4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+];
+const NES_CPU_CLOCK: u32 = 1789773;
+
+/// Order "jumped to" by the `Cxx` effect.
+const HALT: usize = usize::MAX;
+
+/// Where [`MusicPlayer::tick`] and [`MusicPlayer::stop`] send the channels.
+fn write_guest(channel: Channel, regs: &ChannelRegs) {
+    super::write_channel(channel, regs);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SongError {
+    BadMagic,
+    Truncated,
+    NoSuchTrack,
+    TooManyChannels,
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// A compiled song, borrowed from e.g. an `include_bytes!`.
+#[derive(Copy, Clone)]
+pub struct Song<'a> {
+    data: &'a [u8],
+    channels: &'a [u8],
+    macros: &'a [u8],
+    num_macros: u8,
+    instruments: &'a [u8],
+    tracks: &'a [u8],
+}
+
+#[derive(Copy, Clone)]
+struct Macro<'a> {
+    values: &'a [u8],
+    loop_point: u8,
+    release: u8,
+}
+
+#[derive(Copy, Clone)]
+struct Track<'a> {
+    speed: u8,
+    tempo: u8,
+    rows: u16,
+    patterns: &'a [u8],
+    orders: &'a [u8],
+}
+
+fn take<'a>(data: &'a [u8], at: &mut usize, len: usize) -> Result<&'a [u8], SongError> {
+    let slice = data.get(*at..*at + len).ok_or(SongError::Truncated)?;
+    *at += len;
+    Ok(slice)
+}
+
+fn u16_at(data: &[u8], at: usize) -> Result<u16, SongError> {
+    let bytes = data.get(at..at + 2).ok_or(SongError::Truncated)?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+impl<'a> Song<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, SongError> {
+        let mut at = 0;
+        if take(data, &mut at, 4)? != MAGIC {
+            return Err(SongError::BadMagic);
+        }
+        let num_channels = take(data, &mut at, 1)?[0] as usize;
+        if num_channels > MAX_CHANNELS {
+            return Err(SongError::TooManyChannels);
+        }
+        let channels = take(data, &mut at, num_channels)?;
+
+        let num_macros = take(data, &mut at, 1)?[0];
+        let macros_start = at;
+        for _ in 0..num_macros {
+            let len = take(data, &mut at, 1)?[0] as usize;
+            take(data, &mut at, 2 + len)?;
+        }
+        let macros = &data[macros_start..at];
+
+        let num_instruments = take(data, &mut at, 1)?[0] as usize;
+        let instruments = take(data, &mut at, num_instruments * 5)?;
+
+        let num_tracks = take(data, &mut at, 1)?[0] as usize;
+        let tracks = take(data, &mut at, num_tracks * 2)?;
+
+        Ok(Self {
+            data,
+            channels,
+            macros,
+            num_macros,
+            instruments,
+            tracks,
+        })
+    }
+
+    pub fn num_tracks(&self) -> usize {
+        self.tracks.len() / 2
+    }
+
+    pub fn num_channels(&self) -> usize {
+        self.channels.len()
+    }
+
+    fn track(&self, index: usize) -> Result<Track<'a>, SongError> {
+        if index >= self.num_tracks() {
+            return Err(SongError::NoSuchTrack);
+        }
+        let mut at = u16_at(self.tracks, index * 2)? as usize;
+        let header = take(self.data, &mut at, 6)?;
+        let num_orders = header[4] as usize;
+        let num_patterns = header[5] as usize;
+        Ok(Track {
+            speed: header[0].max(1),
+            tempo: header[1].max(1),
+            rows: u16::from_le_bytes([header[2], header[3]]).max(1),
+            patterns: take(self.data, &mut at, num_patterns * 2)?,
+            orders: take(self.data, &mut at, num_orders * self.channels.len())?,
+        })
+    }
+
+    /// Linear scan, songs have a few dozen macros at most and this only runs on note on.
+    fn macro_at(&self, id: u8) -> Option<Macro<'a>> {
+        if id >= self.num_macros {
+            return None;
+        }
+        let mut at = 0;
+        for _ in 0..id {
+            at += 3 + *self.macros.get(at)? as usize;
+        }
+        let len = *self.macros.get(at)? as usize;
+        Some(Macro {
+            loop_point: *self.macros.get(at + 1)?,
+            release: *self.macros.get(at + 2)?,
+            values: self.macros.get(at + 3..at + 3 + len)?,
+        })
+    }
+
+    fn instrument(&self, index: u8) -> [u8; 5] {
+        let at = index as usize * 5;
+        match self.instruments.get(at..at + 5) {
+            Some(inst) => [inst[0], inst[1], inst[2], inst[3], inst[4]],
+            None => [NONE; 5],
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Default)]
+struct MacroState<'a> {
+    mac: Option<Macro<'a>>,
+    pos: u8,
+}
+
+impl<'a> MacroState<'a> {
+    fn new(mac: Option<Macro<'a>>) -> Self {
+        Self { mac, pos: 0 }
+    }
+
+    /// Current value and advance, `None` once a macro without loop has run out or when there is
+    /// no macro at all.
+    fn step(&mut self, released: bool) -> Option<i8> {
+        let mac = self.mac?;
+        let len = mac.values.len();
+        let pos = self.pos as usize;
+        let value = *mac.values.get(pos)? as i8;
+
+        let has_loop = (mac.loop_point as usize) < len;
+        let has_release = (mac.release as usize) < len;
+        // a loop in front of the release point only runs until the note is released
+        let loop_before_release = has_loop && has_release && mac.loop_point <= mac.release;
+        self.pos = if has_release && !released && pos >= mac.release as usize {
+            if loop_before_release {
+                mac.loop_point
+            } else {
+                mac.release
+            }
+        } else if pos + 1 < len {
+            self.pos + 1
+        } else if has_loop && !loop_before_release {
+            mac.loop_point
+        } else {
+            NONE
+        };
+        Some(value)
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+struct ChannelState<'a> {
+    cursor: usize,
+    wait: u16,
+    note: Option<u8>,
+    released: bool,
+    instrument: u8,
+    volume: u8,
+    volume_macro: MacroState<'a>,
+    arpeggio_macro: MacroState<'a>,
+    pitch_macro: MacroState<'a>,
+    hi_pitch_macro: MacroState<'a>,
+    duty_macro: MacroState<'a>,
+    /// Last values of the absolute macros, these hold once the macro has run out.
+    macro_volume: i8,
+    arpeggio: i8,
+    pitch: i32,
+    duty: u8,
+    last: ChannelRegs,
+}
+
+/// Plays one track of a [`Song`] on the tone channels.
+pub struct MusicPlayer<'a> {
+    song: Song<'a>,
+    track: Option<Track<'a>>,
+    order: usize,
+    row: u16,
+    speed: u8,
+    tempo: u8,
+    tick_acc: u32,
+    channels: [ChannelState<'a>; MAX_CHANNELS],
+    /// Channels not written to, someone else (sound effects) owns them for now.
+    muted: u8,
+    /// Order and row to go on from at the next row, set by `Bxx`, `Cxx` and `Dxx`.
+    jump: Option<(usize, u16)>,
+    pub looping: bool,
+}
+
+impl<'a> MusicPlayer<'a> {
+    pub fn new(song: Song<'a>) -> Self {
+        Self {
+            song,
+            track: None,
+            order: 0,
+            row: 0,
+            speed: 6,
+            tempo: 150,
+            tick_acc: 0,
+            channels: [ChannelState::default(); MAX_CHANNELS],
+            muted: 0,
+            jump: None,
+            looping: true,
+        }
+    }
+
+    /// Starts `track` from the first order.
+    pub fn play(&mut self, track: usize) -> Result<(), SongError> {
+        let track = self.song.track(track)?;
+        self.speed = track.speed;
+        self.tempo = track.tempo;
+        self.track = Some(track);
+        self.channels = [ChannelState {
+            volume: 15,
+            ..Default::default()
+        }; MAX_CHANNELS];
+        self.jump = None;
+        self.start_order(0, 0, &mut write_guest);
+        // make the first tick start the first row
+        self.tick_acc = self.row_length();
+        Ok(())
+    }
+
+    pub fn stop(&mut self) {
+        self.stop_with(&mut write_guest);
+    }
+
+    /// [`stop`](Self::stop), silencing the channels through `write`.
+    pub fn stop_with(&mut self, write: &mut dyn FnMut(Channel, &ChannelRegs)) {
+        self.track = None;
+        for (i, target) in self.song.channels.iter().enumerate() {
+            if self.muted & (1 << i) == 0 {
+                if let Some(channel) = Channel::from_index(*target as usize) {
+                    write(channel, &ChannelRegs::OFF);
+                }
+            }
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.track.is_some()
+    }
+
+    /// Stops writing `channel` while `muted`. When unmuted the channel registers are rewritten on
+    /// the next tick.
+    pub fn set_muted(&mut self, channel: Channel, muted: bool) {
+        for (i, target) in self.song.channels.iter().enumerate() {
+            if *target == channel as u8 {
+                if muted {
+                    self.muted |= 1 << i;
+                } else {
+                    self.muted &= !(1 << i);
+                    self.channels[i].last = ChannelRegs::default();
+                    self.channels[i].last.frequency = u32::MAX;
+                }
+            }
+        }
+    }
+
+    fn row_length(&self) -> u32 {
+        self.speed as u32 * 150
+    }
+
+    fn start_order(
+        &mut self,
+        order: usize,
+        row: u16,
+        write: &mut dyn FnMut(Channel, &ChannelRegs),
+    ) {
+        let track = match self.track {
+            Some(track) => track,
+            None => return,
+        };
+        let num_channels = self.song.num_channels();
+        let num_orders = track.orders.len() / num_channels.max(1);
+        let order = if order >= num_orders {
+            if !self.looping || order == HALT {
+                self.stop_with(write);
+                return;
+            }
+            0
+        } else {
+            order
+        };
+        self.order = order;
+        self.row = row.min(track.rows);
+        for (i, channel) in self.channels.iter_mut().enumerate().take(num_channels) {
+            let pattern = track.orders[order * num_channels + i] as usize;
+            channel.cursor = u16_at(track.patterns, pattern * 2).unwrap_or(0) as usize;
+            channel.wait = 0;
+        }
+        for _ in 0..self.row {
+            for i in 0..num_channels {
+                self.skip_row(i);
+            }
+        }
+    }
+
+    /// Advances by one tick of [`TICK_RATE`], reading new rows when they are due and running
+    /// the instrument macros.
+    pub fn tick(&mut self) {
+        self.tick_with(&mut write_guest);
+    }
+
+    /// [`tick`](Self::tick), handing the channel writes to `write` instead of the audio device.
+    pub fn tick_with(&mut self, write: &mut dyn FnMut(Channel, &ChannelRegs)) {
+        if self.track.is_none() {
+            return;
+        }
+        self.tick_acc += self.tempo as u32;
+        if self.tick_acc >= self.row_length() {
+            self.tick_acc -= self.row_length();
+            self.next_row(write);
+        }
+        if self.track.is_none() {
+            return;
+        }
+        for i in 0..self.song.num_channels() {
+            self.update_channel(i, write);
+        }
+    }
+
+    fn next_row(&mut self, write: &mut dyn FnMut(Channel, &ChannelRegs)) {
+        let track = match self.track {
+            Some(track) => track,
+            None => return,
+        };
+        if self.row >= track.rows || self.jump.is_some() {
+            let (order, row) = self.jump.take().unwrap_or((self.order + 1, 0));
+            self.start_order(order, row, write);
+            if self.track.is_none() {
+                return;
+            }
+        }
+        for i in 0..self.song.num_channels() {
+            self.read_row(i);
+        }
+        self.row += 1;
+    }
+
+    fn read_row(&mut self, index: usize) {
+        let data = self.song.data;
+        let channel = &mut self.channels[index];
+        if channel.wait > 0 {
+            channel.wait -= 1;
+            return;
+        }
+        loop {
+            let cmd = match data.get(channel.cursor) {
+                Some(cmd) => *cmd,
+                None => CMD_END_PATTERN,
+            };
+            let arg = |n: usize| data.get(channel.cursor + n).copied().unwrap_or(0);
+            match cmd {
+                0x00..=0x7F => {
+                    channel.note = Some(cmd);
+                    channel.released = false;
+                    channel.macro_volume = 15;
+                    channel.arpeggio = 0;
+                    channel.pitch = 0;
+                    let inst = self.song.instrument(channel.instrument);
+                    channel.volume_macro = MacroState::new(self.song.macro_at(inst[0]));
+                    channel.arpeggio_macro = MacroState::new(self.song.macro_at(inst[1]));
+                    channel.pitch_macro = MacroState::new(self.song.macro_at(inst[2]));
+                    channel.hi_pitch_macro = MacroState::new(self.song.macro_at(inst[3]));
+                    channel.duty_macro = MacroState::new(self.song.macro_at(inst[4]));
+                    channel.cursor += 1;
+                }
+                CMD_NOTE_CUT => {
+                    channel.note = None;
+                    channel.cursor += 1;
+                }
+                CMD_RELEASE => {
+                    channel.released = true;
+                    channel.cursor += 1;
+                }
+                CMD_INSTRUMENT => {
+                    channel.instrument = arg(1);
+                    channel.cursor += 2;
+                }
+                CMD_VOLUME => {
+                    channel.volume = arg(1) & 15;
+                    channel.cursor += 2;
+                }
+                CMD_EFFECT => {
+                    let (effect, param) = (arg(1), arg(2));
+                    channel.cursor += 3;
+                    match effect {
+                        b'B' => self.jump = Some((param as usize, 0)),
+                        b'C' => self.jump = Some((HALT, 0)),
+                        b'D' => self.jump = Some((self.order + 1, param as u16)),
+                        b'F' if param != 0 && param < 0x20 => self.speed = param,
+                        b'F' if param >= 0x20 => self.tempo = param,
+                        _ => {}
+                    }
+                }
+                CMD_END_PATTERN => {
+                    channel.wait = u16::MAX;
+                    return;
+                }
+                _ => {
+                    channel.wait = (cmd & 0x3F) as u16;
+                    channel.cursor += 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Moves past a row without playing it, for `Dxx` starting further into the next order.
+    fn skip_row(&mut self, index: usize) {
+        let data = self.song.data;
+        let channel = &mut self.channels[index];
+        if channel.wait > 0 {
+            channel.wait -= 1;
+            return;
+        }
+        loop {
+            match data.get(channel.cursor).copied().unwrap_or(CMD_END_PATTERN) {
+                0x00..=0x7F | CMD_NOTE_CUT | CMD_RELEASE => channel.cursor += 1,
+                CMD_INSTRUMENT | CMD_VOLUME => channel.cursor += 2,
+                CMD_EFFECT => channel.cursor += 3,
+                CMD_END_PATTERN => {
+                    channel.wait = u16::MAX;
+                    return;
+                }
+                cmd => {
+                    channel.wait = (cmd & 0x3F) as u16;
+                    channel.cursor += 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn update_channel(&mut self, index: usize, write: &mut dyn FnMut(Channel, &ChannelRegs)) {
+        let target = match Channel::from_index(self.song.channels[index] as usize) {
+            Some(target) => target,
+            None => return,
+        };
+        let channel = &mut self.channels[index];
+        let released = channel.released;
+
+        if let Some(volume) = channel.volume_macro.step(released) {
+            channel.macro_volume = volume.clamp(0, 15);
+        }
+        if let Some(arpeggio) = channel.arpeggio_macro.step(released) {
+            channel.arpeggio = arpeggio;
+        }
+        channel.pitch += channel.pitch_macro.step(released).unwrap_or(0) as i32;
+        channel.pitch += channel.hi_pitch_macro.step(released).unwrap_or(0) as i32 * 16;
+        if let Some(duty) = channel.duty_macro.step(released) {
+            channel.duty = duty as u8 & 3;
+        }
+
+        let regs = match channel.note {
+            Some(note) => {
+                let macro_volume = channel.macro_volume as u32;
+                let mut volume = (macro_volume * channel.volume as u32).div_ceil(15);
+                if volume == 0 && macro_volume != 0 && channel.volume != 0 {
+                    volume = 1;
+                }
+                let note = (note as i32 + channel.arpeggio as i32).clamp(0, 127) as u8;
+                let frequency = match target {
+                    Channel::Noise => {
+                        (NES_CPU_CLOCK / NOISE_PERIODS[15 - (note as usize & 15)]) << 8
+                    }
+                    // the triangle sequencer has twice the steps, FamiTracker notes for it
+                    // sound an octave lower than the same note on the pulses
+                    Channel::Triangle => super::note_frequency(note) >> 1,
+                    _ => super::note_frequency(note),
+                };
+                // one pitch unit is roughly one NES period step in the middle octaves
+                let bend = frequency as i64 * channel.pitch as i64 / 256;
+                let frequency = (frequency as i64 - bend).clamp(1, u32::MAX as i64) as u32;
+                let regs = ChannelRegs::tone(frequency, volume as u8);
+                if target == Channel::Noise {
+                    regs.with_short_noise(channel.duty & 1 != 0)
+                } else {
+                    regs.with_duty(match channel.duty {
+                        0 => Duty::Eighth,
+                        1 => Duty::Quarter,
+                        2 => Duty::Half,
+                        _ => Duty::ThreeQuarters,
+                    })
+                }
+            }
+            None => ChannelRegs::OFF,
+        };
+
+        if self.muted & (1 << index) == 0 && regs != channel.last {
+            write(target, &regs);
+            channel.last = regs;
+        }
+    }
+}
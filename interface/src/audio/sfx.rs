@@ -0,0 +1,224 @@
+//! One shot sound effects layered over the [`MusicPlayer`].
+//!
+//! An [`Effect`] is a short program of [`Step`]s, each holding a tone for a number of frames
+//! while sliding its volume and pitch. The [`SfxMixer`] picks a channel for every effect played,
+//! stealing one from a lower (or equal) priority effect when all candidates are busy, and mutes
+//! the music on the channels it is using.
+
+use super::{music::MusicPlayer, Channel, ChannelRegs, Duty, NUM_CHANNELS};
+
+/// Either pulse channel, the second one first so the melody usually keeps playing.
+pub const PULSE: &[Channel] = &[Channel::Pulse2, Channel::Pulse1];
+pub const TRIANGLE: &[Channel] = &[Channel::Triangle];
+pub const NOISE: &[Channel] = &[Channel::Noise];
+
+#[derive(Copy, Clone, Debug)]
+pub struct Step {
+    pub frames: u8,
+    /// Hz with 8 fractional bits like [`ChannelRegs::frequency`], `0` keeps the frequency the
+    /// previous step ended on.
+    pub frequency: u32,
+    /// `0..=15`
+    pub volume: u8,
+    /// Added to the volume every frame, in sixteenths of a volume step.
+    pub volume_slide: i8,
+    /// Added to the frequency every frame, same units as `frequency`.
+    pub pitch_slide: i32,
+    pub duty: Duty,
+    pub short_noise: bool,
+}
+
+impl Step {
+    pub const fn tone(frames: u8, frequency: u32, volume: u8) -> Self {
+        Self {
+            frames,
+            frequency,
+            volume,
+            volume_slide: 0,
+            pitch_slide: 0,
+            duty: Duty::Half,
+            short_noise: false,
+        }
+    }
+
+    /// A MIDI note, see [`super::note_frequency`].
+    pub const fn note(frames: u8, note: u8, volume: u8) -> Self {
+        Self::tone(frames, super::note_frequency(note), volume)
+    }
+
+    pub const fn rest(frames: u8) -> Self {
+        Self::tone(frames, 0, 0)
+    }
+
+    pub const fn with_volume_slide(mut self, slide: i8) -> Self {
+        self.volume_slide = slide;
+        self
+    }
+
+    pub const fn with_pitch_slide(mut self, slide: i32) -> Self {
+        self.pitch_slide = slide;
+        self
+    }
+
+    pub const fn with_duty(mut self, duty: Duty) -> Self {
+        self.duty = duty;
+        self
+    }
+
+    pub const fn with_short_noise(mut self, short: bool) -> Self {
+        self.short_noise = short;
+        self
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Effect {
+    pub steps: &'static [Step],
+    /// Channels the effect may play on, in order of preference.
+    pub channels: &'static [Channel],
+    /// Higher priorities steal channels from lower ones, equal priorities replace each other.
+    pub priority: u8,
+}
+
+#[derive(Copy, Clone)]
+struct Voice {
+    effect: &'static Effect,
+    step: usize,
+    frames_left: u8,
+    frequency: u32,
+    /// Volume with 4 fractional bits.
+    volume: i16,
+}
+
+impl Voice {
+    fn new(effect: &'static Effect) -> Self {
+        Self {
+            effect,
+            step: 0,
+            frames_left: 0,
+            frequency: 0,
+            volume: 0,
+        }
+    }
+
+    /// Moves to the next step once the current one is done, false once the effect is over.
+    fn next_step(&mut self) -> bool {
+        while self.frames_left == 0 {
+            let step = match self.effect.steps.get(self.step) {
+                Some(step) => step,
+                None => return false,
+            };
+            self.step += 1;
+            self.frames_left = step.frames;
+            if step.frequency != 0 {
+                self.frequency = step.frequency;
+            }
+            self.volume = (step.volume.min(15) as i16) << 4;
+        }
+        true
+    }
+
+    fn regs(&self) -> ChannelRegs {
+        let step = &self.effect.steps[self.step - 1];
+        ChannelRegs::tone(self.frequency, (self.volume >> 4) as u8)
+            .with_duty(step.duty)
+            .with_short_noise(step.short_noise)
+    }
+
+    fn advance(&mut self) {
+        let step = &self.effect.steps[self.step - 1];
+        self.frames_left -= 1;
+        self.volume = (self.volume + step.volume_slide as i16).clamp(0, 15 << 4);
+        self.frequency =
+            (self.frequency as i64 + step.pitch_slide as i64).clamp(0, u32::MAX as i64) as u32;
+    }
+}
+
+/// Plays [`Effect`]s, one per channel. Call [`SfxMixer::tick`] once per frame before ticking
+/// the music so channels handed back are picked up by the music on the same frame.
+pub struct SfxMixer {
+    voices: [Option<Voice>; NUM_CHANNELS],
+    /// Channels the music was last told to keep off of.
+    claimed: u8,
+}
+
+impl Default for SfxMixer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SfxMixer {
+    pub const fn new() -> Self {
+        Self {
+            voices: [None; NUM_CHANNELS],
+            claimed: 0,
+        }
+    }
+
+    /// Starts `effect` on the first free channel it may use, otherwise on the one playing the
+    /// lowest priority effect that `effect` can steal. Returns false when every candidate is
+    /// busy with something more important.
+    pub fn play(&mut self, effect: &'static Effect) -> bool {
+        let mut best: Option<(Channel, u8)> = None;
+        for channel in effect.channels {
+            match &self.voices[*channel as usize] {
+                None => {
+                    best = Some((*channel, 0));
+                    break;
+                }
+                Some(voice) => {
+                    let priority = voice.effect.priority;
+                    if priority <= effect.priority && best.is_none_or(|(_, p)| priority < p) {
+                        best = Some((*channel, priority));
+                    }
+                }
+            }
+        }
+        match best {
+            Some((channel, _)) => {
+                self.voices[channel as usize] = Some(Voice::new(effect));
+                true
+            }
+            None => false,
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_playing(&self, channel: Channel) -> bool {
+        self.voices[channel as usize].is_some()
+    }
+
+    pub fn stop_all(&mut self) {
+        for channel in Channel::ALL {
+            if self.voices[channel as usize].take().is_some() {
+                super::stop_channel(channel);
+            }
+        }
+    }
+
+    /// Advances every effect by a frame and hands channels of finished effects back to `music`.
+    pub fn tick(&mut self, mut music: Option<&mut MusicPlayer<'_>>) {
+        for channel in Channel::ALL {
+            let slot = &mut self.voices[channel as usize];
+            if let Some(voice) = slot {
+                if voice.next_step() {
+                    super::write_channel(channel, &voice.regs());
+                    voice.advance();
+                } else {
+                    *slot = None;
+                    super::stop_channel(channel);
+                }
+            }
+
+            let mask = 1 << channel as u8;
+            let claimed = slot.is_some();
+            if claimed != (self.claimed & mask != 0) {
+                self.claimed ^= mask;
+                if let Some(music) = music.as_deref_mut() {
+                    music.set_muted(channel, claimed);
+                }
+            }
+        }
+    }
+}
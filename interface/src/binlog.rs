@@ -0,0 +1,206 @@
+//! Logging without formatting on the guest.
+//!
+//! With the `binlog` feature every [`binlog!`](crate::binlog!) call site interns its level,
+//! module, location and format string into the `.binlog` section of the ELF at compile time.
+//! At runtime only the address of that entry and the arguments, each tagged with its type, go
+//! to the host through [`crate::sys::binlog_write`]. The section isn't loaded into guest
+//! memory, the host rebuilds the messages from `mips/bin/com.o` with
+//! [`crate::host::binlog`] (see the `binlog_decode` example).
+//!
+//! ```ignore
+//! interface::binlog!(Trace, "frame {} took {}us", frame, micros);
+//! ```
+//!
+//! Without the feature the same calls format through [`crate::log`] like its macros do, so
+//! switching modes doesn't change any call site. The level and module filters of
+//! [`crate::log::init`] apply in both modes.
+//!
+//! A frame is the entry address as a little endian `u32` followed by the arguments, each a
+//! `TAG_*` byte and a little endian payload. Arguments that don't fit in [`FRAME_MAX`] bytes are
+//! dropped and decode as missing.
+
+/// Largest frame sent to the host, lives on the stack of the logging call.
+pub const FRAME_MAX: usize = 96;
+
+/// `u8`, `u16`, `u32` and `usize` as a `u32`.
+pub const TAG_U32: u8 = 1;
+/// `i8`, `i16`, `i32` and `isize` as an `i32`.
+pub const TAG_I32: u8 = 2;
+pub const TAG_U64: u8 = 3;
+pub const TAG_I64: u8 = 4;
+/// One byte, `0` or `1`.
+pub const TAG_BOOL: u8 = 5;
+/// The scalar value as a `u32`.
+pub const TAG_CHAR: u8 = 6;
+/// The bits, formatting floats is what the host is for.
+pub const TAG_F32: u8 = 7;
+pub const TAG_F64: u8 = 8;
+/// A `u16` byte length and the bytes, cut short when the frame is full.
+pub const TAG_STR: u8 = 9;
+
+/// Separates the parts of an interned entry, `level␟module␟file:line␟format␀`.
+pub const ENTRY_SEPARATOR: char = '\x1f';
+
+pub struct Frame {
+    buf: [u8; FRAME_MAX],
+    len: usize,
+    full: bool,
+}
+
+impl Frame {
+    /// A frame for the entry at `id`, the address of the interned entry.
+    #[inline(always)]
+    pub fn new(id: u32) -> Self {
+        let mut frame = Self {
+            buf: [0; FRAME_MAX],
+            len: 0,
+            full: false,
+        };
+        frame.push(&id.to_le_bytes());
+        frame
+    }
+
+    #[inline(always)]
+    fn fits(&mut self, len: usize) -> bool {
+        self.full |= self.len + len > FRAME_MAX;
+        !self.full
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        self.buf[self.len..self.len + bytes.len()].copy_from_slice(bytes);
+        self.len += bytes.len();
+    }
+
+    /// Appends a tagged value, nothing is appended after the first value that didn't fit.
+    pub fn tagged(&mut self, tag: u8, payload: &[u8]) {
+        if self.fits(1 + payload.len()) {
+            self.push(&[tag]);
+            self.push(payload);
+        }
+    }
+
+    pub fn str(&mut self, str: &str) {
+        if !self.fits(3) {
+            return;
+        }
+        let len = str.len().min(FRAME_MAX - self.len - 3);
+        self.push(&[TAG_STR]);
+        self.push(&(len as u16).to_le_bytes());
+        self.push(&str.as_bytes()[..len]);
+    }
+
+    #[inline(always)]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+
+    pub fn send(&self) {
+        crate::sys::binlog_write(self.as_bytes());
+    }
+}
+
+/// Arguments [`binlog!`](crate::binlog!) can send.
+pub trait Encode {
+    fn encode(&self, frame: &mut Frame);
+}
+
+macro_rules! encode_as {
+    ($tag:ident, $as:ty, $($ty:ty),*) => {$(
+        impl Encode for $ty {
+            #[inline(always)]
+            fn encode(&self, frame: &mut Frame) {
+                frame.tagged($tag, &(*self as $as).to_le_bytes());
+            }
+        }
+    )*};
+}
+
+encode_as!(TAG_U32, u32, u8, u16, u32, usize);
+encode_as!(TAG_I32, i32, i8, i16, i32, isize);
+encode_as!(TAG_U64, u64, u64);
+encode_as!(TAG_I64, i64, i64);
+encode_as!(TAG_CHAR, u32, char);
+
+impl Encode for bool {
+    #[inline(always)]
+    fn encode(&self, frame: &mut Frame) {
+        frame.tagged(TAG_BOOL, &[*self as u8]);
+    }
+}
+
+impl Encode for f32 {
+    #[inline(always)]
+    fn encode(&self, frame: &mut Frame) {
+        frame.tagged(TAG_F32, &self.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for f64 {
+    #[inline(always)]
+    fn encode(&self, frame: &mut Frame) {
+        frame.tagged(TAG_F64, &self.to_bits().to_le_bytes());
+    }
+}
+
+impl Encode for str {
+    #[inline(always)]
+    fn encode(&self, frame: &mut Frame) {
+        frame.str(self);
+    }
+}
+
+impl<T: Encode + ?Sized> Encode for &T {
+    #[inline(always)]
+    fn encode(&self, frame: &mut Frame) {
+        (**self).encode(frame);
+    }
+}
+
+/// Copies an entry into the array stored in `.binlog`.
+#[doc(hidden)]
+pub const fn intern<const N: usize>(entry: &str) -> [u8; N] {
+    let bytes = entry.as_bytes();
+    let mut array = [0; N];
+    let mut i = 0;
+    while i < N {
+        array[i] = bytes[i];
+        i += 1;
+    }
+    array
+}
+
+/// `binlog!(Level, "format", args..)` with `Level` one of the [`crate::log::Level`] variants.
+/// Arguments have to implement [`Encode`] with the `binlog` feature and `Display`/`Debug` (as
+/// the format string asks) without it.
+#[cfg(feature = "binlog")]
+#[macro_export]
+macro_rules! binlog {
+    ($level:ident, $fmt:literal $(, $arg:expr)* $(,)?) => {{
+        if $crate::log::enabled($crate::log::Level::$level, core::module_path!()) {
+            const ENTRY: &str = core::concat!(
+                core::stringify!($level), "\x1f",
+                core::module_path!(), "\x1f",
+                core::file!(), ":", core::line!(), "\x1f",
+                $fmt, "\0"
+            );
+            #[link_section = ".binlog"]
+            static INTERNED: [u8; ENTRY.len()] = $crate::binlog::intern(ENTRY);
+            // only the address is used, the section isn't loaded so never read through it
+            let mut frame =
+                $crate::binlog::Frame::new(core::ptr::addr_of!(INTERNED) as *const u8 as usize as u32);
+            $($crate::binlog::Encode::encode(&$arg, &mut frame);)*
+            frame.send();
+        }
+    }};
+}
+
+/// `binlog!(Level, "format", args..)` with `Level` one of the [`crate::log::Level`] variants.
+/// Arguments have to implement [`Encode`] with the `binlog` feature and `Display`/`Debug` (as
+/// the format string asks) without it.
+#[cfg(not(feature = "binlog"))]
+#[macro_export]
+macro_rules! binlog {
+    ($level:ident, $fmt:literal $(, $arg:expr)* $(,)?) => {
+        $crate::log!($crate::log::Level::$level, $fmt $(, $arg)*)
+    };
+}
@@ -0,0 +1,346 @@
+/// An 8 bit per channel RGBA color.
+///
+/// When packed into a `u32` for the screen syscalls the layout is `0xAABBGGRR`, red in the
+/// lowest byte. This is the order the host reads pixels in, the host ignores the alpha byte.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Color([u8; 4]);
+
+/// How a source color is combined with the color already on screen.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum BlendMode {
+    /// Source replaces destination, alpha is ignored.
+    Replace,
+    /// Standard "source over" alpha compositing.
+    AlphaOver,
+    /// Source is added to destination (scaled by source alpha), saturating at 255.
+    Additive,
+    /// Destination is multiplied by source, white leaves it unchanged.
+    Multiply,
+}
+
+/// Hue, saturation, value. The hue wraps around the full color wheel in `0..=255` so that it
+/// matches the packing used by the `hsv_to_rgb` syscall.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hsv {
+    pub h: u8,
+    pub s: u8,
+    pub v: u8,
+}
+
+/// Hue, saturation, lightness. The hue uses the same `0..=255` wheel as [`Hsv`].
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hsl {
+    pub h: u8,
+    pub s: u8,
+    pub l: u8,
+}
+
+/// `a * b / 255` rounded to nearest, without a division.
+#[inline(always)]
+const fn mul_255(a: u8, b: u8) -> u8 {
+    let x = a as u32 * b as u32 + 128;
+    ((x + (x >> 8)) >> 8) as u8
+}
+
+impl Color {
+    pub const BLACK: Self = Self::from_rgb(0, 0, 0);
+    pub const WHITE: Self = Self::from_rgb(255, 255, 255);
+
+    #[inline(always)]
+    pub const fn from_rgb(r: u8, g: u8, b: u8) -> Self {
+        Self([r, g, b, 255])
+    }
+    #[inline(always)]
+    pub const fn from_rgba(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self([r, g, b, a])
+    }
+
+    /// A color with zero alpha and non zero rgb. [`Color::composite`] treats these as additive
+    /// light, fully transparent black ([`Color::clear`]) is then just a no-op.
+    #[inline(always)]
+    pub const fn from_rgb_additive(r: u8, g: u8, b: u8) -> Self {
+        Self([r, g, b, 0])
+    }
+
+    #[inline(always)]
+    pub const fn clear() -> Self {
+        Self([0, 0, 0, 0])
+    }
+
+    pub fn get_inner(&self) -> &[u8; 4] {
+        &self.0
+    }
+
+    #[inline(always)]
+    pub const fn r(&self) -> u8 {
+        self.0[0]
+    }
+    #[inline(always)]
+    pub const fn g(&self) -> u8 {
+        self.0[1]
+    }
+    #[inline(always)]
+    pub const fn b(&self) -> u8 {
+        self.0[2]
+    }
+
+    #[inline(always)]
+    pub fn is_opaque(&self) -> bool {
+        self.0[3] == 255
+    }
+    #[inline(always)]
+    pub fn get_alpha(&self) -> u8 {
+        self.0[3]
+    }
+
+    #[inline(always)]
+    pub const fn with_alpha(self, a: u8) -> Self {
+        Self([self.0[0], self.0[1], self.0[2], a])
+    }
+
+    //--------------------------------------------------------------------------------------------------------
+
+    /// Combines `self` (the source) with `dst` using `mode`.
+    pub fn blend(self, dst: Color, mode: BlendMode) -> Color {
+        match mode {
+            BlendMode::Replace => self,
+            BlendMode::AlphaOver => self.over(dst),
+            BlendMode::Additive => self.additive(dst),
+            BlendMode::Multiply => self.multiply(dst),
+        }
+    }
+
+    /// Picks the blend mode from the alpha channel: opaque colors replace, zero alpha colors
+    /// (see [`Color::from_rgb_additive`]) add, everything in between is alpha composited.
+    pub fn composite(self, dst: Color) -> Color {
+        match self.0[3] {
+            255 => self,
+            0 => Self::from_rgba(self.0[0], self.0[1], self.0[2], 255).additive(dst),
+            _ => self.over(dst),
+        }
+    }
+
+    /// Porter-Duff "source over destination".
+    pub fn over(self, dst: Color) -> Color {
+        let a = self.0[3];
+        let inv = 255 - a;
+        let mut out = [0u8; 4];
+        for (i, out) in out.iter_mut().enumerate().take(3) {
+            *out = mul_255(self.0[i], a) + mul_255(dst.0[i], inv);
+        }
+        out[3] = a + mul_255(dst.0[3], inv);
+        Color(out)
+    }
+
+    /// Adds `self` scaled by its alpha on top of `dst`.
+    pub fn additive(self, dst: Color) -> Color {
+        let a = self.0[3];
+        let mut out = dst.0;
+        for (i, out) in out.iter_mut().enumerate().take(3) {
+            *out = out.saturating_add(mul_255(self.0[i], a));
+        }
+        Color(out)
+    }
+
+    /// Channel wise multiply, keeps the alpha of `dst`.
+    pub fn multiply(self, dst: Color) -> Color {
+        let mut out = dst.0;
+        for (i, out) in out.iter_mut().enumerate().take(3) {
+            *out = mul_255(self.0[i], *out);
+        }
+        Color(out)
+    }
+
+    /// Linear interpolation of all four channels, `t == 0` is `self` and `t == 255` is `other`.
+    pub fn lerp(self, other: Color, t: u8) -> Color {
+        let mut out = [0u8; 4];
+        for (i, out) in out.iter_mut().enumerate() {
+            let a = self.0[i] as u32 * (255 - t as u32);
+            let b = other.0[i] as u32 * t as u32;
+            *out = ((a + b + 127) / 255) as u8;
+        }
+        Color(out)
+    }
+
+    //--------------------------------------------------------------------------------------------------------
+
+    /// Pure integer HSV to RGB, replaces a round trip through the `hsv_to_rgb` syscall.
+    pub const fn from_hsv(hsv: Hsv) -> Self {
+        let Hsv { h, s, v } = hsv;
+        if s == 0 {
+            return Self::from_rgb(v, v, v);
+        }
+        let (h, s, v) = (h as u32, s as u32, v as u32);
+        let region = h / 43;
+        let rem = (h - region * 43) * 6;
+
+        let p = ((v * (255 - s)) >> 8) as u8;
+        let q = ((v * (255 - ((s * rem) >> 8))) >> 8) as u8;
+        let t = ((v * (255 - ((s * (255 - rem)) >> 8))) >> 8) as u8;
+        let v = v as u8;
+
+        match region {
+            0 => Self::from_rgb(v, t, p),
+            1 => Self::from_rgb(q, v, p),
+            2 => Self::from_rgb(p, v, t),
+            3 => Self::from_rgb(p, q, v),
+            4 => Self::from_rgb(t, p, v),
+            _ => Self::from_rgb(v, p, q),
+        }
+    }
+
+    pub const fn to_hsv(self) -> Hsv {
+        let [r, g, b, _] = self.0;
+        let max = max3(r, g, b);
+        let min = min3(r, g, b);
+        let v = max;
+        if max == 0 || max == min {
+            return Hsv { h: 0, s: 0, v };
+        }
+        let s = (255 * (max - min) as u32 / max as u32) as u8;
+        Hsv {
+            h: hue(r, g, b, max, min),
+            s,
+            v,
+        }
+    }
+
+    pub const fn from_hsl(hsl: Hsl) -> Self {
+        let Hsl { h, s, l } = hsl;
+        let (s32, l32) = (s as u32, l as u32);
+        let chroma_half = if l32 < 128 { l32 } else { 255 - l32 };
+        let v = l32 + (s32 * chroma_half + 127) / 255;
+        let s = match (2 * (v - l32) * 255 + v / 2).checked_div(v) {
+            Some(s) => s,
+            None => 0,
+        };
+        Self::from_hsv(Hsv {
+            h,
+            s: if s > 255 { 255 } else { s as u8 },
+            v: v as u8,
+        })
+    }
+
+    pub const fn to_hsl(self) -> Hsl {
+        let [r, g, b, _] = self.0;
+        let max = max3(r, g, b) as u32;
+        let min = min3(r, g, b) as u32;
+        let l = ((max + min) / 2) as u8;
+        if max == min {
+            return Hsl { h: 0, s: 0, l };
+        }
+        let sum = max + min;
+        let denom = if sum > 255 { 510 - sum } else { sum };
+        let s = ((max - min) * 255 / denom) as u8;
+        Hsl {
+            h: hue(r, g, b, max as u8, min as u8),
+            s,
+            l,
+        }
+    }
+
+    //--------------------------------------------------------------------------------------------------------
+
+    /// `0xAABBGGRR`, see the type level docs.
+    #[inline(always)]
+    pub const fn to_u32(self) -> u32 {
+        u32::from_le_bytes(self.0)
+    }
+
+    /// Inverse of [`Color::to_u32`], alpha included.
+    #[inline(always)]
+    pub const fn from_u32(color: u32) -> Self {
+        Self(color.to_le_bytes())
+    }
+
+    /// Reads a `0x__BBGGRR` value as returned by the host, the top byte is ignored and the
+    /// result is opaque.
+    #[inline(always)]
+    pub const fn from_u32_rgb(color: u32) -> Self {
+        Self::from_u32(color).with_alpha(255)
+    }
+
+    /// 5 bits red in the high bits, 6 bits green, 5 bits blue. Alpha is dropped.
+    #[inline(always)]
+    pub const fn to_rgb565(self) -> u16 {
+        ((self.0[0] as u16 >> 3) << 11) | ((self.0[1] as u16 >> 2) << 5) | (self.0[2] as u16 >> 3)
+    }
+
+    /// Expands by bit replication so that white maps to 255 and black to 0. The result is opaque.
+    #[inline(always)]
+    pub const fn from_rgb565(color: u16) -> Self {
+        let r = ((color >> 11) & 0x1F) as u8;
+        let g = ((color >> 5) & 0x3F) as u8;
+        let b = (color & 0x1F) as u8;
+        Self::from_rgb(
+            (r << 3) | (r >> 2),
+            (g << 2) | (g >> 4),
+            (b << 3) | (b >> 2),
+        )
+    }
+}
+
+const fn max3(a: u8, b: u8, c: u8) -> u8 {
+    let m = if a > b { a } else { b };
+    if m > c {
+        m
+    } else {
+        c
+    }
+}
+
+const fn min3(a: u8, b: u8, c: u8) -> u8 {
+    let m = if a < b { a } else { b };
+    if m < c {
+        m
+    } else {
+        c
+    }
+}
+
+/// Hue on the `0..=255` wheel, `max != min` must hold.
+const fn hue(r: u8, g: u8, b: u8, max: u8, min: u8) -> u8 {
+    let delta = (max - min) as i32;
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let h = if max as i32 == r {
+        43 * (g - b) / delta
+    } else if max as i32 == g {
+        85 + 43 * (b - r) / delta
+    } else {
+        171 + 43 * (r - g) / delta
+    };
+    h.rem_euclid(256) as u8
+}
+
+impl From<Color> for u32 {
+    fn from(color: Color) -> Self {
+        color.to_u32()
+    }
+}
+
+/// Treats the value as host `0x__BBGGRR` and produces an opaque color, see
+/// [`Color::from_u32`] to keep the alpha byte.
+impl From<u32> for Color {
+    fn from(color: u32) -> Self {
+        Color::from_u32_rgb(color)
+    }
+}
+
+impl From<Hsv> for Color {
+    fn from(hsv: Hsv) -> Self {
+        Color::from_hsv(hsv)
+    }
+}
+
+impl From<Hsl> for Color {
+    fn from(hsl: Hsl) -> Self {
+        Color::from_hsl(hsl)
+    }
+}
+
+impl From<Hsv> for u32 {
+    /// Packs as `0x00VVSSHH`, the argument layout of the `hsv_to_rgb` syscall.
+    fn from(hsv: Hsv) -> Self {
+        hsv.h as u32 | ((hsv.s as u32) << 8) | ((hsv.v as u32) << 16)
+    }
+}
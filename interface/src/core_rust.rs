@@ -1,84 +1,212 @@
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[inline(always)]
 /// # Safety
-pub unsafe extern "C" fn memset(data: *mut u8, val: u8, size: usize) -> *mut core::ffi::c_void {
-    for i in 0..size {
-        *data.add(i) = val;
-    }
-    core::mem::transmute(data)
+pub unsafe extern "C" fn memset(data: *mut u8, val: i32, size: usize) -> *mut u8 {
+    crate::mem::intrinsics::memset(data, val, size)
 }
 
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[inline(always)]
 /// # Safety
-pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *mut u8, size: usize) {
-    for i in 0..size {
-        *dest.add(i) = *src.add(i);
-    }
+pub unsafe extern "C" fn memcpy(dest: *mut u8, src: *const u8, size: usize) -> *mut u8 {
+    crate::mem::intrinsics::memcpy(dest, src, size)
 }
 
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[inline(always)]
 /// # Safety
-pub unsafe extern "C" fn memcmp(str1: *mut u8, str2: *mut u8, size: usize) -> core::ffi::c_int {
-    for i in 0..size {
-        match (*str1.add(i)).cmp(&*str2.add(i)) {
-            core::cmp::Ordering::Less => return -1,
-            core::cmp::Ordering::Equal => return 1,
-            core::cmp::Ordering::Greater => continue,
-        }
-    }
-    0
+pub unsafe extern "C" fn memcmp(str1: *const u8, str2: *const u8, size: usize) -> core::ffi::c_int {
+    crate::mem::intrinsics::memcmp(str1, str2, size)
+}
+
+#[cfg(target_arch = "mips")]
+#[no_mangle]
+#[inline(always)]
+/// # Safety
+pub unsafe extern "C" fn bcmp(str1: *const u8, str2: *const u8, size: usize) -> core::ffi::c_int {
+    crate::mem::intrinsics::bcmp(str1, str2, size)
+}
+
+#[cfg(target_arch = "mips")]
+#[no_mangle]
+#[inline(always)]
+/// # Safety
+pub unsafe extern "C" fn memmove(dest: *mut u8, src: *const u8, count: usize) -> *mut u8 {
+    crate::mem::intrinsics::memmove(dest, src, count)
 }
 
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[inline(always)]
 /// # Safety
-pub unsafe extern "C" fn memmove(mut dest: *mut u8, mut src: *mut u8, count: usize) {
-    if src.addr() < dest.addr() {
-        dest = dest.add(count);
-        src = src.add(count);
-        for _ in 0..count {
-            dest = dest.sub(1);
-            src = src.sub(1);
-            *dest = *src;
+pub unsafe extern "C" fn strlen(str: *const u8) -> usize {
+    crate::mem::intrinsics::strlen(str)
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Bytes [`Console`] collects before handing them to the host in one syscall.
+pub const CONSOLE_CHUNK: usize = 128;
+
+/// The emulator console as a [`core::fmt::Write`], output of any length is passed on in
+/// [`CONSOLE_CHUNK`] sized pieces so nothing has to be formatted into a buffer up front.
+///
+/// Whatever is left is flushed on drop. The host only has the one console, stdout and stderr
+/// both end up on it.
+pub struct Console {
+    // one extra byte for the terminator `print_zero_term_str` needs
+    buf: [u8; CONSOLE_CHUNK + 1],
+    len: usize,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub const fn new() -> Self {
+        Self {
+            buf: [0; CONSOLE_CHUNK + 1],
+            len: 0,
+        }
+    }
+
+    pub fn flush(&mut self) {
+        if self.len == 0 {
+            return;
         }
-    } else {
-        for _ in 0..count {
-            *dest = *src;
-            dest = dest.add(1);
-            src = src.add(1);
+        self.buf[self.len] = 0;
+        unsafe { crate::sys::raw::print_zero_term_str(self.buf.as_ptr().addr() as u32) };
+        self.len = 0;
+    }
+
+    /// Buffers `str`, which must not contain `\0`, flushing whenever the chunk is full. Chunks
+    /// are only ever cut on char boundaries so the host never sees half of a character.
+    fn push(&mut self, mut str: &str) {
+        while !str.is_empty() {
+            let room = CONSOLE_CHUNK - self.len;
+            let mut split = str.len().min(room);
+            while !str.is_char_boundary(split) {
+                split -= 1;
+            }
+            self.buf[self.len..self.len + split].copy_from_slice(&str.as_bytes()[..split]);
+            self.len += split;
+            str = &str[split..];
+            if !str.is_empty() {
+                self.flush();
+            }
         }
     }
 }
 
-pub const BUFF_SIZE: usize = 2000;
+impl core::fmt::Write for Console {
+    fn write_str(&mut self, str: &str) -> core::fmt::Result {
+        // a nul would end the chunk early on the host side, those go through `print_char`
+        let mut parts = str.split('\0');
+        if let Some(first) = parts.next() {
+            self.push(first);
+        }
+        for part in parts {
+            self.flush();
+            crate::sys::print_char('\0');
+            self.push(part);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for Console {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
+
+#[doc(hidden)]
+pub fn _print(args: core::fmt::Arguments) {
+    // `Console` never fails, only a `Display` impl returning an error could
+    let _ = core::fmt::Write::write_fmt(&mut Console::new(), args);
+}
+
+#[doc(hidden)]
+#[cfg(feature = "alloc")]
+pub fn _format(args: core::fmt::Arguments) -> alloc::string::String {
+    alloc::fmt::format(args)
+}
 
 #[macro_export]
 macro_rules! print {
     ($($arg:tt)*) => {
-        let mut buf = ['\0' as u8; $crate::core_rust::BUFF_SIZE + 1];
-        let buf = unsafe{core::slice::from_raw_parts_mut(buf.as_mut_ptr(), $crate::core_rust::BUFF_SIZE)};
-        let mut wrapper = $crate::core_rust::Wrapper::new(buf);
-        core::fmt::Write::write_fmt(&mut wrapper, core::format_args!($($arg)*)).expect("Cant write?");
-        let str = unsafe{core::str::from_utf8_unchecked(buf)};
-        $crate::sys::print_zero_term_str(str);
+        $crate::core_rust::_print(core::format_args!($($arg)*))
     };
 }
 
 #[macro_export]
 #[allow_internal_unstable(format_args_nl)]
 macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
+    ($($arg:tt)*) => {
+        $crate::core_rust::_print(core::format_args_nl!($($arg)*))
+    };
+}
+
+/// Same as [`print!`], the host has a single console.
+#[macro_export]
+macro_rules! eprint {
+    ($($arg:tt)*) => {
+        $crate::print!($($arg)*)
+    };
+}
+
+/// Same as [`println!`], the host has a single console.
+#[macro_export]
+macro_rules! eprintln {
+    ($($arg:tt)*) => {
+        $crate::println!($($arg)*)
+    };
+}
+
+/// Like `std::dbg!`, prints the expression with its file and line and returns its value.
+#[macro_export]
+macro_rules! dbg {
+    () => {
+        $crate::eprintln!("[{}:{}]", core::file!(), core::line!())
+    };
+    ($val:expr $(,)?) => {
+        match $val {
+            tmp => {
+                $crate::eprintln!(
+                    "[{}:{}] {} = {:#?}",
+                    core::file!(),
+                    core::line!(),
+                    core::stringify!($val),
+                    &tmp
+                );
+                tmp
+            }
+        }
+    };
+    ($($val:expr),+ $(,)?) => {
+        ($($crate::dbg!($val)),+,)
+    };
+}
+
+/// Like `alloc::format!`, needs the `alloc` feature.
+#[cfg(feature = "alloc")]
+#[macro_export]
+macro_rules! format {
     ($($arg:tt)*) => {
-        let mut buf = ['\0' as u8; $crate::core_rust::BUFF_SIZE + 1];
-        let buf = unsafe{core::slice::from_raw_parts_mut(buf.as_mut_ptr(), $crate::core_rust::BUFF_SIZE)};
-        let mut wrapper = $crate::core_rust::Wrapper::new(buf);
-        core::fmt::Write::write_fmt(&mut wrapper, core::format_args_nl!($($arg)*)).expect("Cant write?");
-        let str = unsafe{core::str::from_utf8_unchecked(buf)};
-        $crate::sys::print_zero_term_str(str);
+        $crate::core_rust::_format(core::format_args!($($arg)*))
     };
 }
 
+/// Writes into a fixed buffer, failing once it is full.
 pub struct Wrapper<'a> {
     buf: &'a mut [u8],
     offset: usize,
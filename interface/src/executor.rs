@@ -0,0 +1,399 @@
+//! A single threaded async executor, for code that waits on frames, time and keys and would
+//! otherwise be a state machine stepped once per frame.
+//!
+//! [`Executor::spawn`] moves futures into an [`Arena`] over a buffer the caller provides, so
+//! nothing comes from the heap. [`Executor::run`] polls a main future and every spawned one
+//! that was woken. When nothing is left to poll it waits for whatever they are waiting for: the
+//! next frame, which ends with `update_screen_vsync` and reads the keyboard, or the earliest
+//! [`sleep`]. Either one wakes every task, the futures here check for themselves whether it
+//! was theirs.
+//!
+//! Input is read once per frame into a [`Keyboard`] the futures share, read it with
+//! [`with_keyboard`] instead of draining the key queue yourself.
+//!
+//! ```ignore
+//! let mut buf = [0u8; 4096];
+//! let mut executor: Executor = Executor::new(&mut buf);
+//! executor.spawn(async {
+//!     loop {
+//!         draw_wiggly_text();
+//!         next_frame().await;
+//!     }
+//! });
+//! executor.run(async {
+//!     fade_in().await;
+//!     match select(key_pressed(KeyCode::Enter), sleep(Duration::from_secs(10))).await {
+//!         Either::Left(()) => start_game().await,
+//!         Either::Right(()) => play_demo().await,
+//!     }
+//! });
+//! ```
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    future::Future,
+    pin::{pin, Pin},
+    ptr::NonNull,
+    task::{Context, Poll, RawWaker, RawWakerVTable, Waker},
+};
+
+use crate::{
+    input::{KeyCode, Keyboard},
+    mem::Arena,
+    time::{Duration, Instant},
+};
+
+struct Reactor {
+    /// Frames ended by the executor.
+    frame: Cell<u64>,
+    /// Whether a pending future waits for the next frame.
+    frame_wanted: Cell<bool>,
+    /// The earliest [`Sleep`] a pending future waits for.
+    deadline: Cell<Option<Instant>>,
+    keyboard: UnsafeCell<Keyboard>,
+}
+
+// the guest is single threaded
+unsafe impl Sync for Reactor {}
+
+static REACTOR: Reactor = Reactor {
+    frame: Cell::new(0),
+    frame_wanted: Cell::new(false),
+    deadline: Cell::new(None),
+    keyboard: UnsafeCell::new(Keyboard::new()),
+};
+
+/// Frames the executor has ended so far.
+#[inline(always)]
+pub fn frame() -> u64 {
+    REACTOR.frame.get()
+}
+
+/// The keyboard as of the last frame.
+pub fn with_keyboard<R>(f: impl FnOnce(&Keyboard) -> R) -> R {
+    // only changed between polls
+    f(unsafe { &*REACTOR.keyboard.get() })
+}
+
+fn end_frame() {
+    let _ = crate::sys::update_screen_vsync();
+    REACTOR.frame.set(REACTOR.frame.get() + 1);
+    unsafe { (*REACTOR.keyboard.get()).update() };
+}
+
+//----------------------------------------------------------------
+
+/// Wakers set a flag next to the future, the executor must outlive them.
+fn waker(woken: &Cell<bool>) -> Waker {
+    unsafe fn clone(data: *const ()) -> RawWaker {
+        RawWaker::new(data, &VTABLE)
+    }
+    unsafe fn wake(data: *const ()) {
+        (*(data as *const Cell<bool>)).set(true);
+    }
+    unsafe fn drop(_data: *const ()) {}
+    static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake, drop);
+
+    unsafe {
+        Waker::from_raw(RawWaker::new(
+            woken as *const Cell<bool> as *const (),
+            &VTABLE,
+        ))
+    }
+}
+
+struct Slot<'a> {
+    future: Cell<Option<NonNull<dyn Future<Output = ()> + 'a>>>,
+    woken: Cell<bool>,
+}
+
+/// Runs up to `TASKS` spawned futures next to the one given to [`Executor::run`].
+///
+/// Finished tasks free their slot but their bytes in the arena only come back when the
+/// executor is dropped, which also drops whatever didn't finish.
+pub struct Executor<'a, const TASKS: usize = 16> {
+    arena: Arena<'a>,
+    tasks: [Slot<'a>; TASKS],
+}
+
+impl<'a, const TASKS: usize> Executor<'a, TASKS> {
+    /// Spawned futures are moved into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            arena: Arena::new(buf),
+            tasks: core::array::from_fn(|_| Slot {
+                future: Cell::new(None),
+                woken: Cell::new(false),
+            }),
+        }
+    }
+
+    /// Starts polling `future` with the next [`Executor::run`], gives it back when all the
+    /// slots are taken or it doesn't fit the arena.
+    pub fn spawn<F: Future<Output = ()> + 'a>(&self, future: F) -> Result<(), F> {
+        let Some(slot) = self.tasks.iter().find(|slot| slot.future.get().is_none()) else {
+            return Err(future);
+        };
+        let future: &mut (dyn Future<Output = ()> + 'a) = self.arena.alloc(future)?;
+        slot.future.set(Some(NonNull::from(future)));
+        slot.woken.set(true);
+        Ok(())
+    }
+
+    /// Spawned futures that haven't finished.
+    pub fn pending(&self) -> usize {
+        self.tasks
+            .iter()
+            .filter(|slot| slot.future.get().is_some())
+            .count()
+    }
+
+    /// Bytes of the arena left for [`Executor::spawn`].
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.arena.remaining()
+    }
+
+    /// Polls `future` and the spawned ones until `future` is done. Spawned ones that are still
+    /// pending then carry on with the next `run`.
+    pub fn run<F: Future>(&mut self, future: F) -> F::Output {
+        let mut future = pin!(future);
+        let woken = Cell::new(true);
+        loop {
+            if woken.replace(false) {
+                let waker = waker(&woken);
+                if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker))
+                {
+                    return output;
+                }
+            }
+            self.poll_tasks();
+            if woken.get() || self.tasks.iter().any(|slot| slot.woken.get()) {
+                continue;
+            }
+
+            wait();
+            woken.set(true);
+            for slot in &self.tasks {
+                slot.woken.set(true);
+            }
+        }
+    }
+
+    fn poll_tasks(&self) {
+        for slot in &self.tasks {
+            let Some(mut future) = slot.future.get() else {
+                continue;
+            };
+            if !slot.woken.replace(false) {
+                continue;
+            }
+            let waker = waker(&slot.woken);
+            // the arena never moves it
+            let future = unsafe { Pin::new_unchecked(future.as_mut()) };
+            if future.poll(&mut Context::from_waker(&waker)).is_ready() {
+                slot.future.set(None);
+            }
+        }
+    }
+}
+
+/// Blocks until the first thing anything is waiting for has happened.
+fn wait() {
+    let deadline = REACTOR.deadline.get();
+    if deadline.is_some_and(|deadline| deadline <= Instant::now()) {
+        REACTOR.deadline.set(None);
+    } else if REACTOR.frame_wanted.replace(false) {
+        end_frame();
+    } else if let Some(deadline) = deadline {
+        crate::time::sleep_until(deadline);
+        REACTOR.deadline.set(None);
+    } else {
+        panic!("executor: every future is waiting and none of them on a frame or a timer");
+    }
+}
+
+/// Runs `future` to the end without spawning anything next to it.
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    Executor::<0>::new(&mut []).run(future)
+}
+
+//----------------------------------------------------------------
+
+/// Waits for the executor to end the frame, see [`next_frame`].
+pub struct NextFrame {
+    after: Option<u64>,
+}
+
+/// Done once the executor has called `update_screen_vsync` and read the keyboard.
+pub fn next_frame() -> NextFrame {
+    NextFrame { after: None }
+}
+
+impl Future for NextFrame {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let after = *self.after.get_or_insert(frame());
+        if frame() > after {
+            return Poll::Ready(());
+        }
+        REACTOR.frame_wanted.set(true);
+        Poll::Pending
+    }
+}
+
+/// Waits for a deadline, see [`sleep`].
+pub struct Sleep {
+    deadline: Instant,
+}
+
+/// Done once `duration` from now has passed. The executor may notice up to a frame late when
+/// something else waits for a frame.
+pub fn sleep(duration: Duration) -> Sleep {
+    sleep_until(Instant::now().saturating_add(duration))
+}
+
+/// Done once `deadline` has passed.
+pub fn sleep_until(deadline: Instant) -> Sleep {
+    Sleep { deadline }
+}
+
+impl Future for Sleep {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(());
+        }
+        let deadline = REACTOR
+            .deadline
+            .get()
+            .map_or(self.deadline, |d| d.min(self.deadline));
+        REACTOR.deadline.set(Some(deadline));
+        Poll::Pending
+    }
+}
+
+/// Waits for a key to go down, see [`key_pressed`].
+pub struct KeyPressed {
+    key: KeyCode,
+    after: Option<u64>,
+}
+
+/// Done at the end of the first frame the key went down in, holding it before doesn't count.
+pub fn key_pressed(key: KeyCode) -> KeyPressed {
+    KeyPressed { key, after: None }
+}
+
+impl Future for KeyPressed {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+        let after = *self.after.get_or_insert(frame());
+        if frame() > after && with_keyboard(|keyboard| keyboard.just_pressed(self.key)) {
+            return Poll::Ready(());
+        }
+        REACTOR.frame_wanted.set(true);
+        Poll::Pending
+    }
+}
+
+/// Lets everything else that was woken run first, see [`yield_now`].
+pub struct YieldNow {
+    yielded: bool,
+}
+
+pub fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+//----------------------------------------------------------------
+
+/// Both outputs of a [`join`].
+pub struct Join<A: Future, B: Future> {
+    a: A,
+    b: B,
+    a_output: Option<A::Output>,
+    b_output: Option<B::Output>,
+}
+
+/// Polls both until both are done.
+pub fn join<A: Future, B: Future>(a: A, b: B) -> Join<A, B> {
+    Join {
+        a,
+        b,
+        a_output: None,
+        b_output: None,
+    }
+}
+
+impl<A: Future, B: Future> Future for Join<A, B> {
+    type Output = (A::Output, B::Output);
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the futures are never moved out, the outputs aren't pinned
+        let this = unsafe { self.get_unchecked_mut() };
+        if this.a_output.is_none() {
+            if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+                this.a_output = Some(output);
+            }
+        }
+        if this.b_output.is_none() {
+            if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+                this.b_output = Some(output);
+            }
+        }
+        if this.a_output.is_some() && this.b_output.is_some() {
+            return Poll::Ready((this.a_output.take().unwrap(), this.b_output.take().unwrap()));
+        }
+        Poll::Pending
+    }
+}
+
+/// Which of the two futures of a [`select`] finished first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Either<A, B> {
+    Left(A),
+    Right(B),
+}
+
+/// The output of whichever finishes first, see [`select`].
+pub struct Select<A, B> {
+    a: A,
+    b: B,
+}
+
+/// Polls both until one is done, `a` first, and drops the other one with the `Select`.
+pub fn select<A: Future, B: Future>(a: A, b: B) -> Select<A, B> {
+    Select { a, b }
+}
+
+impl<A: Future, B: Future> Future for Select<A, B> {
+    type Output = Either<A::Output, B::Output>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        // the futures are never moved out
+        let this = unsafe { self.get_unchecked_mut() };
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.a) }.poll(cx) {
+            return Poll::Ready(Either::Left(output));
+        }
+        if let Poll::Ready(output) = unsafe { Pin::new_unchecked(&mut this.b) }.poll(cx) {
+            return Poll::Ready(Either::Right(output));
+        }
+        Poll::Pending
+    }
+}
@@ -0,0 +1,349 @@
+//! Files in a sandboxed directory on the host.
+//!
+//! Paths are `/` separated and always relative to the sandbox root, a leading `/` is ignored and
+//! `..` components are rejected with [`Error::InvalidPath`]. Handles are closed when the
+//! [`File`] or [`ReadDir`] owning them is dropped. Errors are [`crate::sys::SysError`], with the
+//! same codes as every other syscall.
+
+use core::fmt;
+
+pub use crate::sys::SysError as Error;
+
+pub type Result<T> = crate::sys::SysResult<T>;
+
+//--------------------------------------------------------------------------------------------------------
+
+pub const OPEN_READ: u32 = 1 << 0;
+pub const OPEN_WRITE: u32 = 1 << 1;
+pub const OPEN_APPEND: u32 = 1 << 2;
+pub const OPEN_CREATE: u32 = 1 << 3;
+pub const OPEN_TRUNCATE: u32 = 1 << 4;
+pub const OPEN_CREATE_NEW: u32 = 1 << 5;
+/// Opens a directory for [`crate::sys::fs_read_dir`] instead of a file.
+pub const OPEN_DIRECTORY: u32 = 1 << 6;
+
+/// Same meaning as `std::fs::OpenOptions`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct OpenOptions {
+    flags: u32,
+}
+
+impl OpenOptions {
+    pub const fn new() -> Self {
+        Self { flags: 0 }
+    }
+
+    #[inline(always)]
+    const fn flag(mut self, flag: u32, on: bool) -> Self {
+        if on {
+            self.flags |= flag;
+        } else {
+            self.flags &= !flag;
+        }
+        self
+    }
+
+    pub const fn read(self, read: bool) -> Self {
+        self.flag(OPEN_READ, read)
+    }
+
+    pub const fn write(self, write: bool) -> Self {
+        self.flag(OPEN_WRITE, write)
+    }
+
+    pub const fn append(self, append: bool) -> Self {
+        self.flag(OPEN_APPEND, append)
+    }
+
+    pub const fn create(self, create: bool) -> Self {
+        self.flag(OPEN_CREATE, create)
+    }
+
+    pub const fn truncate(self, truncate: bool) -> Self {
+        self.flag(OPEN_TRUNCATE, truncate)
+    }
+
+    pub const fn create_new(self, create_new: bool) -> Self {
+        self.flag(OPEN_CREATE_NEW, create_new)
+    }
+
+    #[inline(always)]
+    pub const fn flags(&self) -> u32 {
+        self.flags
+    }
+
+    pub fn open(&self, path: &str) -> Result<File> {
+        let handle = crate::sys::fs_open(path, self.flags)?;
+        Ok(File { handle })
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum SeekFrom {
+    Start(u32),
+    End(i32),
+    Current(i32),
+}
+
+/// An open file, closed on drop.
+#[derive(Debug)]
+pub struct File {
+    handle: u32,
+}
+
+impl File {
+    /// Opens an existing file for reading.
+    pub fn open(path: &str) -> Result<Self> {
+        OpenOptions::new().read(true).open(path)
+    }
+
+    /// Opens a file for writing, creating it or truncating what was there.
+    pub fn create(path: &str) -> Result<Self> {
+        OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)
+    }
+
+    #[inline(always)]
+    pub fn handle(&self) -> u32 {
+        self.handle
+    }
+
+    /// Reads up to `buf.len()` bytes, `Ok(0)` at the end of the file.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize> {
+        crate::sys::fs_read(self.handle, buf).map(|read| read as usize)
+    }
+
+    pub fn read_exact(&mut self, mut buf: &mut [u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.read(buf)? {
+                0 => return Err(Error::UnexpectedEof),
+                read => buf = &mut buf[read..],
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&mut self, buf: &[u8]) -> Result<usize> {
+        crate::sys::fs_write(self.handle, buf).map(|written| written as usize)
+    }
+
+    pub fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+        while !buf.is_empty() {
+            match self.write(buf)? {
+                0 => return Err(Error::UnexpectedEof),
+                written => buf = &buf[written..],
+            }
+        }
+        Ok(())
+    }
+
+    /// Moves the cursor and returns the new position from the start of the file.
+    pub fn seek(&mut self, pos: SeekFrom) -> Result<u32> {
+        let (offset, whence) = match pos {
+            SeekFrom::Start(offset) => (offset as i32, 0),
+            SeekFrom::Current(offset) => (offset, 1),
+            SeekFrom::End(offset) => (offset, 2),
+        };
+        crate::sys::fs_seek(self.handle, offset, whence)
+    }
+
+    #[inline(always)]
+    pub fn stream_position(&mut self) -> Result<u32> {
+        self.seek(SeekFrom::Current(0))
+    }
+}
+
+impl fmt::Write for File {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_all(s.as_bytes()).map_err(|_| fmt::Error)
+    }
+}
+
+impl Drop for File {
+    fn drop(&mut self) {
+        let _ = crate::sys::fs_close(self.handle);
+    }
+}
+
+/// Reads the start of the file at `path` into `buf`, returns how many bytes were read.
+pub fn read(path: &str, buf: &mut [u8]) -> Result<usize> {
+    let mut file = File::open(path)?;
+    let mut len = 0;
+    while len < buf.len() {
+        match file.read(&mut buf[len..])? {
+            0 => break,
+            read => len += read,
+        }
+    }
+    Ok(len)
+}
+
+/// Replaces the contents of the file at `path` with `data`.
+pub fn write(path: &str, data: &[u8]) -> Result<()> {
+    File::create(path)?.write_all(data)
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+pub const KIND_FILE: u8 = 0;
+pub const KIND_DIRECTORY: u8 = 1;
+
+/// Layout the host writes for `fs_stat`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RawStat {
+    pub size: u32,
+    pub kind: u8,
+    pub readonly: u8,
+    pub _reserved: [u8; 2],
+    /// Seconds since the unix epoch, `0` when the host doesn't know.
+    pub modified: u32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum FileType {
+    File,
+    Directory,
+}
+
+impl FileType {
+    fn from_raw(kind: u8) -> Self {
+        if kind == KIND_DIRECTORY {
+            FileType::Directory
+        } else {
+            FileType::File
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Metadata {
+    pub len: u32,
+    pub file_type: FileType,
+    pub readonly: bool,
+    /// Seconds since the unix epoch, `0` when the host doesn't know.
+    pub modified: u32,
+}
+
+impl Metadata {
+    #[inline(always)]
+    pub fn is_file(&self) -> bool {
+        self.file_type == FileType::File
+    }
+
+    #[inline(always)]
+    pub fn is_dir(&self) -> bool {
+        self.file_type == FileType::Directory
+    }
+}
+
+pub fn metadata(path: &str) -> Result<Metadata> {
+    let mut raw = RawStat::default();
+    crate::sys::fs_stat(path, &mut raw)?;
+    Ok(Metadata {
+        len: raw.size,
+        file_type: FileType::from_raw(raw.kind),
+        readonly: raw.readonly != 0,
+        modified: raw.modified,
+    })
+}
+
+/// Entries with longer names are skipped by the host.
+pub const NAME_MAX: usize = 60;
+
+/// Layout the host writes for `fs_read_dir`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct RawDirEntry {
+    pub name: [u8; NAME_MAX],
+    pub name_len: u8,
+    pub kind: u8,
+    pub _reserved: [u8; 2],
+    pub size: u32,
+}
+
+impl Default for RawDirEntry {
+    fn default() -> Self {
+        Self {
+            name: [0; NAME_MAX],
+            name_len: 0,
+            kind: 0,
+            _reserved: [0; 2],
+            size: 0,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct DirEntry {
+    raw: RawDirEntry,
+}
+
+impl DirEntry {
+    /// The name inside its directory, not the full path.
+    pub fn name(&self) -> &str {
+        let len = (self.raw.name_len as usize).min(NAME_MAX);
+        core::str::from_utf8(&self.raw.name[..len]).unwrap_or("")
+    }
+
+    #[inline(always)]
+    pub fn file_type(&self) -> FileType {
+        FileType::from_raw(self.raw.kind)
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> u32 {
+        self.raw.size
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.raw.size == 0
+    }
+}
+
+/// Iterator over the entries of a directory, in whatever order the host lists them.
+#[derive(Debug)]
+pub struct ReadDir {
+    handle: u32,
+    done: bool,
+}
+
+pub fn read_dir(path: &str) -> Result<ReadDir> {
+    let handle = crate::sys::fs_open(path, OPEN_DIRECTORY)?;
+    Ok(ReadDir {
+        handle,
+        done: false,
+    })
+}
+
+impl Iterator for ReadDir {
+    type Item = Result<DirEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let mut raw = RawDirEntry::default();
+        match crate::sys::fs_read_dir(self.handle, &mut raw) {
+            Ok(false) => {
+                self.done = true;
+                None
+            }
+            Ok(true) => Some(Ok(DirEntry { raw })),
+            Err(err) => {
+                self.done = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl Drop for ReadDir {
+    fn drop(&mut self) {
+        let _ = crate::sys::fs_close(self.handle);
+    }
+}
@@ -0,0 +1,720 @@
+//! A general purpose allocator for the guest, two level segregated fit (TLSF).
+//!
+//! Free blocks are kept in lists by size class, a power of two split into [`SUB_CLASSES`]. Two
+//! bitmaps say which lists have blocks, so finding one that fits is a couple of
+//! `trailing_zeros` no matter how many blocks there are. Every block starts with a header holding
+//! its size, free ones also end with it so freeing merges with both neighbours right away.
+//! `realloc` grows into a free neighbour or shrinks in place before it falls back to copying.
+//!
+//! The heap is [`crate::mem::MemoryMap::heap`], below the stack. Allocations past its end fail
+//! instead of running into the stack, [`Heap::set_oom_handler`] decides what happens then. By
+//! default the request, the heap stats and the caller are printed and the program halts.
+//!
+//! [`Heap::stats`] counts what is live and how broken up the free memory is, [`Heap::dump`] lists
+//! every block and [`Heap::check_leaks`] the ones still allocated. With
+//! [`Heap::track_callers`] on each block remembers the return address of the allocation, so
+//! those say where it came from. Freeing a pointer that isn't an allocation panics with what is
+//! wrong with it instead of corrupting the lists.
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: interface::heap::Heap = interface::heap::Heap::new();
+//! ```
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::UnsafeCell,
+    mem::size_of,
+    ptr,
+};
+
+/// Blocks and payloads are aligned to this.
+pub const ALIGN: usize = 8;
+/// Size, flags and the caller, keeps payloads [`ALIGN`]ed.
+const HEADER: usize = 2 * size_of::<usize>();
+/// Header, the free list links and the footer of a free block, 24 bytes on the guest.
+pub const MIN_BLOCK: usize = (size_of::<Block>() + size_of::<usize>() + ALIGN - 1) & !(ALIGN - 1);
+
+/// Each power of two size class is split into this many lists.
+pub const SUB_CLASSES: usize = 1 << SUB_CLASS_BITS;
+const SUB_CLASS_BITS: u32 = 2;
+/// Blocks below this share the first class, split linearly.
+const SMALL_BLOCK: usize = 64;
+const SMALL_STEP: usize = SMALL_BLOCK / SUB_CLASSES;
+/// Enough classes for blocks up to 2 GiB.
+const CLASSES: usize = 27;
+
+const USED: usize = 1 << 0;
+const PREV_USED: usize = 1 << 1;
+const FLAGS: usize = USED | PREV_USED;
+
+#[repr(C)]
+struct Block {
+    /// Size of the whole block, header included, and the flags in the low bits.
+    size: usize,
+    /// `$ra` of the allocation when callers are tracked, otherwise 0.
+    caller: usize,
+    // only in free blocks
+    next_free: *mut Block,
+    prev_free: *mut Block,
+}
+
+impl Block {
+    #[inline(always)]
+    unsafe fn size(this: *mut Block) -> usize {
+        (*this).size & !FLAGS
+    }
+
+    #[inline(always)]
+    unsafe fn is_used(this: *mut Block) -> bool {
+        (*this).size & USED != 0
+    }
+
+    #[inline(always)]
+    unsafe fn is_prev_used(this: *mut Block) -> bool {
+        (*this).size & PREV_USED != 0
+    }
+
+    #[inline(always)]
+    unsafe fn next(this: *mut Block) -> *mut Block {
+        this.byte_add(Self::size(this))
+    }
+
+    /// Only valid when the previous block is free, from its footer.
+    #[inline(always)]
+    unsafe fn prev(this: *mut Block) -> *mut Block {
+        let prev_size = *(this as *mut usize).sub(1);
+        this.byte_sub(prev_size)
+    }
+
+    #[inline(always)]
+    unsafe fn payload(this: *mut Block) -> *mut u8 {
+        (this as *mut u8).add(HEADER)
+    }
+
+    #[inline(always)]
+    unsafe fn from_payload(ptr: *mut u8) -> *mut Block {
+        ptr.sub(HEADER) as *mut Block
+    }
+
+    /// Marks the block free and writes its footer, the next block learns it.
+    unsafe fn set_free(this: *mut Block, size: usize) {
+        (*this).size = size | ((*this).size & PREV_USED);
+        *(this.byte_add(size) as *mut usize).sub(1) = size;
+        (*Self::next(this)).size &= !PREV_USED;
+    }
+
+    unsafe fn set_used(this: *mut Block, size: usize) {
+        (*this).size = size | USED | ((*this).size & PREV_USED);
+        (*Self::next(this)).size |= PREV_USED;
+    }
+}
+
+/// Block size for `size` bytes of payload.
+#[inline(always)]
+const fn block_size(size: usize) -> usize {
+    let size = (size + HEADER + ALIGN - 1) & !(ALIGN - 1);
+    if size < MIN_BLOCK {
+        MIN_BLOCK
+    } else {
+        size
+    }
+}
+
+/// The list a free block of `size` goes in.
+#[inline(always)]
+fn class_of(size: usize) -> (usize, usize) {
+    if size < SMALL_BLOCK {
+        (0, size / SMALL_STEP)
+    } else {
+        let log = size.ilog2();
+        let sub = (size >> (log - SUB_CLASS_BITS)) & (SUB_CLASSES - 1);
+        ((log - SMALL_BLOCK.ilog2() + 1) as usize, sub)
+    }
+}
+
+/// The first list where every block is at least `size`.
+#[inline(always)]
+fn class_fitting(size: usize) -> (usize, usize) {
+    let size = if size < SMALL_BLOCK {
+        (size + SMALL_STEP - 1) & !(SMALL_STEP - 1)
+    } else {
+        size + (1 << (size.ilog2() - SUB_CLASS_BITS)) - 1
+    };
+    class_of(size)
+}
+
+struct State {
+    start: usize,
+    end: usize,
+    initialized: bool,
+    /// Bit `n` when `sub_class_map[n]` isn't empty.
+    class_map: u32,
+    /// Bit `n` when `free[class][n]` has blocks.
+    sub_class_map: [u8; CLASSES],
+    free: [[*mut Block; SUB_CLASSES]; CLASSES],
+    track_callers: bool,
+    oom_handler: OomHandler,
+    allocations: usize,
+    /// Whole blocks, headers included.
+    used_bytes: usize,
+    peak_bytes: usize,
+}
+
+/// A snapshot of the heap from [`Heap::stats`]. Sizes are of whole blocks, headers and padding
+/// included, so they add up to `heap_bytes`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub heap_bytes: usize,
+    /// Live allocations.
+    pub allocations: usize,
+    pub used_bytes: usize,
+    /// The most `used_bytes` has ever been.
+    pub peak_bytes: usize,
+    pub free_bytes: usize,
+    /// The biggest block an allocation could get right now.
+    pub largest_free: usize,
+}
+
+impl Stats {
+    /// How much of the free memory is outside the largest free block, 0 when it is all in one
+    /// piece and close to 100 when it is scattered in small ones.
+    pub fn fragmentation_percent(&self) -> u32 {
+        if self.free_bytes == 0 {
+            return 0;
+        }
+        100 - (self.largest_free as u64 * 100 / self.free_bytes as u64) as u32
+    }
+}
+
+impl core::fmt::Display for Stats {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "{} allocations, {}/{} bytes used (peak {}), {} free, largest {} ({}% fragmented)",
+            self.allocations,
+            self.used_bytes,
+            self.heap_bytes,
+            self.peak_bytes,
+            self.free_bytes,
+            self.largest_free,
+            self.fragmentation_percent()
+        )
+    }
+}
+
+/// One block as [`Heap::walk`] sees it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockInfo {
+    /// Where the payload starts, what the allocation returned for used blocks.
+    pub addr: usize,
+    /// The whole block, header included.
+    pub size: usize,
+    pub used: bool,
+    /// Return address of the allocation, for used blocks while callers were tracked.
+    pub caller: Option<usize>,
+}
+
+impl core::fmt::Display for BlockInfo {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let state = if self.used { "used" } else { "free" };
+        write!(f, "{:08x} {:>8} {}", self.addr, self.size, state)?;
+        if let Some(caller) = self.caller {
+            write_caller(f, caller)?;
+        }
+        Ok(())
+    }
+}
+
+fn write_caller(f: &mut core::fmt::Formatter<'_>, caller: usize) -> core::fmt::Result {
+    write!(f, " from {:08x}", caller)?;
+    if let Some(symbol) = crate::panic::symbolize(caller as u32) {
+        write!(f, " {}+{:#x}", symbol.name, symbol.offset)?;
+    }
+    Ok(())
+}
+
+/// An allocation the heap had no room for, what an [`OomHandler`] gets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutOfMemory {
+    pub layout: Layout,
+    /// Return address of the call into the allocator, see [`Heap::track_callers`].
+    pub caller: Option<usize>,
+    pub stats: Stats,
+}
+
+impl core::fmt::Display for OutOfMemory {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "out of memory allocating {} bytes aligned to {}",
+            self.layout.size(),
+            self.layout.align()
+        )?;
+        if let Some(caller) = self.caller {
+            write_caller(f, caller)?;
+        }
+        write!(f, "\nheap: {}", self.stats)
+    }
+}
+
+/// Called when an allocation fails, before null is returned. Returning lets `try_reserve` and
+/// friends see the failure, everything else ends up in `handle_alloc_error` and panics.
+pub type OomHandler = fn(&OutOfMemory);
+
+/// Prints what failed and halts.
+pub fn default_oom_handler(oom: &OutOfMemory) {
+    crate::println!("{}", oom);
+    crate::log::flush();
+    crate::sys::halt();
+}
+
+/// Does nothing, failed allocations return null.
+pub fn ignore_oom(_: &OutOfMemory) {}
+
+pub struct Heap {
+    state: UnsafeCell<State>,
+}
+
+// the guest is single threaded, interrupts are off while the heap is touched
+unsafe impl Sync for Heap {}
+
+impl Default for Heap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Heap {
+    /// A heap over [`crate::mem::MemoryMap::heap`], set up on the first allocation.
+    pub const fn new() -> Self {
+        Self {
+            state: UnsafeCell::new(State {
+                start: 0,
+                end: 0,
+                initialized: false,
+                class_map: 0,
+                sub_class_map: [0; CLASSES],
+                free: [[ptr::null_mut(); SUB_CLASSES]; CLASSES],
+                track_callers: false,
+                oom_handler: default_oom_handler,
+                allocations: 0,
+                used_bytes: 0,
+                peak_bytes: 0,
+            }),
+        }
+    }
+
+    /// Uses `start..end` instead of the linker symbols, has to come before the first allocation.
+    ///
+    /// # Safety
+    /// The memory has to be unused and stay that way other than through this heap.
+    pub unsafe fn init(&self, start: usize, end: usize) {
+        locked(|| (*self.state.get()).init(start, end));
+    }
+
+    /// The start and end of the memory the heap manages, `(0, 0)` before it is set up.
+    pub fn bounds(&self) -> (usize, usize) {
+        let state = unsafe { &*self.state.get() };
+        (state.start, state.end)
+    }
+
+    /// Whether new allocations remember who made them, for [`Heap::dump`] and
+    /// [`Heap::check_leaks`]. It is the return address of the call into the allocator, with
+    /// `alloc`'s wrappers inlined that is usually the function that allocated.
+    pub fn track_callers(&self, track: bool) {
+        locked(|| unsafe { (*self.state.get()).track_callers = track });
+    }
+
+    /// Runs `handler` when an allocation fails, returns the one it replaces.
+    pub fn set_oom_handler(&self, handler: OomHandler) -> OomHandler {
+        locked(|| unsafe { core::mem::replace(&mut (*self.state.get()).oom_handler, handler) })
+    }
+
+    pub fn stats(&self) -> Stats {
+        self.with_state(|state| {
+            let heap_bytes = (state.end - state.start).saturating_sub(HEADER);
+            Stats {
+                heap_bytes,
+                allocations: state.allocations,
+                used_bytes: state.used_bytes,
+                peak_bytes: state.peak_bytes,
+                free_bytes: heap_bytes - state.used_bytes,
+                largest_free: unsafe { state.largest_free() },
+            }
+        })
+    }
+
+    /// Calls `f` with every block from the start of the heap to the end. Interrupts stay off
+    /// and `f` must not allocate.
+    pub fn walk(&self, mut f: impl FnMut(BlockInfo)) {
+        self.with_state(|state| unsafe {
+            if state.start == state.end {
+                return;
+            }
+            let mut block = state.start as *mut Block;
+            while Block::size(block) != 0 {
+                let used = Block::is_used(block);
+                f(BlockInfo {
+                    addr: Block::payload(block) as usize,
+                    size: Block::size(block),
+                    used,
+                    caller: Some((*block).caller).filter(|caller| used && *caller != 0),
+                });
+                block = Block::next(block);
+            }
+        })
+    }
+
+    /// Prints the stats and then every block.
+    pub fn dump(&self) {
+        let stats = self.stats();
+        let (start, end) = self.bounds();
+        crate::println!("heap {:08x}..{:08x}: {}", start, end, stats);
+        self.walk(|block| crate::println!("  {}", block));
+    }
+
+    /// Prints every allocation still live and returns how many there are, for the end of the
+    /// program when everything should have been freed.
+    pub fn check_leaks(&self) -> usize {
+        let stats = self.stats();
+        if stats.allocations == 0 {
+            return 0;
+        }
+        crate::println!(
+            "heap: {} allocations ({} bytes) leaked",
+            stats.allocations,
+            stats.used_bytes
+        );
+        self.walk(|block| {
+            if block.used {
+                crate::println!("  {}", block);
+            }
+        });
+        stats.allocations
+    }
+
+    #[inline(always)]
+    fn with_state<R>(&self, f: impl FnOnce(&mut State) -> R) -> R {
+        locked(|| {
+            let state = unsafe { &mut *self.state.get() };
+            if !state.initialized {
+                let heap = crate::mem::memory_map().heap;
+                unsafe { state.init(heap.start, heap.end) };
+            }
+            f(state)
+        })
+    }
+
+    /// Allocates on behalf of `caller`, running the OOM handler when there is no room.
+    unsafe fn allocate(&self, layout: Layout, caller: usize) -> *mut u8 {
+        let ptr = self.with_state(|state| state.alloc(layout, caller));
+        if ptr.is_null() {
+            // outside of `with_state`, the handler may look at the heap
+            let handler = self.with_state(|state| state.oom_handler);
+            handler(&OutOfMemory {
+                layout,
+                caller: Some(caller).filter(|caller| *caller != 0),
+                stats: self.stats(),
+            });
+        }
+        ptr
+    }
+}
+
+/// Runs `f` with interrupts off once `trap::install` has run. Before that nothing can interrupt
+/// it and COP0 is left alone, so the heap can be used that early.
+#[inline(always)]
+fn locked<R>(f: impl FnOnce() -> R) -> R {
+    if crate::trap::is_installed() {
+        crate::trap::without_interrupts(f)
+    } else {
+        f()
+    }
+}
+
+impl State {
+    unsafe fn init(&mut self, start: usize, end: usize) {
+        self.initialized = true;
+        let start = (start + ALIGN - 1) & !(ALIGN - 1);
+        let end = end & !(ALIGN - 1);
+        // room for one block and the sentinel
+        if end < start || end - start < MIN_BLOCK + HEADER {
+            return;
+        }
+        self.start = start;
+        self.end = end;
+
+        // a used block of size 0 at the end, nothing merges past it
+        let sentinel = (end - HEADER) as *mut Block;
+        (*sentinel).size = USED;
+        let first = start as *mut Block;
+        (*first).size = PREV_USED;
+        Block::set_free(first, end - HEADER - start);
+        self.insert(first);
+    }
+
+    unsafe fn insert(&mut self, block: *mut Block) {
+        let (class, sub) = class_of(Block::size(block));
+        let head = self.free[class][sub];
+        (*block).next_free = head;
+        (*block).prev_free = ptr::null_mut();
+        if !head.is_null() {
+            (*head).prev_free = block;
+        }
+        self.free[class][sub] = block;
+        self.class_map |= 1 << class;
+        self.sub_class_map[class] |= 1 << sub;
+    }
+
+    unsafe fn remove(&mut self, block: *mut Block) {
+        let (class, sub) = class_of(Block::size(block));
+        let (next, prev) = ((*block).next_free, (*block).prev_free);
+        if !next.is_null() {
+            (*next).prev_free = prev;
+        }
+        if prev.is_null() {
+            self.free[class][sub] = next;
+            if next.is_null() {
+                self.sub_class_map[class] &= !(1 << sub);
+                if self.sub_class_map[class] == 0 {
+                    self.class_map &= !(1 << class);
+                }
+            }
+        } else {
+            (*prev).next_free = next;
+        }
+    }
+
+    /// A free block of at least `size`, taken off its list.
+    unsafe fn take_fitting(&mut self, size: usize) -> Option<*mut Block> {
+        let (mut class, mut sub) = class_fitting(size);
+        if class >= CLASSES {
+            return self.take_first_fit(size);
+        }
+        let mut subs = self.sub_class_map[class] as u32 & (!0 << sub);
+        if subs == 0 {
+            let classes = self.class_map & (!0u32).checked_shl(class as u32 + 1).unwrap_or(0);
+            if classes == 0 {
+                return self.take_first_fit(size);
+            }
+            class = classes.trailing_zeros() as usize;
+            subs = self.sub_class_map[class] as u32;
+        }
+        sub = subs.trailing_zeros() as usize;
+        let block = self.free[class][sub];
+        self.remove(block);
+        Some(block)
+    }
+
+    /// Looks through the list `size` itself falls in, for when nothing bigger is free. Slower,
+    /// but otherwise a block only a little larger than the request goes unused.
+    unsafe fn take_first_fit(&mut self, size: usize) -> Option<*mut Block> {
+        let (class, sub) = class_of(size);
+        let mut block = *self.free.get(class)?.get(sub)?;
+        while !block.is_null() {
+            if Block::size(block) >= size {
+                self.remove(block);
+                return Some(block);
+            }
+            block = (*block).next_free;
+        }
+        None
+    }
+
+    /// The largest block on the highest list that has any.
+    unsafe fn largest_free(&self) -> usize {
+        if self.class_map == 0 {
+            return 0;
+        }
+        let class = self.class_map.ilog2() as usize;
+        let sub = (self.sub_class_map[class] as u32).ilog2() as usize;
+        let mut largest = 0;
+        let mut block = self.free[class][sub];
+        while !block.is_null() {
+            largest = largest.max(Block::size(block));
+            block = (*block).next_free;
+        }
+        largest
+    }
+
+    /// The block of a pointer handed to `dealloc` or `realloc`, panics when it can't be one
+    /// this heap gave out.
+    unsafe fn block_of(&self, ptr: *mut u8) -> *mut Block {
+        let addr = ptr as usize;
+        if addr < self.start + HEADER || addr >= self.end {
+            panic!(
+                "heap: freeing {:p}, outside of the heap {:08x}..{:08x}",
+                ptr, self.start, self.end
+            );
+        }
+        if addr & (ALIGN - 1) != 0 {
+            panic!("heap: freeing {:p}, not aligned like an allocation", ptr);
+        }
+        let block = Block::from_payload(ptr);
+        let size = Block::size(block);
+        if size < MIN_BLOCK || size & (ALIGN - 1) != 0 || size > self.end - block as usize - HEADER
+        {
+            panic!(
+                "heap: freeing {:p}, not an allocation or its header was overwritten (size word {:#x})",
+                ptr,
+                (*block).size
+            );
+        }
+        if !Block::is_used(block) {
+            panic!(
+                "heap: freeing {:p} twice, the block of {} bytes is already free",
+                ptr, size
+            );
+        }
+        block
+    }
+
+    /// Counts `block` in and hands out its payload.
+    unsafe fn hand_out(&mut self, block: *mut Block, caller: usize) -> *mut u8 {
+        let size = Block::size(block);
+        Block::set_used(block, size);
+        (*block).caller = if self.track_callers { caller } else { 0 };
+        self.allocations += 1;
+        self.used_bytes += size;
+        self.peak_bytes = self.peak_bytes.max(self.used_bytes);
+        Block::payload(block)
+    }
+
+    /// Cuts `block` down to `size` and frees the rest, when there is enough of it for a block.
+    unsafe fn split(&mut self, block: *mut Block, size: usize) {
+        let rest_size = Block::size(block) - size;
+        if rest_size < MIN_BLOCK {
+            return;
+        }
+        (*block).size = size | ((*block).size & FLAGS);
+        let rest = Block::next(block);
+        // the block in front is used or being handed out
+        (*rest).size = PREV_USED;
+        self.free_block(rest, rest_size);
+    }
+
+    /// Merges with the free neighbours and puts the result on its list.
+    unsafe fn free_block(&mut self, mut block: *mut Block, mut size: usize) {
+        let next = block.byte_add(size);
+        if !Block::is_used(next) {
+            self.remove(next);
+            size += Block::size(next);
+        }
+        if !Block::is_prev_used(block) {
+            let prev = Block::prev(block);
+            self.remove(prev);
+            size += Block::size(prev);
+            block = prev;
+        }
+        Block::set_free(block, size);
+        self.insert(block);
+    }
+
+    unsafe fn alloc(&mut self, layout: Layout, caller: usize) -> *mut u8 {
+        if layout.size() > isize::MAX as usize - MIN_BLOCK - layout.align() {
+            return ptr::null_mut();
+        }
+        let size = block_size(layout.size());
+        if layout.align() <= ALIGN {
+            let Some(block) = self.take_fitting(size) else {
+                return ptr::null_mut();
+            };
+            self.split(block, size);
+            return self.hand_out(block, caller);
+        }
+
+        // over aligned, with room to free whatever is in front of the aligned payload
+        let Some(mut block) = self.take_fitting(size + layout.align() + MIN_BLOCK) else {
+            return ptr::null_mut();
+        };
+        let payload = Block::payload(block) as usize;
+        let mut aligned = (payload + layout.align() - 1) & !(layout.align() - 1);
+        if aligned != payload {
+            if aligned - payload < MIN_BLOCK {
+                aligned = (payload + MIN_BLOCK + layout.align() - 1) & !(layout.align() - 1);
+            }
+            let front = aligned - payload;
+            let rest = Block::size(block) - front;
+            let front_block = block;
+            block = block.byte_add(front);
+            (*block).size = rest;
+            // the block in front of the taken one was used, free blocks are always merged
+            (*front_block).size = PREV_USED;
+            Block::set_free(front_block, front);
+            self.insert(front_block);
+        }
+        self.split(block, size);
+        self.hand_out(block, caller)
+    }
+
+    unsafe fn dealloc(&mut self, ptr: *mut u8) {
+        let block = self.block_of(ptr);
+        let size = Block::size(block);
+        self.allocations -= 1;
+        self.used_bytes -= size;
+        self.free_block(block, size);
+    }
+
+    /// Grows or shrinks without moving, `false` when it would have to move.
+    unsafe fn resize_in_place(&mut self, ptr: *mut u8, new_size: usize) -> bool {
+        let block = self.block_of(ptr);
+        let size = block_size(new_size);
+        let current = Block::size(block);
+        if size <= current {
+            self.split(block, size);
+            self.used_bytes -= current - Block::size(block);
+            return true;
+        }
+        let next = Block::next(block);
+        if Block::is_used(next) || current + Block::size(next) < size {
+            return false;
+        }
+        self.remove(next);
+        (*block).size = (current + Block::size(next)) | ((*block).size & FLAGS);
+        (*Block::next(block)).size |= PREV_USED;
+        self.split(block, size);
+        self.used_bytes += Block::size(block) - current;
+        self.peak_bytes = self.peak_bytes.max(self.used_bytes);
+        true
+    }
+}
+
+/// `$ra`, read before anything has had the chance to overwrite it.
+#[inline(always)]
+fn return_address() -> usize {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let ra: usize;
+        core::arch::asm!("move {0}, $ra", out(reg) ra, options(nomem, nostack));
+        ra
+    }
+    #[cfg(not(target_arch = "mips"))]
+    0
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        self.allocate(layout, return_address())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+        self.with_state(|state| state.dealloc(ptr))
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let caller = return_address();
+        if self.with_state(|state| state.resize_in_place(ptr, new_size)) {
+            return ptr;
+        }
+        let new = self.allocate(
+            Layout::from_size_align_unchecked(new_size, layout.align()),
+            caller,
+        );
+        if !new.is_null() {
+            ptr::copy_nonoverlapping(ptr, new, layout.size().min(new_size));
+            self.dealloc(ptr, layout);
+        }
+        new
+    }
+}
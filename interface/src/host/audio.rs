@@ -0,0 +1,291 @@
+//! Software stand-in for the host audio device.
+//!
+//! [`Synth`] implements the behaviour of the audio syscalls (tone channels, wave table, master
+//! volume and the PCM ring buffer) and renders them to 16 bit mono samples at
+//! [`OUTPUT_SAMPLE_RATE`]. Together with [`WavWriter`] this lets an emulator, or a test harness
+//! without a sound card, dump exactly what a guest would have played:
+//!
+//! ```ignore
+//! let mut synth = Synth::new();
+//! let mut wav = WavWriter::new(File::create("out.wav")?, OUTPUT_SAMPLE_RATE, 1)?;
+//! // forward the guest's audio syscalls to `synth`, then once per host frame:
+//! let mut buf = [0i16; 735];
+//! synth.render(&mut buf);
+//! wav.write_samples(&buf)?;
+//! // ..
+//! wav.finish()?;
+//! ```
+//!
+//! The `synth_wav` example renders every channel into a file like this.
+
+use std::{
+    collections::VecDeque,
+    io::{self, Seek, SeekFrom, Write},
+};
+
+use crate::audio::{Channel, ChannelRegs, NUM_CHANNELS, OUTPUT_SAMPLE_RATE, SWEEP_RATE};
+
+/// Size of the PCM ring buffer in samples, this is what `audio_pcm_available` counts down from.
+pub const PCM_CAPACITY: usize = 16384;
+
+/// A single channel at full volume is this fraction of full scale, so all channels together
+/// can't clip on their own.
+const CHANNEL_SCALE: f32 = 1.0 / NUM_CHANNELS as f32;
+
+const DUTY_THRESHOLD: [u32; 4] = [1 << 29, 1 << 30, 1 << 31, 3 << 30];
+
+#[derive(Copy, Clone, Default)]
+struct Voice {
+    regs: ChannelRegs,
+    /// Current frequency, moves away from `regs.frequency` while sweeping.
+    frequency: u32,
+    phase: u64,
+    sweep_timer: u8,
+    lfsr: u16,
+}
+
+impl Voice {
+    fn write(&mut self, regs: ChannelRegs) {
+        self.regs = regs;
+        self.frequency = regs.frequency;
+        self.sweep_timer = regs.sweep_period;
+        if self.lfsr == 0 {
+            self.lfsr = 0x7FFF;
+        }
+    }
+
+    fn clock_sweep(&mut self) {
+        if self.regs.sweep_period == 0 || self.regs.sweep_shift == 0 {
+            return;
+        }
+        self.sweep_timer = self.sweep_timer.saturating_sub(1);
+        if self.sweep_timer != 0 {
+            return;
+        }
+        self.sweep_timer = self.regs.sweep_period;
+        let delta = self.frequency >> self.regs.sweep_shift;
+        self.frequency = if self.regs.sweep_negate != 0 {
+            self.frequency.saturating_sub(delta)
+        } else {
+            self.frequency.saturating_add(delta)
+        };
+    }
+
+    fn clock_lfsr(&mut self) {
+        let feedback = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+        self.lfsr = (self.lfsr >> 1) | (feedback << 14);
+        if self.regs.mode != 0 {
+            self.lfsr = (self.lfsr & !(1 << 6)) | (feedback << 6);
+        }
+    }
+
+    /// Advances one output sample and returns the waveform in `-1.0..=1.0`.
+    fn next(&mut self, channel: Channel, wave: &[u8; 32]) -> f32 {
+        let increment = ((self.frequency as u64) << 24) / OUTPUT_SAMPLE_RATE as u64;
+        let phase = self.phase as u32;
+        let out = match channel {
+            Channel::Pulse1 | Channel::Pulse2 => {
+                if phase < DUTY_THRESHOLD[self.regs.duty as usize & 3] {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Channel::Triangle => {
+                let step = phase >> 27;
+                let level = if step < 16 { 15 - step } else { step - 16 };
+                level as f32 / 7.5 - 1.0
+            }
+            Channel::Noise => {
+                if self.lfsr & 1 == 0 {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Channel::Wave => (wave[(phase >> 27) as usize] & 15) as f32 / 7.5 - 1.0,
+        };
+
+        self.phase += increment;
+        if channel == Channel::Noise {
+            // the LFSR can be clocked faster than the output rate, cap the work per sample
+            let mut clocks = (self.phase >> 32).min(64);
+            while clocks > 0 {
+                self.clock_lfsr();
+                clocks -= 1;
+            }
+        }
+        self.phase &= u32::MAX as u64;
+        out
+    }
+}
+
+pub struct Synth {
+    voices: [Voice; NUM_CHANNELS],
+    wave: [u8; 32],
+    master_volume: u8,
+    sweep_counter: u32,
+    pcm: VecDeque<i16>,
+    pcm_rate: u32,
+    pcm_channels: u32,
+    /// Position between PCM frames with 16 fractional bits.
+    pcm_phase: u32,
+    pcm_frame: f32,
+}
+
+impl Default for Synth {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Synth {
+    pub fn new() -> Self {
+        Self {
+            voices: [Voice::default(); NUM_CHANNELS],
+            wave: [0; 32],
+            master_volume: 15,
+            sweep_counter: 0,
+            pcm: VecDeque::with_capacity(PCM_CAPACITY),
+            pcm_rate: OUTPUT_SAMPLE_RATE,
+            pcm_channels: 1,
+            pcm_phase: 0,
+            pcm_frame: 0.0,
+        }
+    }
+
+    /// `audio_write_channel`, out of range channels are ignored.
+    pub fn write_channel(&mut self, channel: u32, regs: ChannelRegs) {
+        if let Some(voice) = self.voices.get_mut(channel as usize) {
+            voice.write(regs);
+        }
+    }
+
+    /// `audio_set_wave`
+    pub fn set_wave(&mut self, table: &[u8; 32]) {
+        self.wave = *table;
+    }
+
+    /// `audio_set_master_volume`
+    pub fn set_master_volume(&mut self, volume: u32) {
+        self.master_volume = volume.min(15) as u8;
+    }
+
+    /// `audio_pcm_configure`
+    pub fn pcm_configure(&mut self, sample_rate: u32, channels: u32) {
+        self.pcm.clear();
+        self.pcm_rate = sample_rate.max(1);
+        self.pcm_channels = channels.clamp(1, 2);
+        self.pcm_phase = 0;
+        self.pcm_frame = 0.0;
+    }
+
+    /// `audio_pcm_write`, returns how many samples were taken.
+    pub fn pcm_write(&mut self, samples: &[i16]) -> usize {
+        let taken = samples.len().min(self.pcm_available());
+        self.pcm.extend(&samples[..taken]);
+        taken
+    }
+
+    /// `audio_pcm_available`
+    pub fn pcm_available(&self) -> usize {
+        PCM_CAPACITY - self.pcm.len()
+    }
+
+    /// Current registers of a channel, including the swept frequency.
+    pub fn channel(&self, channel: Channel) -> ChannelRegs {
+        let voice = &self.voices[channel as usize];
+        ChannelRegs {
+            frequency: voice.frequency,
+            ..voice.regs
+        }
+    }
+
+    /// Renders the next `out.len()` mono samples at [`OUTPUT_SAMPLE_RATE`].
+    pub fn render(&mut self, out: &mut [i16]) {
+        for sample in out {
+            self.sweep_counter += SWEEP_RATE;
+            if self.sweep_counter >= OUTPUT_SAMPLE_RATE {
+                self.sweep_counter -= OUTPUT_SAMPLE_RATE;
+                for voice in &mut self.voices {
+                    voice.clock_sweep();
+                }
+            }
+
+            let mut mix = 0.0;
+            for (channel, voice) in Channel::ALL.into_iter().zip(&mut self.voices) {
+                let level = voice.next(channel, &self.wave);
+                if voice.regs.is_enabled() {
+                    mix += level * (voice.regs.volume.min(15) as f32 / 15.0) * CHANNEL_SCALE;
+                }
+            }
+            mix += self.next_pcm();
+
+            let mix = mix * self.master_volume as f32 / 15.0;
+            *sample = (mix.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+        }
+    }
+
+    fn next_pcm(&mut self) -> f32 {
+        self.pcm_phase += (((self.pcm_rate as u64) << 16) / OUTPUT_SAMPLE_RATE as u64) as u32;
+        while self.pcm_phase >= 1 << 16 {
+            self.pcm_phase -= 1 << 16;
+            if self.pcm.len() < self.pcm_channels as usize {
+                self.pcm_frame = 0.0;
+                continue;
+            }
+            let mut frame = 0.0;
+            for _ in 0..self.pcm_channels {
+                frame += self.pcm.pop_front().unwrap_or(0) as f32 / 32768.0;
+            }
+            self.pcm_frame = frame / self.pcm_channels as f32;
+        }
+        self.pcm_frame
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Minimal 16 bit PCM `.wav` writer. The header sizes are patched in by [`WavWriter::finish`].
+pub struct WavWriter<W: Write + Seek> {
+    inner: W,
+    data_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    pub fn new(mut inner: W, sample_rate: u32, channels: u16) -> io::Result<Self> {
+        let block_align = channels as u32 * 2;
+        inner.write_all(b"RIFF")?;
+        inner.write_all(&0u32.to_le_bytes())?;
+        inner.write_all(b"WAVEfmt ")?;
+        inner.write_all(&16u32.to_le_bytes())?;
+        inner.write_all(&1u16.to_le_bytes())?;
+        inner.write_all(&channels.to_le_bytes())?;
+        inner.write_all(&sample_rate.to_le_bytes())?;
+        inner.write_all(&(sample_rate * block_align).to_le_bytes())?;
+        inner.write_all(&(block_align as u16).to_le_bytes())?;
+        inner.write_all(&16u16.to_le_bytes())?;
+        inner.write_all(b"data")?;
+        inner.write_all(&0u32.to_le_bytes())?;
+        Ok(Self { inner, data_len: 0 })
+    }
+
+    pub fn write_samples(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.inner.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += samples.len() as u32 * 2;
+        Ok(())
+    }
+
+    /// Fixes up the RIFF and data chunk sizes and hands back the writer.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.seek(SeekFrom::Start(4))?;
+        self.inner.write_all(&(36 + self.data_len).to_le_bytes())?;
+        self.inner.seek(SeekFrom::Start(40))?;
+        self.inner.write_all(&self.data_len.to_le_bytes())?;
+        self.inner.seek(SeekFrom::End(0))?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
@@ -0,0 +1,435 @@
+//! Host side of [`crate::binlog`], turns frames back into messages.
+//!
+//! [`Table::from_elf`] reads the interned entries out of the `.binlog` section of the guest ELF
+//! (`mips/bin/com.o`), [`Table::decode`] matches a frame to its entry and formats the
+//! arguments into the format string. `{}`, `{:?}`, `{:x}`, `{:X}`, `{:b}`, `{:o}`, `{:e}`, `#`,
+//! fill, alignment, width and precision are understood, the same as `core::fmt` does them.
+//!
+//! An emulator implementing `binlog_write` either decodes right away or appends the frames to
+//! a file with [`write_frame`] to be decoded later by the `binlog_decode` example.
+
+use std::{
+    collections::BTreeMap,
+    fmt, format,
+    io::{self, Read, Write},
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::{
+    binlog::{
+        ENTRY_SEPARATOR, TAG_BOOL, TAG_CHAR, TAG_F32, TAG_F64, TAG_I32, TAG_I64, TAG_STR, TAG_U32,
+        TAG_U64,
+    },
+    log::Level,
+};
+
+use super::elf::{Elf, ElfError};
+
+/// Name of the section the entries are interned into.
+pub const SECTION: &str = ".binlog";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TableError {
+    Elf(ElfError),
+    /// The ELF has no `.binlog` section, nothing logged with the `binlog` feature on.
+    NoSection,
+    BadEntry(u32),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::Elf(err) => err.fmt(f),
+            TableError::NoSection => write!(f, "no {} section", SECTION),
+            TableError::BadEntry(id) => write!(f, "malformed entry at {:#x}", id),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<ElfError> for TableError {
+    fn from(err: ElfError) -> Self {
+        TableError::Elf(err)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodeError {
+    /// Shorter than the entry id.
+    Truncated,
+    UnknownEntry(u32),
+    UnknownTag(u8),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Truncated => write!(f, "frame is truncated"),
+            DecodeError::UnknownEntry(id) => write!(f, "no entry at {:#x}, wrong ELF?", id),
+            DecodeError::UnknownTag(tag) => write!(f, "unknown argument tag {}", tag),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub level: Level,
+    pub module: String,
+    /// `file:line` of the call site.
+    pub location: String,
+    pub format: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    U32(u32),
+    I32(i32),
+    U64(u64),
+    I64(i64),
+    Bool(bool),
+    /// Not every `u32` the guest sends is a valid `char`.
+    Char(u32),
+    F32(f32),
+    F64(f64),
+    Str(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct Message<'a> {
+    pub entry: &'a Entry,
+    pub args: Vec<Value>,
+    /// The format string with the arguments filled in.
+    pub text: String,
+}
+
+impl fmt::Display for Message<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:<5} {}: {} ({})",
+            self.entry.level, self.entry.module, self.text, self.entry.location
+        )
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Table {
+    entries: BTreeMap<u32, Entry>,
+}
+
+impl Table {
+    pub fn from_elf(elf: &[u8]) -> Result<Self, TableError> {
+        let section = Elf::parse(elf)?
+            .section(SECTION)?
+            .ok_or(TableError::NoSection)?;
+        Self::from_section(section.addr, section.data)
+    }
+
+    /// Entries from the contents of the section loaded at `addr`.
+    pub fn from_section(addr: u32, data: &[u8]) -> Result<Self, TableError> {
+        let mut entries = BTreeMap::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            // entries are nul terminated, anything between them is alignment padding
+            if data[offset] == 0 {
+                offset += 1;
+                continue;
+            }
+            let id = addr + offset as u32;
+            let len = data[offset..]
+                .iter()
+                .position(|byte| *byte == 0)
+                .ok_or(TableError::BadEntry(id))?;
+            let entry = std::str::from_utf8(&data[offset..offset + len])
+                .ok()
+                .and_then(parse_entry)
+                .ok_or(TableError::BadEntry(id))?;
+            entries.insert(id, entry);
+            offset += len + 1;
+        }
+        Ok(Self { entries })
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Entry> {
+        self.entries.get(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn decode(&self, frame: &[u8]) -> Result<Message<'_>, DecodeError> {
+        let id = frame
+            .get(..4)
+            .ok_or(DecodeError::Truncated)
+            .map(|id| u32::from_le_bytes(id.try_into().unwrap()))?;
+        let entry = self.get(id).ok_or(DecodeError::UnknownEntry(id))?;
+        let args = decode_args(&frame[4..])?;
+        let text = format_message(&entry.format, &args);
+        Ok(Message { entry, args, text })
+    }
+}
+
+fn parse_entry(entry: &str) -> Option<Entry> {
+    let mut parts = entry.splitn(4, ENTRY_SEPARATOR);
+    let level = match parts.next()? {
+        "Error" => Level::Error,
+        "Warn" => Level::Warn,
+        "Info" => Level::Info,
+        "Debug" => Level::Debug,
+        "Trace" => Level::Trace,
+        _ => return None,
+    };
+    Some(Entry {
+        level,
+        module: parts.next()?.to_string(),
+        location: parts.next()?.to_string(),
+        format: parts.next()?.to_string(),
+    })
+}
+
+fn decode_args(mut bytes: &[u8]) -> Result<Vec<Value>, DecodeError> {
+    fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+        let (taken, rest) = (bytes.get(..len)?, bytes.get(len..)?);
+        *bytes = rest;
+        Some(taken)
+    }
+    fn array<const N: usize>(bytes: &mut &[u8]) -> Option<[u8; N]> {
+        take(bytes, N).map(|taken| taken.try_into().unwrap())
+    }
+
+    let mut args = Vec::new();
+    while let Some(tag) = take(&mut bytes, 1) {
+        // a value cut off by the end of the frame is dropped like the guest drops the ones
+        // that don't fit at all
+        let value = match tag[0] {
+            TAG_U32 => array(&mut bytes).map(|b| Value::U32(u32::from_le_bytes(b))),
+            TAG_I32 => array(&mut bytes).map(|b| Value::I32(i32::from_le_bytes(b))),
+            TAG_U64 => array(&mut bytes).map(|b| Value::U64(u64::from_le_bytes(b))),
+            TAG_I64 => array(&mut bytes).map(|b| Value::I64(i64::from_le_bytes(b))),
+            TAG_BOOL => array::<1>(&mut bytes).map(|b| Value::Bool(b[0] != 0)),
+            TAG_CHAR => array(&mut bytes).map(|b| Value::Char(u32::from_le_bytes(b))),
+            TAG_F32 => array(&mut bytes).map(|b| Value::F32(f32::from_bits(u32::from_le_bytes(b)))),
+            TAG_F64 => array(&mut bytes).map(|b| Value::F64(f64::from_bits(u64::from_le_bytes(b)))),
+            TAG_STR => array(&mut bytes).and_then(|len| {
+                let len = u16::from_le_bytes(len) as usize;
+                take(&mut bytes, len).map(|str| Value::Str(String::from_utf8_lossy(str).into()))
+            }),
+            tag => return Err(DecodeError::UnknownTag(tag)),
+        };
+        match value {
+            Some(value) => args.push(value),
+            None => break,
+        }
+    }
+    Ok(args)
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug, Default)]
+struct Spec {
+    fill: Option<char>,
+    align: Option<char>,
+    plus: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+    /// `'\0'` for `Display`.
+    kind: char,
+}
+
+fn parse_spec(spec: &str) -> Spec {
+    let mut out = Spec::default();
+    let chars: Vec<char> = spec.chars().collect();
+    let mut i = 0;
+    let is_align = |c: Option<&char>| matches!(c, Some('<' | '^' | '>'));
+    if is_align(chars.get(1)) {
+        out.fill = Some(chars[0]);
+        out.align = Some(chars[1]);
+        i = 2;
+    } else if is_align(chars.first()) {
+        out.align = Some(chars[0]);
+        i = 1;
+    }
+    if chars.get(i) == Some(&'+') {
+        out.plus = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'#') {
+        out.alternate = true;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'0') {
+        out.zero = true;
+        i += 1;
+    }
+    while let Some(digit) = chars.get(i).and_then(|c| c.to_digit(10)) {
+        out.width = out.width * 10 + digit as usize;
+        i += 1;
+    }
+    if chars.get(i) == Some(&'.') {
+        i += 1;
+        let mut precision = 0;
+        while let Some(digit) = chars.get(i).and_then(|c| c.to_digit(10)) {
+            precision = precision * 10 + digit as usize;
+            i += 1;
+        }
+        out.precision = Some(precision);
+    }
+    out.kind = chars.get(i).copied().unwrap_or('\0');
+    out
+}
+
+/// Formats one value without padding, returns the sign/prefix and the digits separately so
+/// zero padding can go between them.
+fn render(value: &Value, spec: &Spec) -> (String, String) {
+    macro_rules! int {
+        ($v:expr) => {{
+            let v = $v;
+            let (prefix, digits) = match spec.kind {
+                'x' => ("0x", format!("{:x}", v)),
+                'X' => ("0x", format!("{:X}", v)),
+                'b' => ("0b", format!("{:b}", v)),
+                'o' => ("0o", format!("{:o}", v)),
+                'e' => ("", format!("{:e}", v)),
+                _ => ("", format!("{}", v)),
+            };
+            let (sign, digits) = match digits.strip_prefix('-') {
+                Some(digits) => ("-", digits.to_string()),
+                None if spec.plus => ("+", digits),
+                None => ("", digits),
+            };
+            let prefix = if spec.alternate { prefix } else { "" };
+            (format!("{}{}", sign, prefix), digits)
+        }};
+    }
+    macro_rules! float {
+        ($v:expr) => {{
+            let v = $v;
+            let digits = match (spec.kind, spec.precision) {
+                ('e', Some(p)) => format!("{:.*e}", p, v),
+                ('e', None) => format!("{:e}", v),
+                ('?', None) => format!("{:?}", v),
+                (_, Some(p)) => format!("{:.*}", p, v),
+                (_, None) => format!("{}", v),
+            };
+            match digits.strip_prefix('-') {
+                Some(digits) => ("-".to_string(), digits.to_string()),
+                None if spec.plus => ("+".to_string(), digits),
+                None => (String::new(), digits),
+            }
+        }};
+    }
+    match value {
+        Value::U32(v) => int!(*v),
+        Value::I32(v) => int!(*v),
+        Value::U64(v) => int!(*v),
+        Value::I64(v) => int!(*v),
+        Value::F32(v) => float!(*v),
+        Value::F64(v) => float!(*v),
+        Value::Bool(v) => (String::new(), v.to_string()),
+        Value::Char(v) => {
+            let char = char::from_u32(*v).unwrap_or(char::REPLACEMENT_CHARACTER);
+            match spec.kind {
+                '?' => (String::new(), format!("{:?}", char)),
+                _ => (String::new(), char.to_string()),
+            }
+        }
+        Value::Str(v) => {
+            let str = match spec.precision {
+                Some(p) => v.chars().take(p).collect(),
+                None => v.clone(),
+            };
+            match spec.kind {
+                '?' => (String::new(), format!("{:?}", str)),
+                _ => (String::new(), str),
+            }
+        }
+    }
+}
+
+fn pad(value: &Value, spec: &Spec) -> String {
+    let (prefix, digits) = render(value, spec);
+    let len = prefix.chars().count() + digits.chars().count();
+    if len >= spec.width {
+        return prefix + &digits;
+    }
+    let missing = spec.width - len;
+    let numeric = !matches!(value, Value::Str(_) | Value::Char(_) | Value::Bool(_));
+    if spec.zero && numeric {
+        return prefix + &"0".repeat(missing) + &digits;
+    }
+    let fill = spec.fill.unwrap_or(' ').to_string();
+    let align = spec.align.unwrap_or(if numeric { '>' } else { '<' });
+    let (left, right) = match align {
+        '<' => (0, missing),
+        '^' => (missing / 2, missing - missing / 2),
+        _ => (missing, 0),
+    };
+    fill.repeat(left) + &prefix + &digits + &fill.repeat(right)
+}
+
+/// Fills the `{}`s of a format string in order, missing arguments show up as `{?}`.
+pub fn format_message(format: &str, args: &[Value]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut chars = format.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                out.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                out.push('}');
+            }
+            '{' => {
+                let placeholder: String = chars.by_ref().take_while(|c| *c != '}').collect();
+                // named and positional arguments are resolved by the guest compiler only in
+                // text mode, here they are taken in order like `{}`
+                let spec = placeholder.split_once(':').map_or("", |(_, spec)| spec);
+                match args.next() {
+                    Some(value) => out.push_str(&pad(value, &parse_spec(spec))),
+                    None => out.push_str("{?}"),
+                }
+            }
+            char => out.push(char),
+        }
+    }
+    out
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Appends `frame` to a frame log, each frame is prefixed with its length as a little endian
+/// `u16`.
+pub fn write_frame(out: &mut impl Write, frame: &[u8]) -> io::Result<()> {
+    let len = u16::try_from(frame.len())
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "frame too long"))?;
+    out.write_all(&len.to_le_bytes())?;
+    out.write_all(frame)
+}
+
+/// Next frame written by [`write_frame`], `None` at the end of the log.
+pub fn read_frame(input: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut len = [0; 2];
+    match input.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let mut frame = std::vec![0; u16::from_le_bytes(len) as usize];
+    input.read_exact(&mut frame)?;
+    Ok(Some(frame))
+}
@@ -0,0 +1,231 @@
+//! The host stand-ins behind one [`SyscallHandler`].
+//!
+//! [`Devices`] copies syscall arguments out of guest memory, hands them to the [`Sandbox`] and
+//! the [`Synth`] and writes the results back, so an emulator only has to forward the calls it
+//! doesn't handle itself:
+//!
+//! ```ignore
+//! let mut devices = Devices::new(memory, Sandbox::new("saves"), Synth::new());
+//! if !devices.dispatch(call_id, &mut regs) {
+//!     // not in the table at all
+//! }
+//! ```
+//!
+//! Calls that aren't fs or audio calls return [`SysRet::unsupported`]. Structs in guest memory
+//! are big endian like the mips target.
+//!
+//! [`SysRet::unsupported`]: crate::sys::table::SysRet::unsupported
+
+use std::{string::String, vec, vec::Vec};
+
+use super::{audio::Synth, fs::Sandbox};
+use crate::{
+    audio::{ChannelRegs, NUM_CHANNELS},
+    fs::{RawDirEntry, RawStat, NAME_MAX},
+    sys::{SysError, SysResult, SyscallHandler},
+};
+
+/// Most bytes a single `fs_read` or `fs_write` moves, the guest loops on short reads and writes
+/// anyway and a bogus length shouldn't allocate gigabytes.
+const MAX_TRANSFER: u32 = 1 << 20;
+
+/// Longer guest paths are [`SysError::InvalidPath`].
+const MAX_PATH: u32 = 4096;
+
+/// Guest memory as the emulator sees it.
+pub trait GuestMemory {
+    /// Fills `buf` from `addr` onwards, `false` if any of it isn't mapped.
+    fn read(&self, addr: u32, buf: &mut [u8]) -> bool;
+
+    /// Copies `data` to `addr` onwards, `false` if any of it isn't mapped.
+    fn write(&mut self, addr: u32, data: &[u8]) -> bool;
+}
+
+/// Flat memory starting at address 0.
+impl GuestMemory for Vec<u8> {
+    fn read(&self, addr: u32, buf: &mut [u8]) -> bool {
+        let start = addr as usize;
+        match self.get(start..start + buf.len()) {
+            Some(src) => {
+                buf.copy_from_slice(src);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn write(&mut self, addr: u32, data: &[u8]) -> bool {
+        let start = addr as usize;
+        match self.get_mut(start..start + data.len()) {
+            Some(dst) => {
+                dst.copy_from_slice(data);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+pub struct Devices<M> {
+    pub memory: M,
+    pub fs: Sandbox,
+    pub audio: Synth,
+}
+
+impl<M: GuestMemory> Devices<M> {
+    pub fn new(memory: M, fs: Sandbox, audio: Synth) -> Self {
+        Self { memory, fs, audio }
+    }
+
+    /// [`SysError::InvalidArgument`] for unmapped memory.
+    fn read(&self, addr: u32, len: usize) -> SysResult<Vec<u8>> {
+        let mut buf = vec![0; len];
+        if self.memory.read(addr, &mut buf) {
+            Ok(buf)
+        } else {
+            Err(SysError::InvalidArgument)
+        }
+    }
+
+    fn write(&mut self, addr: u32, data: &[u8]) -> SysResult<()> {
+        if self.memory.write(addr, data) {
+            Ok(())
+        } else {
+            Err(SysError::InvalidArgument)
+        }
+    }
+
+    fn read_path(&self, ptr: u32, len: u32) -> SysResult<String> {
+        if len > MAX_PATH {
+            return Err(SysError::InvalidPath);
+        }
+        String::from_utf8(self.read(ptr, len as usize)?).map_err(|_| SysError::InvalidPath)
+    }
+}
+
+fn stat_bytes(stat: &RawStat) -> [u8; 12] {
+    let mut bytes = [0; 12];
+    bytes[0..4].copy_from_slice(&stat.size.to_be_bytes());
+    bytes[4] = stat.kind;
+    bytes[5] = stat.readonly;
+    bytes[8..12].copy_from_slice(&stat.modified.to_be_bytes());
+    bytes
+}
+
+fn dir_entry_bytes(entry: &RawDirEntry) -> [u8; NAME_MAX + 8] {
+    let mut bytes = [0; NAME_MAX + 8];
+    bytes[..NAME_MAX].copy_from_slice(&entry.name);
+    bytes[NAME_MAX] = entry.name_len;
+    bytes[NAME_MAX + 1] = entry.kind;
+    bytes[NAME_MAX + 4..].copy_from_slice(&entry.size.to_be_bytes());
+    bytes
+}
+
+fn channel_regs(bytes: &[u8]) -> ChannelRegs {
+    ChannelRegs {
+        frequency: u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]),
+        volume: bytes[4],
+        duty: bytes[5],
+        sweep_period: bytes[6],
+        sweep_shift: bytes[7],
+        sweep_negate: bytes[8],
+        mode: bytes[9],
+        enabled: bytes[10],
+        _reserved: 0,
+    }
+}
+
+const _: () = {
+    assert!(core::mem::size_of::<RawStat>() == 12);
+    assert!(core::mem::size_of::<RawDirEntry>() == NAME_MAX + 8);
+    assert!(core::mem::size_of::<ChannelRegs>() == 12);
+};
+
+impl<M: GuestMemory> SyscallHandler for Devices<M> {
+    fn audio_write_channel(&mut self, channel: u32, regs_ptr: u32) -> SysResult<()> {
+        if channel as usize >= NUM_CHANNELS {
+            return Err(SysError::InvalidArgument);
+        }
+        let regs = self.read(regs_ptr, core::mem::size_of::<ChannelRegs>())?;
+        self.audio.write_channel(channel, channel_regs(&regs));
+        Ok(())
+    }
+
+    fn audio_set_wave(&mut self, table_ptr: u32) {
+        let mut table = [0; 32];
+        if self.memory.read(table_ptr, &mut table) {
+            self.audio.set_wave(&table);
+        }
+    }
+
+    fn audio_set_master_volume(&mut self, volume: u32) {
+        self.audio.set_master_volume(volume);
+    }
+
+    fn audio_pcm_configure(&mut self, sample_rate: u32, channels: u32) -> SysResult<()> {
+        if sample_rate == 0 || !(1..=2).contains(&channels) {
+            return Err(SysError::InvalidArgument);
+        }
+        self.audio.pcm_configure(sample_rate, channels);
+        Ok(())
+    }
+
+    fn audio_pcm_write(&mut self, samples_ptr: u32, len: u32) -> u32 {
+        // nothing past what fits is read, the guest resends it
+        let len = (len as usize).min(self.audio.pcm_available());
+        let Ok(bytes) = self.read(samples_ptr, len * 2) else {
+            return 0;
+        };
+        let samples: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|sample| i16::from_be_bytes([sample[0], sample[1]]))
+            .collect();
+        self.audio.pcm_write(&samples) as u32
+    }
+
+    fn audio_pcm_available(&mut self) -> u32 {
+        self.audio.pcm_available() as u32
+    }
+
+    fn fs_open(&mut self, path_ptr: u32, path_len: u32, flags: u32) -> SysResult<u32> {
+        let path = self.read_path(path_ptr, path_len)?;
+        self.fs.open(&path, flags)
+    }
+
+    fn fs_close(&mut self, handle: u32) -> SysResult<()> {
+        self.fs.close(handle)
+    }
+
+    fn fs_read(&mut self, handle: u32, buf_ptr: u32, len: u32) -> SysResult<u32> {
+        // checks the guest buffer before the file moves on, a bad one must not lose data
+        let mut buf = self.read(buf_ptr, len.min(MAX_TRANSFER) as usize)?;
+        let read = self.fs.read(handle, &mut buf)?;
+        self.write(buf_ptr, &buf[..read as usize])?;
+        Ok(read)
+    }
+
+    fn fs_write(&mut self, handle: u32, buf_ptr: u32, len: u32) -> SysResult<u32> {
+        let buf = self.read(buf_ptr, len.min(MAX_TRANSFER) as usize)?;
+        self.fs.write(handle, &buf)
+    }
+
+    fn fs_seek(&mut self, handle: u32, offset: i32, whence: u32) -> SysResult<u32> {
+        self.fs.seek(handle, offset, whence)
+    }
+
+    fn fs_stat(&mut self, path_ptr: u32, path_len: u32, stat_ptr: u32) -> SysResult<()> {
+        let path = self.read_path(path_ptr, path_len)?;
+        let mut stat = RawStat::default();
+        self.fs.stat(&path, &mut stat)?;
+        self.write(stat_ptr, &stat_bytes(&stat))
+    }
+
+    fn fs_read_dir(&mut self, handle: u32, entry_ptr: u32) -> SysResult<bool> {
+        let mut entry = RawDirEntry::default();
+        if !self.fs.read_dir(handle, &mut entry)? {
+            return Ok(false);
+        }
+        self.write(entry_ptr, &dir_entry_bytes(&entry))?;
+        Ok(true)
+    }
+}
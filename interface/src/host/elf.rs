@@ -0,0 +1,171 @@
+//! Just enough of an ELF32 reader for the tools working on `mips/bin/com.o`: sections by name
+//! and the symbol table. Either endianness is read, the guest itself is big endian.
+
+use std::{fmt, vec::Vec};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ElfError {
+    NotElf,
+    /// Only 32 bit ELFs are read, that's all the guest produces.
+    Not32Bit,
+    Truncated,
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::NotElf => write!(f, "not an ELF file"),
+            ElfError::Not32Bit => write!(f, "not a 32 bit ELF"),
+            ElfError::Truncated => write!(f, "ELF is truncated"),
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+const SHT_SYMTAB: u32 = 2;
+const STT_FUNC: u8 = 2;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Section<'a> {
+    pub addr: u32,
+    pub data: &'a [u8],
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Symbol<'a> {
+    /// Still mangled.
+    pub name: &'a str,
+    pub addr: u32,
+    pub size: u32,
+    pub is_function: bool,
+}
+
+pub struct Elf<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+    sh_off: usize,
+    sh_entsize: usize,
+    sh_num: usize,
+}
+
+impl<'a> Elf<'a> {
+    pub fn parse(data: &'a [u8]) -> Result<Self, ElfError> {
+        if data.get(..4) != Some(b"\x7fELF") {
+            return Err(ElfError::NotElf);
+        }
+        if data.get(4) != Some(&1) {
+            return Err(ElfError::Not32Bit);
+        }
+        let mut elf = Self {
+            data,
+            big_endian: data.get(5) == Some(&2),
+            sh_off: 0,
+            sh_entsize: 0,
+            sh_num: 0,
+        };
+        elf.sh_off = elf.u32_at(0x20)? as usize;
+        elf.sh_entsize = elf.u16_at(0x2e)? as usize;
+        elf.sh_num = elf.u16_at(0x30)? as usize;
+        Ok(elf)
+    }
+
+    fn u16_at(&self, at: usize) -> Result<u16, ElfError> {
+        let bytes: [u8; 2] = self
+            .data
+            .get(at..at + 2)
+            .ok_or(ElfError::Truncated)?
+            .try_into()
+            .unwrap();
+        Ok(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, at: usize) -> Result<u32, ElfError> {
+        let bytes: [u8; 4] = self
+            .data
+            .get(at..at + 4)
+            .ok_or(ElfError::Truncated)?
+            .try_into()
+            .unwrap();
+        Ok(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn slice(&self, offset: u32, size: u32) -> Result<&'a [u8], ElfError> {
+        self.data
+            .get(offset as usize..offset as usize + size as usize)
+            .ok_or(ElfError::Truncated)
+    }
+
+    fn header(&self, index: usize) -> usize {
+        self.sh_off + index * self.sh_entsize
+    }
+
+    fn section_at(&self, index: usize) -> Result<Section<'a>, ElfError> {
+        let header = self.header(index);
+        Ok(Section {
+            addr: self.u32_at(header + 0x0c)?,
+            data: self.slice(self.u32_at(header + 0x10)?, self.u32_at(header + 0x14)?)?,
+        })
+    }
+
+    /// The section called `name`, `None` when there is no such section.
+    pub fn section(&self, name: &str) -> Result<Option<Section<'a>>, ElfError> {
+        let names = self.section_at(self.u16_at(0x32)? as usize)?.data;
+        for index in 0..self.sh_num {
+            let name_at = self.u32_at(self.header(index))? as usize;
+            let section_name = names
+                .get(name_at..)
+                .and_then(|rest| rest.split(|byte| *byte == 0).next())
+                .ok_or(ElfError::Truncated)?;
+            if section_name == name.as_bytes() {
+                return self.section_at(index).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// Every named symbol of the symbol table, empty for a stripped ELF.
+    pub fn symbols(&self) -> Result<Vec<Symbol<'a>>, ElfError> {
+        let mut symbols = Vec::new();
+        for index in 0..self.sh_num {
+            let header = self.header(index);
+            if self.u32_at(header + 0x04)? != SHT_SYMTAB {
+                continue;
+            }
+            let table = self.section_at(index)?.data;
+            let names = self.section_at(self.u32_at(header + 0x18)? as usize)?.data;
+            let entry_size = (self.u32_at(header + 0x24)? as usize).max(16);
+
+            let table_offset = self.u32_at(header + 0x10)? as usize;
+            for entry in (0..table.len() / entry_size).map(|i| table_offset + i * entry_size) {
+                let name_at = self.u32_at(entry)? as usize;
+                let name = names
+                    .get(name_at..)
+                    .and_then(|rest| rest.split(|byte| *byte == 0).next())
+                    .ok_or(ElfError::Truncated)?;
+                let Ok(name) = core::str::from_utf8(name) else {
+                    continue;
+                };
+                if name.is_empty() {
+                    continue;
+                }
+                symbols.push(Symbol {
+                    name,
+                    addr: self.u32_at(entry + 0x04)?,
+                    size: self.u32_at(entry + 0x08)?,
+                    is_function: self.data.get(entry + 0x0c).ok_or(ElfError::Truncated)? & 0xf
+                        == STT_FUNC,
+                });
+            }
+        }
+        Ok(symbols)
+    }
+}
@@ -0,0 +1,351 @@
+//! Reads FamiTracker 0.5 text exports and compiles them into the song format played by
+//! [`crate::audio::music`], see the docs there for the layout. `binary/build.rs` runs this over
+//! the songs in `res/sound`.
+
+use std::{
+    collections::HashMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+use crate::audio::music::{
+    CMD_EFFECT, CMD_END_PATTERN, CMD_END_ROW, CMD_INSTRUMENT, CMD_NOTE_CUT, CMD_RELEASE,
+    CMD_VOLUME, MAGIC, NONE,
+};
+
+/// Square 1, Square 2, Triangle, Noise and DPCM, the 2A03 channels in export order.
+const CHANNELS: usize = 5;
+/// Where the columns end up, DPCM has no counterpart and is dropped.
+const CHANNEL_TARGETS: [Option<u8>; CHANNELS] = [Some(0), Some(1), Some(2), Some(3), None];
+const NOISE_CHANNEL: usize = 3;
+
+const MACRO_TYPES: usize = 5;
+
+#[derive(Default)]
+pub struct Macro {
+    loop_point: i32,
+    release: i32,
+    values: Vec<i8>,
+}
+
+#[derive(Default, Clone, Copy)]
+pub struct Cell {
+    note: Option<Note>,
+    instrument: Option<u8>,
+    volume: Option<u8>,
+    effects: [Option<(u8, u8)>; 4],
+}
+
+#[derive(Clone, Copy)]
+pub enum Note {
+    /// MIDI note, or the noise period index on the noise channel.
+    On(u8),
+    Cut,
+    Release,
+}
+
+#[derive(Default)]
+pub struct Track {
+    rows: u16,
+    speed: u8,
+    tempo: u8,
+    orders: Vec<[u8; CHANNELS]>,
+    /// `patterns[pattern][row][channel]`
+    patterns: HashMap<u8, Vec<[Cell; CHANNELS]>>,
+}
+
+/// The parts of an export the player has a use for.
+#[derive(Default)]
+pub struct Module {
+    /// Keyed by `(type, index)`.
+    macros: HashMap<(usize, i32), Macro>,
+    /// Macro index per type, `-1` is none.
+    instruments: HashMap<u8, [i32; MACRO_TYPES]>,
+    tracks: Vec<Track>,
+}
+
+fn hex(s: &str) -> Result<u8, String> {
+    u8::from_str_radix(s, 16).map_err(|_| format!("bad hex value `{s}`"))
+}
+
+fn num<T: std::str::FromStr>(s: Option<&str>) -> Result<T, String> {
+    let s = s.ok_or("missing value")?;
+    s.parse().map_err(|_| format!("bad number `{s}`"))
+}
+
+fn parse_note(s: &str, channel: usize) -> Result<Option<Note>, String> {
+    let bytes = s.as_bytes();
+    Ok(match s {
+        "..." => None,
+        "---" => Some(Note::Cut),
+        "===" => Some(Note::Release),
+        _ if channel == NOISE_CHANNEL && s.ends_with("-#") => Some(Note::On(hex(&s[..1])?)),
+        _ if bytes.len() == 3 => {
+            let semi = match bytes[0] {
+                b'C' => 0,
+                b'D' => 2,
+                b'E' => 4,
+                b'F' => 5,
+                b'G' => 7,
+                b'A' => 9,
+                b'B' => 11,
+                _ => return Err(format!("bad note `{s}`")),
+            } + (bytes[1] == b'#') as i32;
+            let octave = (bytes[2] as char)
+                .to_digit(10)
+                .ok_or_else(|| format!("bad octave `{s}`"))? as i32;
+            let note = (octave + 1) * 12 + semi;
+            if note > 0x7F {
+                return Err(format!("note `{s}` is above G9"));
+            }
+            Some(Note::On(note as u8))
+        }
+        _ => return Err(format!("bad note `{s}`")),
+    })
+}
+
+fn parse_cell(s: &str, channel: usize) -> Result<Cell, String> {
+    let mut parts = s.split_whitespace();
+    let mut cell = Cell {
+        note: parse_note(parts.next().ok_or("missing note")?, channel)?,
+        ..Default::default()
+    };
+    // some exports have junk in the instrument/volume/effect columns of otherwise empty
+    // rows (`&&`), those are read as empty instead of failing the whole song
+    cell.instrument = hex(parts.next().ok_or("missing instrument")?).ok();
+    cell.volume = hex(parts.next().ok_or("missing volume")?)
+        .ok()
+        .map(|vol| vol & 15);
+    for (slot, effect) in cell.effects.iter_mut().zip(parts) {
+        if effect != "..." && effect.len() == 3 {
+            *slot = Some((effect.as_bytes()[0], hex(&effect[1..]).unwrap_or(0)));
+        }
+    }
+    Ok(cell)
+}
+
+/// Errors name the line they are on.
+pub fn parse_text(text: &str) -> Result<Module, String> {
+    let mut module = Module::default();
+    let mut pattern: Option<u8> = None;
+
+    for (line_num, line) in text.lines().enumerate() {
+        let result: Result<(), String> = (|| {
+            let line = line.trim();
+            let (head, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            match head {
+                "MACRO" => {
+                    let (header, values) = rest.split_once(':').ok_or("missing `:`")?;
+                    let mut header = header.split_whitespace();
+                    let kind: usize = num(header.next())?;
+                    let index: i32 = num(header.next())?;
+                    let mac = Macro {
+                        loop_point: num(header.next())?,
+                        release: num(header.next())?,
+                        values: values
+                            .split_whitespace()
+                            .map(|v| num(Some(v)))
+                            .collect::<Result<_, _>>()?,
+                    };
+                    if kind < MACRO_TYPES {
+                        module.macros.insert((kind, index), mac);
+                    }
+                }
+                "INST2A03" => {
+                    let mut parts = rest.split_whitespace();
+                    let index: u8 = num(parts.next())?;
+                    let mut macros = [-1; MACRO_TYPES];
+                    for mac in &mut macros {
+                        *mac = num(parts.next())?;
+                    }
+                    module.instruments.insert(index, macros);
+                }
+                "TRACK" => {
+                    let mut parts = rest.split_whitespace();
+                    module.tracks.push(Track {
+                        rows: num(parts.next())?,
+                        speed: num(parts.next())?,
+                        tempo: num::<u32>(parts.next())?.min(255) as u8,
+                        ..Default::default()
+                    });
+                    pattern = None;
+                }
+                "ORDER" => {
+                    let track = module.tracks.last_mut().ok_or("ORDER outside of a TRACK")?;
+                    let (_, patterns) = rest.split_once(':').ok_or("missing `:`")?;
+                    let mut order = [0; CHANNELS];
+                    for (slot, pattern) in order.iter_mut().zip(patterns.split_whitespace()) {
+                        *slot = hex(pattern)?;
+                    }
+                    track.orders.push(order);
+                }
+                "PATTERN" => pattern = Some(hex(rest.trim())?),
+                "ROW" => {
+                    let track = module.tracks.last_mut().ok_or("ROW outside of a TRACK")?;
+                    let pattern = pattern.ok_or("ROW outside of a PATTERN")?;
+                    // junk effects can contain a `:` of their own
+                    let mut columns = rest.split(" : ");
+                    let row = hex(columns.next().unwrap_or("").trim())? as usize;
+                    let mut cells = [Cell::default(); CHANNELS];
+                    for (channel, cell) in cells.iter_mut().enumerate() {
+                        *cell = parse_cell(columns.next().ok_or("missing column")?, channel)?;
+                    }
+                    let rows = track.patterns.entry(pattern).or_default();
+                    if rows.len() <= row {
+                        rows.resize(row + 1, [Cell::default(); CHANNELS]);
+                    }
+                    rows[row] = cells;
+                }
+                _ => {}
+            }
+            Ok(())
+        })();
+        result.map_err(|err| format!("line {}: {err}", line_num + 1))?;
+    }
+    Ok(module)
+}
+
+fn compile_pattern(rows: &[[Cell; CHANNELS]], channel: usize) -> Vec<u8> {
+    let mut out = Vec::new();
+    // rows without anything in them since the last end of row command
+    let mut pending: Option<usize> = None;
+    for row in rows {
+        let cell = &row[channel];
+        let mut cmds = Vec::new();
+        if let Some(inst) = cell.instrument {
+            cmds.extend([CMD_INSTRUMENT, inst]);
+        }
+        if let Some(vol) = cell.volume {
+            cmds.extend([CMD_VOLUME, vol]);
+        }
+        for (effect, param) in cell.effects.iter().flatten() {
+            // jumps, halt, skip and speed/tempo, the rest has no equivalent here
+            if matches!(effect, b'B' | b'C' | b'D' | b'F') {
+                cmds.extend([CMD_EFFECT, *effect, *param]);
+            }
+        }
+        match cell.note {
+            Some(Note::On(note)) => cmds.push(note),
+            Some(Note::Cut) => cmds.push(CMD_NOTE_CUT),
+            Some(Note::Release) => cmds.push(CMD_RELEASE),
+            None => {}
+        }
+
+        if cmds.is_empty() {
+            if let Some(empty) = &mut pending {
+                if *empty < 0x3E {
+                    *empty += 1;
+                    continue;
+                }
+            }
+        }
+        if let Some(empty) = pending.take() {
+            out.push(CMD_END_ROW | empty as u8);
+        }
+        out.extend(cmds);
+        pending = Some(0);
+    }
+    out.push(CMD_END_PATTERN);
+    out
+}
+
+/// The song for [`Song::parse`](crate::audio::music::Song::parse). Fails when something doesn't
+/// fit the format, like more than 255 orders or a song past 64 KiB.
+pub fn compile(module: &Module) -> Result<Vec<u8>, String> {
+    let mut out = MAGIC.to_vec();
+    let targets: Vec<u8> = CHANNEL_TARGETS.iter().flatten().copied().collect();
+    out.push(targets.len() as u8);
+    out.extend(&targets);
+
+    let mut macro_keys: Vec<_> = module.macros.keys().copied().collect();
+    macro_keys.sort();
+    if macro_keys.len() >= NONE as usize {
+        return Err("too many macros".to_string());
+    }
+    let point = |p: i32, len: usize| {
+        if p < 0 || p as usize >= len {
+            NONE
+        } else {
+            p as u8
+        }
+    };
+    out.push(macro_keys.len() as u8);
+    for key in &macro_keys {
+        let mac = &module.macros[key];
+        if mac.values.len() >= NONE as usize {
+            return Err(format!("macro {key:?} is too long"));
+        }
+        out.push(mac.values.len() as u8);
+        out.push(point(mac.loop_point, mac.values.len()));
+        out.push(point(mac.release, mac.values.len()));
+        out.extend(mac.values.iter().map(|v| *v as u8));
+    }
+
+    let num_instruments = module
+        .instruments
+        .keys()
+        .max()
+        .map_or(0, |max| *max as usize + 1);
+    out.push(num_instruments as u8);
+    for index in 0..num_instruments {
+        let macros = module.instruments.get(&(index as u8));
+        for kind in 0..MACRO_TYPES {
+            let id = macros.and_then(|macros| {
+                macro_keys
+                    .iter()
+                    .position(|key| *key == (kind, macros[kind]))
+            });
+            out.push(id.map_or(NONE, |id| id as u8));
+        }
+    }
+
+    out.push(module.tracks.len() as u8);
+    let track_table = out.len();
+    out.resize(track_table + module.tracks.len() * 2, 0);
+
+    for (index, track) in module.tracks.iter().enumerate() {
+        let offset = u16::try_from(out.len()).map_err(|_| "song is too large")?;
+        out[track_table + index * 2..][..2].copy_from_slice(&offset.to_le_bytes());
+
+        // every channel has its own patterns, identical ones are only stored once
+        let mut streams: Vec<Vec<u8>> = Vec::new();
+        let mut orders = Vec::new();
+        for order in &track.orders {
+            for (channel, target) in CHANNEL_TARGETS.iter().enumerate() {
+                if target.is_none() {
+                    continue;
+                }
+                let rows = track
+                    .patterns
+                    .get(&order[channel])
+                    .map_or(&[][..], |rows| rows);
+                let rows = &rows[..rows.len().min(track.rows as usize)];
+                let stream = compile_pattern(rows, channel);
+                let id = match streams.iter().position(|s| *s == stream) {
+                    Some(id) => id,
+                    None => {
+                        streams.push(stream);
+                        streams.len() - 1
+                    }
+                };
+                orders.push(u8::try_from(id).map_err(|_| "too many patterns")?);
+            }
+        }
+
+        out.extend([track.speed, track.tempo]);
+        out.extend(track.rows.to_le_bytes());
+        out.push(u8::try_from(track.orders.len()).map_err(|_| "too many orders")?);
+        out.push(u8::try_from(streams.len()).map_err(|_| "too many patterns")?);
+        let pattern_table = out.len();
+        out.resize(pattern_table + streams.len() * 2, 0);
+        out.extend(orders);
+        for (id, stream) in streams.iter().enumerate() {
+            let offset = u16::try_from(out.len()).map_err(|_| "song is too large")?;
+            out[pattern_table + id * 2..][..2].copy_from_slice(&offset.to_le_bytes());
+            out.extend(stream);
+        }
+    }
+    u16::try_from(out.len()).map_err(|_| "song is too large")?;
+    Ok(out)
+}
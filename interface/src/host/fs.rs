@@ -0,0 +1,216 @@
+//! Host side of the `fs_*` syscalls.
+//!
+//! [`Sandbox`] serves a single directory on the host. Guest paths are resolved component by
+//! component below that root, `..` and anything that could name another drive or root are
+//! rejected, so a guest can't reach files outside of it through the path alone. Symlinks inside
+//! the root are followed, don't put any there that point out of it.
+//!
+//! Every call returns what the syscall of the same name does, [`crate::host::devices::Devices`]
+//! copies the arguments out of guest memory and writes the results back through
+//! [`crate::sys::SyscallHandler::dispatch`]:
+//!
+//! ```ignore
+//! let mut devices = Devices::new(memory, Sandbox::new("saves"), Synth::new());
+//! // in the emulator's syscall handler:
+//! devices.dispatch(call_id, &mut regs);
+//! ```
+
+use std::{
+    fs,
+    io::{self, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+    vec::Vec,
+};
+
+use crate::fs::{
+    Error, RawDirEntry, RawStat, KIND_DIRECTORY, KIND_FILE, NAME_MAX, OPEN_APPEND, OPEN_CREATE,
+    OPEN_CREATE_NEW, OPEN_DIRECTORY, OPEN_READ, OPEN_TRUNCATE, OPEN_WRITE,
+};
+
+/// Handles a guest may have open at once.
+pub const MAX_HANDLES: usize = 32;
+
+enum Handle {
+    File(fs::File),
+    Dir(fs::ReadDir),
+}
+
+pub struct Sandbox {
+    root: PathBuf,
+    handles: Vec<Option<Handle>>,
+}
+
+fn io_error(err: io::Error) -> Error {
+    match err.kind() {
+        io::ErrorKind::NotFound => Error::NotFound,
+        io::ErrorKind::PermissionDenied => Error::PermissionDenied,
+        io::ErrorKind::AlreadyExists => Error::AlreadyExists,
+        io::ErrorKind::InvalidInput => Error::InvalidPath,
+        io::ErrorKind::UnexpectedEof => Error::UnexpectedEof,
+        _ => Error::Other,
+    }
+}
+
+impl Sandbox {
+    /// Serves `root`, which should already exist.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            handles: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Host path for a guest path, or [`Error::InvalidPath`] if it would leave the root.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, Error> {
+        let mut resolved = self.root.clone();
+        for component in path.split('/') {
+            match component {
+                "" | "." => {}
+                ".." => return Err(Error::InvalidPath),
+                _ if component.contains(['\\', ':', '\0']) => return Err(Error::InvalidPath),
+                _ => resolved.push(component),
+            }
+        }
+        Ok(resolved)
+    }
+
+    fn insert(&mut self, handle: Handle) -> Result<u32, Error> {
+        let slot = match self.handles.iter().position(Option::is_none) {
+            Some(slot) => slot,
+            None if self.handles.len() < MAX_HANDLES => {
+                self.handles.push(None);
+                self.handles.len() - 1
+            }
+            None => return Err(Error::TooManyOpenFiles),
+        };
+        self.handles[slot] = Some(handle);
+        Ok(slot as u32)
+    }
+
+    fn file(&mut self, handle: u32) -> Result<&mut fs::File, Error> {
+        match self.handles.get_mut(handle as usize) {
+            Some(Some(Handle::File(file))) => Ok(file),
+            _ => Err(Error::BadHandle),
+        }
+    }
+
+    /// `fs_open`
+    pub fn open(&mut self, path: &str, flags: u32) -> Result<u32, Error> {
+        let path = self.resolve(path)?;
+        if flags & OPEN_DIRECTORY != 0 {
+            if !path.is_dir() {
+                return Err(if path.exists() {
+                    Error::NotADirectory
+                } else {
+                    Error::NotFound
+                });
+            }
+            let dir = fs::read_dir(path).map_err(io_error)?;
+            return self.insert(Handle::Dir(dir));
+        }
+        if path.is_dir() {
+            return Err(Error::IsADirectory);
+        }
+        let file = fs::OpenOptions::new()
+            .read(flags & OPEN_READ != 0)
+            .write(flags & OPEN_WRITE != 0)
+            .append(flags & OPEN_APPEND != 0)
+            .create(flags & OPEN_CREATE != 0)
+            .truncate(flags & OPEN_TRUNCATE != 0)
+            .create_new(flags & OPEN_CREATE_NEW != 0)
+            .open(path)
+            .map_err(io_error)?;
+        self.insert(Handle::File(file))
+    }
+
+    /// `fs_close`
+    pub fn close(&mut self, handle: u32) -> Result<(), Error> {
+        match self.handles.get_mut(handle as usize).and_then(Option::take) {
+            Some(_) => Ok(()),
+            None => Err(Error::BadHandle),
+        }
+    }
+
+    /// `fs_read`
+    pub fn read(&mut self, handle: u32, buf: &mut [u8]) -> Result<u32, Error> {
+        let file = self.file(handle)?;
+        file.read(buf).map(|read| read as u32).map_err(io_error)
+    }
+
+    /// `fs_write`
+    pub fn write(&mut self, handle: u32, buf: &[u8]) -> Result<u32, Error> {
+        let file = self.file(handle)?;
+        file.write(buf)
+            .map(|written| written as u32)
+            .map_err(io_error)
+    }
+
+    /// `fs_seek`
+    pub fn seek(&mut self, handle: u32, offset: i32, whence: u32) -> Result<u32, Error> {
+        let pos = match whence {
+            0 => SeekFrom::Start(offset as u32 as u64),
+            1 => SeekFrom::Current(offset as i64),
+            2 => SeekFrom::End(offset as i64),
+            _ => return Err(Error::InvalidArgument),
+        };
+        let pos = self.file(handle)?.seek(pos).map_err(io_error)?;
+        u32::try_from(pos)
+            .ok()
+            .filter(|pos| *pos <= i32::MAX as u32)
+            .ok_or(Error::TooLarge)
+    }
+
+    /// `fs_stat`
+    pub fn stat(&mut self, path: &str, stat: &mut RawStat) -> Result<(), Error> {
+        let meta = fs::metadata(self.resolve(path)?).map_err(io_error)?;
+        *stat = RawStat {
+            size: u32::try_from(meta.len()).map_err(|_| Error::TooLarge)?,
+            kind: if meta.is_dir() {
+                KIND_DIRECTORY
+            } else {
+                KIND_FILE
+            },
+            readonly: meta.permissions().readonly() as u8,
+            _reserved: [0; 2],
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |time| time.as_secs().min(u32::MAX as u64) as u32),
+        };
+        Ok(())
+    }
+
+    /// `fs_read_dir`
+    pub fn read_dir(&mut self, handle: u32, entry: &mut RawDirEntry) -> Result<bool, Error> {
+        let dir = match self.handles.get_mut(handle as usize) {
+            Some(Some(Handle::Dir(dir))) => dir,
+            _ => return Err(Error::BadHandle),
+        };
+        for next in dir {
+            let next = next.map_err(io_error)?;
+            let name = next.file_name();
+            let name = match name.to_str() {
+                Some(name) if name.len() <= NAME_MAX => name,
+                // can't be named from the guest, skip it
+                _ => continue,
+            };
+            let meta = next.metadata().ok();
+            *entry = RawDirEntry::default();
+            entry.name[..name.len()].copy_from_slice(name.as_bytes());
+            entry.name_len = name.len() as u8;
+            entry.kind = match &meta {
+                Some(meta) if meta.is_dir() => KIND_DIRECTORY,
+                _ => KIND_FILE,
+            };
+            entry.size = meta.map_or(0, |meta| meta.len().min(u32::MAX as u64) as u32);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
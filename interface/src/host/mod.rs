@@ -0,0 +1,10 @@
+//! Host side counterparts of the guest APIs, for emulators and tools that link against this
+//! crate with the `host` feature. Nothing in here is built for the mips target.
+
+pub mod audio;
+pub mod binlog;
+pub mod devices;
+pub mod elf;
+pub mod fami;
+pub mod fs;
+pub mod symbols;
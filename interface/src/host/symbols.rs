@@ -0,0 +1,43 @@
+//! Builds the symbol table [`crate::panic`] names backtrace frames with, from the functions in
+//! the symbol table of the guest ELF.
+
+use std::{collections::BTreeMap, format, vec::Vec};
+
+use crate::panic::SYMBOL_ENTRY_SIZE;
+
+use super::elf::{Elf, ElfError};
+
+/// The table for the functions of `elf`, only a count of zero for a stripped one.
+pub fn table_from_elf(elf: &[u8]) -> Result<Vec<u8>, ElfError> {
+    let functions = Elf::parse(elf)?
+        .symbols()?
+        .into_iter()
+        .filter(|symbol| symbol.is_function && symbol.size != 0)
+        .map(|symbol| {
+            let name = format!("{:#}", rustc_demangle::demangle(symbol.name));
+            (symbol.addr, symbol.size, name)
+        });
+    Ok(encode_table(functions))
+}
+
+/// Lays out `(address, size, name)`s the way [`crate::panic::lookup`] reads them. Of the ones
+/// at the same address the first is kept.
+pub fn encode_table(functions: impl IntoIterator<Item = (u32, u32, impl AsRef<str>)>) -> Vec<u8> {
+    let mut by_addr = BTreeMap::new();
+    for (addr, size, name) in functions {
+        by_addr.entry(addr).or_insert((size, name));
+    }
+
+    let mut entries = Vec::with_capacity(4 + by_addr.len() * SYMBOL_ENTRY_SIZE);
+    let mut names = Vec::new();
+    entries.extend_from_slice(&(by_addr.len() as u32).to_be_bytes());
+    for (addr, (size, name)) in by_addr {
+        entries.extend_from_slice(&addr.to_be_bytes());
+        entries.extend_from_slice(&size.to_be_bytes());
+        entries.extend_from_slice(&(names.len() as u32).to_be_bytes());
+        names.extend_from_slice(name.as_ref().as_bytes());
+        names.push(0);
+    }
+    entries.extend_from_slice(&names);
+    entries
+}
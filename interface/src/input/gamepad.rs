@@ -0,0 +1,298 @@
+use super::KeyCode;
+
+/// Buttons in the usual "Xbox" layout, `A` is the bottom face button.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GamepadButton {
+    A = 0,
+    B = 1,
+    X = 2,
+    Y = 3,
+    LeftShoulder = 4,
+    RightShoulder = 5,
+    Back = 6,
+    Start = 7,
+    Guide = 8,
+    LeftStick = 9,
+    RightStick = 10,
+    DPadUp = 11,
+    DPadDown = 12,
+    DPadLeft = 13,
+    DPadRight = 14,
+    /// Digital view of the analog triggers, see [`Deadzone::trigger_press`].
+    LeftTrigger = 15,
+    RightTrigger = 16,
+}
+
+pub const NUM_BUTTONS: usize = 17;
+
+impl GamepadButton {
+    #[inline(always)]
+    const fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// Layout the host writes for the `gamepad_state` syscall. Stick axes are `-32768..=32767`,
+/// positive is right/down, triggers are `0..=255`.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RawGamepadState {
+    pub buttons: u32,
+    pub left_stick: [i16; 2],
+    pub right_stick: [i16; 2],
+    pub triggers: [u8; 2],
+    _reserved: [u8; 2],
+}
+
+/// Radial deadzone for the sticks and a dead band for the triggers. Values inside `inner`
+/// read as zero, values past `outer` read as full deflection and everything between is
+/// rescaled so there is no jump at the edge of the deadzone.
+#[derive(Copy, Clone, Debug)]
+pub struct Deadzone {
+    pub inner: u16,
+    pub outer: u16,
+    pub trigger: u8,
+    /// A trigger reads as pressed for [`GamepadButton::LeftTrigger`]/[`GamepadButton::RightTrigger`]
+    /// past this value.
+    pub trigger_press: u8,
+    /// Stick deflection at which the left stick also counts as the dpad, anything past
+    /// `i16::MAX` takes full deflection.
+    pub stick_press: u16,
+}
+
+impl Default for Deadzone {
+    fn default() -> Self {
+        Self {
+            inner: 7849,
+            outer: 32000,
+            trigger: 30,
+            trigger_press: 128,
+            stick_press: 16384,
+        }
+    }
+}
+
+fn isqrt(n: u32) -> u32 {
+    if n < 2 {
+        return n;
+    }
+    let mut x = n;
+    let mut y = (x + n / x) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
+}
+
+impl Deadzone {
+    pub fn apply_stick(&self, stick: [i16; 2]) -> [i16; 2] {
+        let x = stick[0].max(-i16::MAX) as i32;
+        let y = stick[1].max(-i16::MAX) as i32;
+        let mag = isqrt((x * x + y * y) as u32) as i32;
+        let inner = self.inner as i32;
+        let outer = (self.outer as i32).max(inner + 1);
+        if mag <= inner {
+            return [0, 0];
+        }
+        let scaled = ((mag.min(outer) - inner) * i16::MAX as i32) / (outer - inner);
+        [(x * scaled / mag) as i16, (y * scaled / mag) as i16]
+    }
+
+    pub fn apply_trigger(&self, trigger: u8) -> u8 {
+        if trigger <= self.trigger {
+            0
+        } else {
+            ((trigger - self.trigger) as u32 * 255 / (255 - self.trigger) as u32) as u8
+        }
+    }
+}
+
+/// Keys merged into a [`Gamepad`], so games written against the pad keep working from the
+/// keyboard whether or not a controller is plugged in.
+#[derive(Copy, Clone, Debug)]
+pub struct KeyboardMapping {
+    pub keys: &'static [(KeyCode, GamepadButton)],
+}
+
+impl KeyboardMapping {
+    /// WASD and the arrows on the dpad, space/c/v/x on the face buttons, enter is start and
+    /// backspace is back. These are the keys the demos have always used.
+    pub const DEFAULT: Self = Self {
+        keys: &[
+            (KeyCode::W, GamepadButton::DPadUp),
+            (KeyCode::Up, GamepadButton::DPadUp),
+            (KeyCode::A, GamepadButton::DPadLeft),
+            (KeyCode::Left, GamepadButton::DPadLeft),
+            (KeyCode::S, GamepadButton::DPadDown),
+            (KeyCode::Down, GamepadButton::DPadDown),
+            (KeyCode::D, GamepadButton::DPadRight),
+            (KeyCode::Right, GamepadButton::DPadRight),
+            (KeyCode::Space, GamepadButton::A),
+            (KeyCode::V, GamepadButton::B),
+            (KeyCode::C, GamepadButton::X),
+            (KeyCode::X, GamepadButton::Y),
+            (KeyCode::Q, GamepadButton::LeftShoulder),
+            (KeyCode::E, GamepadButton::RightShoulder),
+            (KeyCode::Enter, GamepadButton::Start),
+            (KeyCode::Backspace, GamepadButton::Back),
+        ],
+    };
+
+    pub const NONE: Self = Self { keys: &[] };
+
+    fn poll(&self) -> u32 {
+        let mut buttons = 0;
+        for (key, button) in self.keys {
+            if buttons & button.mask() == 0 && crate::sys::is_key_pressed(key.legacy_char()) {
+                buttons |= button.mask();
+            }
+        }
+        buttons
+    }
+}
+
+/// A controller with a standard layout, updated once per frame like [`super::Keyboard`].
+pub struct Gamepad {
+    index: u32,
+    pub deadzone: Deadzone,
+    pub mapping: KeyboardMapping,
+    connected: bool,
+    buttons: u32,
+    last_buttons: u32,
+    held_frames: [u16; NUM_BUTTONS],
+    left_stick: [i16; 2],
+    right_stick: [i16; 2],
+    triggers: [u8; 2],
+}
+
+impl Gamepad {
+    /// Pad `index` with the default deadzone and keyboard mapping.
+    pub fn new(index: u32) -> Self {
+        Self::with_mapping(index, KeyboardMapping::DEFAULT)
+    }
+
+    pub fn with_mapping(index: u32, mapping: KeyboardMapping) -> Self {
+        Self {
+            index,
+            deadzone: Deadzone::default(),
+            mapping,
+            connected: false,
+            buttons: 0,
+            last_buttons: 0,
+            held_frames: [0; NUM_BUTTONS],
+            left_stick: [0, 0],
+            right_stick: [0, 0],
+            triggers: [0, 0],
+        }
+    }
+
+    pub fn update(&mut self) {
+        let mut raw = RawGamepadState::default();
+        self.connected = crate::sys::gamepad_state(self.index, &mut raw);
+        if !self.connected {
+            raw = RawGamepadState::default();
+        }
+
+        self.left_stick = self.deadzone.apply_stick(raw.left_stick);
+        self.right_stick = self.deadzone.apply_stick(raw.right_stick);
+        self.triggers = [
+            self.deadzone.apply_trigger(raw.triggers[0]),
+            self.deadzone.apply_trigger(raw.triggers[1]),
+        ];
+
+        let mut buttons = raw.buttons & ((1 << NUM_BUTTONS) - 1);
+        if raw.triggers[0] > self.deadzone.trigger_press {
+            buttons |= GamepadButton::LeftTrigger.mask();
+        }
+        if raw.triggers[1] > self.deadzone.trigger_press {
+            buttons |= GamepadButton::RightTrigger.mask();
+        }
+        let press = self.deadzone.stick_press.min(i16::MAX as u16) as i16;
+        if self.left_stick[1] <= -press {
+            buttons |= GamepadButton::DPadUp.mask();
+        }
+        if self.left_stick[1] >= press {
+            buttons |= GamepadButton::DPadDown.mask();
+        }
+        if self.left_stick[0] <= -press {
+            buttons |= GamepadButton::DPadLeft.mask();
+        }
+        if self.left_stick[0] >= press {
+            buttons |= GamepadButton::DPadRight.mask();
+        }
+        buttons |= self.mapping.poll();
+
+        self.last_buttons = self.buttons;
+        self.buttons = buttons;
+        for (i, held) in self.held_frames.iter_mut().enumerate() {
+            if self.buttons & self.last_buttons & (1 << i) != 0 {
+                *held = held.saturating_add(1);
+            } else {
+                *held = 0;
+            }
+        }
+    }
+
+    /// A real controller answered the last update, the keyboard mapping works either way.
+    #[inline(always)]
+    pub fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    #[inline(always)]
+    pub fn is_down(&self, button: GamepadButton) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    #[inline(always)]
+    pub fn just_pressed(&self, button: GamepadButton) -> bool {
+        (self.buttons & !self.last_buttons) & button.mask() != 0
+    }
+
+    #[inline(always)]
+    pub fn just_released(&self, button: GamepadButton) -> bool {
+        (self.last_buttons & !self.buttons) & button.mask() != 0
+    }
+
+    /// Number of updates the button has been held for, `0` on the frame it was pressed.
+    #[inline(always)]
+    pub fn held_frames(&self, button: GamepadButton) -> u32 {
+        self.held_frames[button as usize] as u32
+    }
+
+    /// True on the press and then every `interval` frames once the button has been held for
+    /// more than `delay` frames. This is the usual "DAS" used for menus and falling blocks.
+    pub fn auto_repeat(&self, button: GamepadButton, delay: u32, interval: u32) -> bool {
+        if self.just_pressed(button) {
+            return true;
+        }
+        let held = self.held_frames(button);
+        self.is_down(button) && held > delay && held.is_multiple_of(interval.max(1))
+    }
+
+    /// Left stick after the deadzone, `-32767..=32767`.
+    #[inline(always)]
+    pub fn left_stick(&self) -> [i16; 2] {
+        self.left_stick
+    }
+
+    /// Right stick after the deadzone, `-32767..=32767`.
+    #[inline(always)]
+    pub fn right_stick(&self) -> [i16; 2] {
+        self.right_stick
+    }
+
+    /// Left trigger after the deadzone, `0..=255`.
+    #[inline(always)]
+    pub fn left_trigger(&self) -> u8 {
+        self.triggers[0]
+    }
+
+    /// Right trigger after the deadzone, `0..=255`.
+    #[inline(always)]
+    pub fn right_trigger(&self) -> u8 {
+        self.triggers[1]
+    }
+}
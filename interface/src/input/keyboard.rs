@@ -0,0 +1,452 @@
+/// Physical key codes as delivered by the host. The values line up with the codes the legacy
+/// `is_key_pressed` syscall already used for non letter keys (`'\x25'` is left, `'\n'` is enter..).
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum KeyCode {
+    Backspace = 8,
+    Tab = 9,
+    Enter = 10,
+    Shift = 16,
+    Control = 17,
+    Alt = 18,
+    Pause = 19,
+    CapsLock = 20,
+    Escape = 27,
+    Space = 32,
+    PageUp = 33,
+    PageDown = 34,
+    End = 35,
+    Home = 36,
+    Left = 37,
+    Up = 38,
+    Right = 39,
+    Down = 40,
+    Comma = 44,
+    Minus = 45,
+    Period = 46,
+    Slash = 47,
+    Num0 = 48,
+    Num1 = 49,
+    Num2 = 50,
+    Num3 = 51,
+    Num4 = 52,
+    Num5 = 53,
+    Num6 = 54,
+    Num7 = 55,
+    Num8 = 56,
+    Num9 = 57,
+    Semicolon = 59,
+    Equals = 61,
+    A = 65,
+    B = 66,
+    C = 67,
+    D = 68,
+    E = 69,
+    F = 70,
+    G = 71,
+    H = 72,
+    I = 73,
+    J = 74,
+    K = 75,
+    L = 76,
+    M = 77,
+    N = 78,
+    O = 79,
+    P = 80,
+    Q = 81,
+    R = 82,
+    S = 83,
+    T = 84,
+    U = 85,
+    V = 86,
+    W = 87,
+    X = 88,
+    Y = 89,
+    Z = 90,
+    OpenBracket = 91,
+    BackSlash = 92,
+    CloseBracket = 93,
+    F1 = 112,
+    F2 = 113,
+    F3 = 114,
+    F4 = 115,
+    F5 = 116,
+    F6 = 117,
+    F7 = 118,
+    F8 = 119,
+    F9 = 120,
+    F10 = 121,
+    F11 = 122,
+    F12 = 123,
+    Delete = 127,
+    Insert = 155,
+    Meta = 157,
+    BackQuote = 192,
+    Quote = 222,
+}
+
+impl KeyCode {
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        use KeyCode::*;
+        Some(match raw {
+            8 => Backspace,
+            9 => Tab,
+            10 => Enter,
+            16 => Shift,
+            17 => Control,
+            18 => Alt,
+            19 => Pause,
+            20 => CapsLock,
+            27 => Escape,
+            32 => Space,
+            33 => PageUp,
+            34 => PageDown,
+            35 => End,
+            36 => Home,
+            37 => Left,
+            38 => Up,
+            39 => Right,
+            40 => Down,
+            44 => Comma,
+            45 => Minus,
+            46 => Period,
+            47 => Slash,
+            48 => Num0,
+            49 => Num1,
+            50 => Num2,
+            51 => Num3,
+            52 => Num4,
+            53 => Num5,
+            54 => Num6,
+            55 => Num7,
+            56 => Num8,
+            57 => Num9,
+            59 => Semicolon,
+            61 => Equals,
+            65 => A,
+            66 => B,
+            67 => C,
+            68 => D,
+            69 => E,
+            70 => F,
+            71 => G,
+            72 => H,
+            73 => I,
+            74 => J,
+            75 => K,
+            76 => L,
+            77 => M,
+            78 => N,
+            79 => O,
+            80 => P,
+            81 => Q,
+            82 => R,
+            83 => S,
+            84 => T,
+            85 => U,
+            86 => V,
+            87 => W,
+            88 => X,
+            89 => Y,
+            90 => Z,
+            91 => OpenBracket,
+            92 => BackSlash,
+            93 => CloseBracket,
+            112 => F1,
+            113 => F2,
+            114 => F3,
+            115 => F4,
+            116 => F5,
+            117 => F6,
+            118 => F7,
+            119 => F8,
+            120 => F9,
+            121 => F10,
+            122 => F11,
+            123 => F12,
+            127 => Delete,
+            155 => Insert,
+            157 => Meta,
+            192 => BackQuote,
+            222 => Quote,
+            _ => return None,
+        })
+    }
+
+    #[inline(always)]
+    pub fn as_raw(self) -> u8 {
+        self as u8
+    }
+
+    /// The char the legacy `is_key_pressed` syscall expects for this key, letters are lower
+    /// case there.
+    pub fn legacy_char(self) -> char {
+        let raw = self as u8;
+        if raw.is_ascii_uppercase() {
+            raw.to_ascii_lowercase() as char
+        } else {
+            raw as char
+        }
+    }
+}
+
+/// Modifier keys held when an event was generated.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Self(0);
+    pub const SHIFT: Self = Self(1);
+    pub const CONTROL: Self = Self(2);
+    pub const ALT: Self = Self(4);
+    pub const META: Self = Self(8);
+
+    #[inline(always)]
+    pub const fn from_bits(bits: u8) -> Self {
+        Self(bits & 0xF)
+    }
+    #[inline(always)]
+    pub const fn bits(self) -> u8 {
+        self.0
+    }
+    #[inline(always)]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+    #[inline(always)]
+    pub const fn shift(self) -> bool {
+        self.contains(Self::SHIFT)
+    }
+    #[inline(always)]
+    pub const fn control(self) -> bool {
+        self.contains(Self::CONTROL)
+    }
+    #[inline(always)]
+    pub const fn alt(self) -> bool {
+        self.contains(Self::ALT)
+    }
+    #[inline(always)]
+    pub const fn meta(self) -> bool {
+        self.contains(Self::META)
+    }
+}
+
+impl core::ops::BitOr for Modifiers {
+    type Output = Self;
+    fn bitor(self, rhs: Self) -> Self::Output {
+        Self(self.0 | rhs.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum KeyEventKind {
+    Down,
+    Up,
+    /// Sent by the host while a key is held, using the OS repeat rate.
+    Repeat,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct KeyEvent {
+    pub kind: KeyEventKind,
+    pub key: KeyCode,
+    pub modifiers: Modifiers,
+}
+
+impl KeyEvent {
+    /// Decodes the packed `kind << 24 | modifiers << 16 | key_code` form returned by
+    /// [`crate::sys::poll_key_event`]. Unknown kinds and keys decode to `None`.
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        let kind = match raw >> 24 {
+            1 => KeyEventKind::Down,
+            2 => KeyEventKind::Up,
+            3 => KeyEventKind::Repeat,
+            _ => return None,
+        };
+        Some(Self {
+            kind,
+            key: KeyCode::from_raw(raw & 0xFFFF)?,
+            modifiers: Modifiers::from_bits((raw >> 16) as u8),
+        })
+    }
+
+    /// Pops the next event from the host queue, skipping events for keys we don't know.
+    pub fn poll() -> Option<Self> {
+        loop {
+            let raw = crate::sys::poll_key_event();
+            if raw == 0 {
+                return None;
+            }
+            if let Some(event) = Self::from_raw(raw) {
+                return Some(event);
+            }
+        }
+    }
+}
+
+pub const MAX_EVENTS_PER_FRAME: usize = 32;
+pub const MAX_TEXT_PER_FRAME: usize = 64;
+
+/// Per frame keyboard state built from the host event queue.
+///
+/// Call [`Keyboard::update`] once per frame, before reading any state. Every query then
+/// describes what happened between the previous `update` and this one.
+pub struct Keyboard {
+    down: [u32; 8],
+    pressed: [u32; 8],
+    released: [u32; 8],
+    repeated: [u32; 8],
+    held_frames: [u16; 256],
+    modifiers: Modifiers,
+    events: [Option<KeyEvent>; MAX_EVENTS_PER_FRAME],
+    num_events: usize,
+    text: [u8; MAX_TEXT_PER_FRAME],
+    text_len: usize,
+}
+
+impl Default for Keyboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[inline(always)]
+fn bit(key: KeyCode) -> (usize, u32) {
+    let raw = key as usize;
+    (raw >> 5, 1 << (raw & 31))
+}
+
+impl Keyboard {
+    pub const fn new() -> Self {
+        Self {
+            down: [0; 8],
+            pressed: [0; 8],
+            released: [0; 8],
+            repeated: [0; 8],
+            held_frames: [0; 256],
+            modifiers: Modifiers::NONE,
+            events: [None; MAX_EVENTS_PER_FRAME],
+            num_events: 0,
+            text: [0; MAX_TEXT_PER_FRAME],
+            text_len: 0,
+        }
+    }
+
+    /// Drains the key and text queues. Events past [`MAX_EVENTS_PER_FRAME`] still update key
+    /// state but aren't kept for [`Keyboard::events`], the same goes for text.
+    pub fn update(&mut self) {
+        self.pressed = [0; 8];
+        self.released = [0; 8];
+        self.repeated = [0; 8];
+        self.num_events = 0;
+        self.text_len = 0;
+
+        for (i, word) in self.down.iter().enumerate() {
+            for b in 0..32 {
+                let held = &mut self.held_frames[i * 32 + b];
+                if word & (1 << b) != 0 {
+                    *held = held.saturating_add(1);
+                } else {
+                    *held = 0;
+                }
+            }
+        }
+
+        while let Some(event) = KeyEvent::poll() {
+            self.handle_event(event);
+        }
+
+        while let Some(char) = crate::sys::poll_text_input() {
+            let mut buf = [0u8; 4];
+            let encoded = char.encode_utf8(&mut buf).as_bytes();
+            if self.text_len + encoded.len() <= MAX_TEXT_PER_FRAME {
+                self.text[self.text_len..self.text_len + encoded.len()].copy_from_slice(encoded);
+                self.text_len += encoded.len();
+            }
+        }
+    }
+
+    /// Applies a single event, [`Keyboard::update`] calls this for everything in the queue.
+    pub fn handle_event(&mut self, event: KeyEvent) {
+        let (word, mask) = bit(event.key);
+        match event.kind {
+            KeyEventKind::Down => {
+                if self.down[word] & mask == 0 {
+                    self.pressed[word] |= mask;
+                    self.held_frames[event.key as usize] = 0;
+                }
+                self.down[word] |= mask;
+            }
+            KeyEventKind::Up => {
+                if self.down[word] & mask != 0 {
+                    self.released[word] |= mask;
+                }
+                self.down[word] &= !mask;
+            }
+            KeyEventKind::Repeat => {
+                self.repeated[word] |= mask;
+            }
+        }
+        self.modifiers = event.modifiers;
+
+        if self.num_events < MAX_EVENTS_PER_FRAME {
+            self.events[self.num_events] = Some(event);
+            self.num_events += 1;
+        }
+    }
+
+    #[inline(always)]
+    pub fn is_down(&self, key: KeyCode) -> bool {
+        let (word, mask) = bit(key);
+        self.down[word] & mask != 0
+    }
+
+    /// Went down since the last update.
+    #[inline(always)]
+    pub fn just_pressed(&self, key: KeyCode) -> bool {
+        let (word, mask) = bit(key);
+        self.pressed[word] & mask != 0
+    }
+
+    /// Went up since the last update.
+    #[inline(always)]
+    pub fn just_released(&self, key: KeyCode) -> bool {
+        let (word, mask) = bit(key);
+        self.released[word] & mask != 0
+    }
+
+    /// Pressed, or the host sent a repeat for it since the last update. Useful for menus and
+    /// text fields that should follow the OS repeat rate.
+    #[inline(always)]
+    pub fn pressed_or_repeated(&self, key: KeyCode) -> bool {
+        let (word, mask) = bit(key);
+        (self.pressed[word] | self.repeated[word]) & mask != 0
+    }
+
+    /// Number of updates the key has been held for, `0` on the frame it was pressed and while
+    /// it is up.
+    #[inline(always)]
+    pub fn held_frames(&self, key: KeyCode) -> u32 {
+        if self.is_down(key) {
+            self.held_frames[key as usize] as u32
+        } else {
+            0
+        }
+    }
+
+    /// Modifiers of the most recent event.
+    #[inline(always)]
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Events received by the last update, in order.
+    pub fn events(&self) -> impl Iterator<Item = KeyEvent> + '_ {
+        self.events[..self.num_events].iter().flatten().copied()
+    }
+
+    /// Text typed since the last update.
+    pub fn text(&self) -> &str {
+        unsafe { core::str::from_utf8_unchecked(&self.text[..self.text_len]) }
+    }
+}
@@ -0,0 +1,7 @@
+pub mod gamepad;
+pub mod keyboard;
+pub mod mouse;
+
+pub use gamepad::{Deadzone, Gamepad, GamepadButton, KeyboardMapping};
+pub use keyboard::{KeyCode, KeyEvent, KeyEventKind, Keyboard, Modifiers};
+pub use mouse::{Mouse, MouseButton};
@@ -0,0 +1,142 @@
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MouseButton {
+    Left = 0,
+    Right = 1,
+    Middle = 2,
+    Back = 3,
+    Forward = 4,
+}
+
+impl MouseButton {
+    pub const ALL: [MouseButton; 5] = [
+        MouseButton::Left,
+        MouseButton::Right,
+        MouseButton::Middle,
+        MouseButton::Back,
+        MouseButton::Forward,
+    ];
+
+    #[inline(always)]
+    const fn mask(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+const INSIDE_BIT: u32 = 1 << 31;
+
+/// Per frame pointer state, built on the `mouse_*` syscalls.
+///
+/// Like [`super::Keyboard`] call [`Mouse::update`] once per frame, queries describe the change
+/// between the last two updates.
+#[derive(Default)]
+pub struct Mouse {
+    position: [i32; 2],
+    last_position: [i32; 2],
+    buttons: u32,
+    last_buttons: u32,
+    held_frames: [u16; 5],
+    wheel: [i32; 2],
+}
+
+impl Mouse {
+    pub const fn new() -> Self {
+        Self {
+            position: [0, 0],
+            last_position: [0, 0],
+            buttons: 0,
+            last_buttons: 0,
+            held_frames: [0; 5],
+            wheel: [0, 0],
+        }
+    }
+
+    pub fn update(&mut self) {
+        self.last_position = self.position;
+        self.last_buttons = self.buttons;
+        self.position = crate::sys::mouse_position();
+        self.buttons = crate::sys::mouse_buttons();
+        self.wheel = crate::sys::mouse_wheel();
+
+        for button in MouseButton::ALL {
+            let held = &mut self.held_frames[button as usize];
+            if self.last_buttons & self.buttons & button.mask() != 0 {
+                *held = held.saturating_add(1);
+            } else {
+                *held = 0;
+            }
+        }
+    }
+
+    /// Cursor position in screen pixels, may be outside the screen, see [`Mouse::is_inside`].
+    #[inline(always)]
+    pub fn position(&self) -> [i32; 2] {
+        self.position
+    }
+
+    /// Cursor position clamped to a `width` x `height` screen, `None` while the cursor is
+    /// outside of it or the screen is empty, like before `init_screen`.
+    pub fn screen_position(&self, width: u32, height: u32) -> Option<[u32; 2]> {
+        if !self.is_inside() || width == 0 || height == 0 {
+            return None;
+        }
+        let max = |size: u32| size.min(i32::MAX as u32) as i32 - 1;
+        Some([
+            self.position[0].clamp(0, max(width)) as u32,
+            self.position[1].clamp(0, max(height)) as u32,
+        ])
+    }
+
+    /// Movement since the last update.
+    #[inline(always)]
+    pub fn delta(&self) -> [i32; 2] {
+        [
+            self.position[0] - self.last_position[0],
+            self.position[1] - self.last_position[1],
+        ]
+    }
+
+    /// Wheel notches since the last update as `[horizontal, vertical]`.
+    #[inline(always)]
+    pub fn wheel(&self) -> [i32; 2] {
+        self.wheel
+    }
+
+    #[inline(always)]
+    pub fn is_inside(&self) -> bool {
+        self.buttons & INSIDE_BIT != 0
+    }
+
+    /// The cursor moved onto the screen since the last update.
+    #[inline(always)]
+    pub fn entered(&self) -> bool {
+        (self.buttons & !self.last_buttons) & INSIDE_BIT != 0
+    }
+
+    /// The cursor moved off the screen since the last update.
+    #[inline(always)]
+    pub fn left(&self) -> bool {
+        (self.last_buttons & !self.buttons) & INSIDE_BIT != 0
+    }
+
+    #[inline(always)]
+    pub fn is_down(&self, button: MouseButton) -> bool {
+        self.buttons & button.mask() != 0
+    }
+
+    #[inline(always)]
+    pub fn just_pressed(&self, button: MouseButton) -> bool {
+        (self.buttons & !self.last_buttons) & button.mask() != 0
+    }
+
+    #[inline(always)]
+    pub fn just_released(&self, button: MouseButton) -> bool {
+        (self.last_buttons & !self.buttons) & button.mask() != 0
+    }
+
+    /// Number of updates the button has been held for, `0` on the frame it was pressed.
+    #[inline(always)]
+    pub fn held_frames(&self, button: MouseButton) -> u32 {
+        self.held_frames[button as usize] as u32
+    }
+}
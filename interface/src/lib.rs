@@ -7,19 +7,47 @@
 #![feature(naked_functions)]
 #![feature(allow_internal_unstable)]
 #![feature(linkage)]
+#![feature(allocator_api)]
 #![allow(incomplete_features)]
 #![feature(generic_const_exprs)]
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "host")]
+extern crate std;
+
+pub mod audio;
+pub mod binlog;
+pub mod color;
 pub mod core_rust;
+pub mod executor;
+pub mod fs;
+#[cfg(feature = "alloc")]
+pub mod heap;
+#[cfg(feature = "host")]
+pub mod host;
+pub mod input;
+pub mod log;
+pub mod mem;
+pub mod panic;
+pub mod rand;
+pub mod stack;
 pub mod sys;
+#[cfg(feature = "alloc")]
+pub mod task;
+pub mod time;
+pub mod trap;
 
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[linkage = "extern_weak"]
 pub static _sp: usize = 0;
+#[cfg(target_arch = "mips")]
 #[linkage = "extern_weak"]
 #[no_mangle]
 pub static _heap: usize = 0;
 
+#[cfg(target_arch = "mips")]
 #[no_mangle]
 #[naked]
 #[link_section = ".text.start"]
@@ -49,6 +77,7 @@ pub fn black_box<T>(dummy: T) -> T {
         ret
     }
 }
+#[cfg(target_arch = "mips")]
 #[inline(always)]
 /// # Safety
 /// this is the start of the heap dont touch it if you arent the global allocator ;)
@@ -0,0 +1,503 @@
+//! Leveled logging to a set of [`Sink`]s.
+//!
+//! Nothing is logged until [`init`] sets the levels, sinks are added with [`add_sink`]:
+//!
+//! ```ignore
+//! static OVERLAY: log::MemorySink<6, 32> = log::MemorySink::new();
+//!
+//! log::init(
+//!     log::Config::new(log::LevelFilter::Info)
+//!         .with_filters(&[("binary::tetris", log::LevelFilter::Debug)])
+//!         .with_timestamps(true),
+//! );
+//! log::add_sink(&log::CONSOLE);
+//! log::add_sink(&OVERLAY);
+//! log::info!("started in {}us", interface::sys::get_micros());
+//! ```
+//!
+//! With the `log` feature [`init`] also installs this as the logger of the `log` crate, so
+//! dependencies using its macros end up in the same sinks.
+
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt::{self, Write},
+};
+
+use crate::fs;
+
+pub use crate::{debug, error, info, log, trace, warn};
+
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Error = 1,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl Level {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN",
+            Level::Info => "INFO",
+            Level::Debug => "DEBUG",
+            Level::Trace => "TRACE",
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+/// The most verbose [`Level`] let through, `Off` lets nothing through.
+#[repr(u8)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum LevelFilter {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LevelFilter {
+    #[inline(always)]
+    pub const fn allows(self, level: Level) -> bool {
+        level as u8 <= self as u8
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug)]
+pub struct Config {
+    level: LevelFilter,
+    filters: &'static [(&'static str, LevelFilter)],
+    timestamps: bool,
+}
+
+impl Config {
+    /// Logs up to `level` everywhere, without timestamps.
+    pub const fn new(level: LevelFilter) -> Self {
+        Self {
+            level,
+            filters: &[],
+            timestamps: false,
+        }
+    }
+
+    /// Levels for module paths, a filter applies to the module and everything below it and the
+    /// longest matching path wins. Modules no filter matches use the level from [`Config::new`].
+    pub const fn with_filters(mut self, filters: &'static [(&'static str, LevelFilter)]) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Stamps every record with [`crate::sys::get_micros`].
+    pub const fn with_timestamps(mut self, timestamps: bool) -> Self {
+        self.timestamps = timestamps;
+        self
+    }
+
+    pub fn level_for(&self, module_path: &str) -> LevelFilter {
+        let mut best: Option<(usize, LevelFilter)> = None;
+        for (path, level) in self.filters {
+            let matches = match module_path.strip_prefix(path) {
+                Some(rest) => rest.is_empty() || rest.starts_with("::"),
+                None => false,
+            };
+            if matches && best.is_none_or(|(len, _)| path.len() > len) {
+                best = Some((path.len(), *level));
+            }
+        }
+        best.map_or(self.level, |(_, level)| level)
+    }
+
+    /// The most verbose level any module can log at.
+    pub fn max_level(&self) -> LevelFilter {
+        self.filters
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.level, core::cmp::max)
+    }
+}
+
+pub struct Record<'a> {
+    pub level: Level,
+    /// Module path of the call site, or the target given to the `log` crate.
+    pub target: &'a str,
+    pub args: fmt::Arguments<'a>,
+    /// Microseconds since start when timestamps are on.
+    pub micros: Option<u64>,
+}
+
+impl Record<'_> {
+    /// `[   1.234567] INFO  target: message` without a line break, the default format of the
+    /// sinks in here.
+    pub fn write_line(&self, out: &mut impl Write) -> fmt::Result {
+        if let Some(micros) = self.micros {
+            write!(out, "[{:4}.{:06}] ", micros / 1_000_000, micros % 1_000_000)?;
+        }
+        write!(out, "{:<5} {}: {}", self.level, self.target, self.args)
+    }
+}
+
+/// Somewhere records go. Sinks live in statics and are only ever used from the one guest
+/// thread, implementations can use interior mutability without locking.
+pub trait Sink: Sync {
+    fn write(&self, record: &Record);
+
+    fn flush(&self) {}
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Sinks [`add_sink`] takes before it starts refusing them.
+pub const MAX_SINKS: usize = 4;
+
+struct Logger {
+    config: Cell<Config>,
+    max_level: Cell<LevelFilter>,
+    sinks: Cell<[Option<&'static dyn Sink>; MAX_SINKS]>,
+    /// Set while records are handed to the sinks, a sink logging itself would recurse.
+    busy: Cell<bool>,
+}
+
+// the guest is single threaded
+unsafe impl Sync for Logger {}
+
+static LOGGER: Logger = Logger {
+    config: Cell::new(Config::new(LevelFilter::Off)),
+    max_level: Cell::new(LevelFilter::Off),
+    sinks: Cell::new([None; MAX_SINKS]),
+    busy: Cell::new(false),
+};
+
+/// Sets the levels and whether to timestamp, can be called again to change them. Sinks added
+/// before stay.
+pub fn init(config: Config) {
+    LOGGER.config.set(config);
+    LOGGER.max_level.set(config.max_level());
+    #[cfg(feature = "log")]
+    bridge::install(config.max_level());
+}
+
+/// Returns `false` when all [`MAX_SINKS`] slots are taken.
+pub fn add_sink(sink: &'static dyn Sink) -> bool {
+    let mut sinks = LOGGER.sinks.get();
+    match sinks.iter_mut().find(|slot| slot.is_none()) {
+        Some(slot) => {
+            *slot = Some(sink);
+            LOGGER.sinks.set(sinks);
+            true
+        }
+        None => false,
+    }
+}
+
+#[inline(always)]
+pub fn max_level() -> LevelFilter {
+    LOGGER.max_level.get()
+}
+
+#[inline(always)]
+pub fn enabled(level: Level, module_path: &str) -> bool {
+    max_level().allows(level) && LOGGER.config.get().level_for(module_path).allows(level)
+}
+
+/// Hands a record to every sink, without checking the filters. The macros check
+/// [`enabled`] first.
+pub fn log(level: Level, target: &str, args: fmt::Arguments) {
+    if LOGGER.busy.replace(true) {
+        return;
+    }
+    let record = Record {
+        level,
+        target,
+        args,
+        micros: LOGGER.config.get().timestamps.then(crate::sys::get_micros),
+    };
+    for sink in LOGGER.sinks.get().into_iter().flatten() {
+        sink.write(&record);
+    }
+    LOGGER.busy.set(false);
+}
+
+pub fn flush() {
+    for sink in LOGGER.sinks.get().into_iter().flatten() {
+        sink.flush();
+    }
+}
+
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $($arg:tt)+) => {{
+        let level = $level;
+        if $crate::log::enabled(level, core::module_path!()) {
+            $crate::log::log(level, core::module_path!(), core::format_args!($($arg)+));
+        }
+    }};
+}
+
+#[macro_export]
+macro_rules! error {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Error, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! warn {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Warn, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! info {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Info, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! debug {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Debug, $($arg)+)
+    };
+}
+
+#[macro_export]
+macro_rules! trace {
+    ($($arg:tt)+) => {
+        $crate::log!($crate::log::Level::Trace, $($arg)+)
+    };
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Prints every record on its own line through the console syscalls.
+pub struct ConsoleSink;
+
+pub static CONSOLE: ConsoleSink = ConsoleSink;
+
+impl Sink for ConsoleSink {
+    fn write(&self, record: &Record) {
+        let mut console = crate::core_rust::Console::new();
+        let _ = record.write_line(&mut console);
+        let _ = console.write_char('\n');
+    }
+}
+
+/// Keeps the last `LINES` records, each cut to `WIDTH` bytes, for drawing a log overlay.
+pub struct MemorySink<const LINES: usize, const WIDTH: usize> {
+    lines: UnsafeCell<[Line<WIDTH>; LINES]>,
+    /// Index of the oldest line.
+    head: Cell<usize>,
+    count: Cell<usize>,
+    reading: Cell<bool>,
+}
+
+#[derive(Copy, Clone)]
+struct Line<const WIDTH: usize> {
+    level: Level,
+    len: usize,
+    buf: [u8; WIDTH],
+}
+
+impl<const WIDTH: usize> Write for Line<WIDTH> {
+    /// Drops whatever doesn't fit.
+    fn write_str(&mut self, str: &str) -> fmt::Result {
+        let mut len = str.len().min(WIDTH - self.len);
+        while !str.is_char_boundary(len) {
+            len -= 1;
+        }
+        self.buf[self.len..self.len + len].copy_from_slice(&str.as_bytes()[..len]);
+        self.len += len;
+        Ok(())
+    }
+}
+
+// the guest is single threaded
+unsafe impl<const LINES: usize, const WIDTH: usize> Sync for MemorySink<LINES, WIDTH> {}
+
+impl<const LINES: usize, const WIDTH: usize> Default for MemorySink<LINES, WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const LINES: usize, const WIDTH: usize> MemorySink<LINES, WIDTH> {
+    pub const fn new() -> Self {
+        Self {
+            lines: UnsafeCell::new(
+                [Line {
+                    level: Level::Info,
+                    len: 0,
+                    buf: [0; WIDTH],
+                }; LINES],
+            ),
+            head: Cell::new(0),
+            count: Cell::new(0),
+            reading: Cell::new(false),
+        }
+    }
+
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.count.get()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.count.get() == 0
+    }
+
+    pub fn clear(&self) {
+        self.head.set(0);
+        self.count.set(0);
+    }
+
+    /// Calls `f` with every kept line, oldest first. Records logged from inside `f` are
+    /// dropped.
+    pub fn for_each_line(&self, mut f: impl FnMut(Level, &str)) {
+        self.reading.set(true);
+        let lines = unsafe { &*self.lines.get() };
+        for i in 0..self.count.get() {
+            let line = &lines[(self.head.get() + i) % LINES];
+            f(
+                line.level,
+                core::str::from_utf8(&line.buf[..line.len]).unwrap_or(""),
+            );
+        }
+        self.reading.set(false);
+    }
+}
+
+impl<const LINES: usize, const WIDTH: usize> Sink for MemorySink<LINES, WIDTH> {
+    fn write(&self, record: &Record) {
+        if LINES == 0 || self.reading.get() {
+            return;
+        }
+        let lines = unsafe { &mut *self.lines.get() };
+        let index = if self.count.get() < LINES {
+            self.count.set(self.count.get() + 1);
+            (self.head.get() + self.count.get() - 1) % LINES
+        } else {
+            let oldest = self.head.get();
+            self.head.set((oldest + 1) % LINES);
+            oldest
+        };
+        let line = &mut lines[index];
+        line.level = record.level;
+        line.len = 0;
+        // the screen is narrow, leave out the timestamp and target
+        let _ = write!(line, "{} {}", &record.level.as_str()[..1], record.args);
+    }
+}
+
+/// Appends records to a file in the [`crate::fs`] sandbox.
+pub struct FileSink {
+    file: UnsafeCell<Option<fs::File>>,
+}
+
+// the guest is single threaded
+unsafe impl Sync for FileSink {}
+
+impl Default for FileSink {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSink {
+    /// A sink that drops everything until [`FileSink::open`] succeeds.
+    pub const fn new() -> Self {
+        Self {
+            file: UnsafeCell::new(None),
+        }
+    }
+
+    /// Appends to `path`, creating it if needed. Closes the file opened before.
+    pub fn open(&self, path: &str) -> fs::Result<()> {
+        let file = fs::OpenOptions::new()
+            .write(true)
+            .append(true)
+            .create(true)
+            .open(path)?;
+        unsafe { *self.file.get() = Some(file) };
+        Ok(())
+    }
+
+    pub fn close(&self) {
+        unsafe { *self.file.get() = None };
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&self, record: &Record) {
+        if let Some(file) = unsafe { &mut *self.file.get() } {
+            let _ = record.write_line(file);
+            let _ = file.write_char('\n');
+        }
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[cfg(feature = "log")]
+mod bridge {
+    use super::{Level, LevelFilter};
+
+    struct Bridge;
+
+    impl ::log::Log for Bridge {
+        fn enabled(&self, metadata: &::log::Metadata) -> bool {
+            super::enabled(level(metadata.level()), metadata.target())
+        }
+
+        fn log(&self, record: &::log::Record) {
+            if self.enabled(record.metadata()) {
+                super::log(level(record.level()), record.target(), *record.args());
+            }
+        }
+
+        fn flush(&self) {
+            super::flush();
+        }
+    }
+
+    static BRIDGE: Bridge = Bridge;
+
+    fn level(level: ::log::Level) -> Level {
+        match level {
+            ::log::Level::Error => Level::Error,
+            ::log::Level::Warn => Level::Warn,
+            ::log::Level::Info => Level::Info,
+            ::log::Level::Debug => Level::Debug,
+            ::log::Level::Trace => Level::Trace,
+        }
+    }
+
+    pub(super) fn install(max_level: LevelFilter) {
+        // fails when it is already installed, which is fine when `init` runs again
+        let _ = unsafe { ::log::set_logger_racy(&BRIDGE) };
+        unsafe {
+            ::log::set_max_level_racy(match max_level {
+                LevelFilter::Off => ::log::LevelFilter::Off,
+                LevelFilter::Error => ::log::LevelFilter::Error,
+                LevelFilter::Warn => ::log::LevelFilter::Warn,
+                LevelFilter::Info => ::log::LevelFilter::Info,
+                LevelFilter::Debug => ::log::LevelFilter::Debug,
+                LevelFilter::Trace => ::log::LevelFilter::Trace,
+            })
+        };
+    }
+}
@@ -0,0 +1,178 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::Cell,
+    marker::PhantomData,
+    mem::{self, needs_drop},
+    ptr::{self, NonNull},
+};
+
+use super::bump::{self, Cursor};
+
+/// Put in front of every value that needs dropping, the arena drops them newest first.
+struct DropEntry {
+    prev: *mut DropEntry,
+    value: *mut u8,
+    drop: unsafe fn(*mut u8),
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+    ptr::drop_in_place(value as *mut T);
+}
+
+/// Like [`super::Bump`], but for values: [`Arena::alloc`] moves one in and hands back a
+/// reference that lives as long as the arena. Their destructors run on [`Arena::reset`] or when
+/// the arena is dropped, so a frame's worth of objects can own things and still be thrown away
+/// at once.
+///
+/// ```ignore
+/// let mut frame = Arena::with_capacity(16 * 1024);
+/// let label = frame.alloc_str("score");
+/// let particle = frame.alloc(Particle::new(x, y)).unwrap();
+/// frame.reset();
+/// ```
+pub struct Arena<'a> {
+    cursor: Cursor,
+    drops: Cell<*mut DropEntry>,
+    _buf: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Arena<'a> {
+    /// Hands out `buf`, a static array or anything else that outlives the arena.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            cursor: Cursor::new(buf),
+            drops: Cell::new(ptr::null_mut()),
+            _buf: PhantomData,
+        }
+    }
+
+    /// `capacity` bytes taken from the global heap, given back when the arena is dropped.
+    #[cfg(feature = "alloc")]
+    pub fn with_capacity(capacity: usize) -> Arena<'static> {
+        Arena {
+            cursor: Cursor::with_capacity(capacity),
+            drops: Cell::new(ptr::null_mut()),
+            _buf: PhantomData,
+        }
+    }
+
+    /// Moves `value` into the arena, gives it back when there is no room. `T: 'a` so whatever
+    /// it borrows is still there when the arena drops it.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T: 'a>(&self, value: T) -> Result<&mut T, T> {
+        if !needs_drop::<T>() {
+            let Some(ptr) = self.cursor.bump(Layout::new::<T>()) else {
+                return Err(value);
+            };
+            let ptr = ptr.as_ptr() as *mut T;
+            unsafe {
+                ptr.write(value);
+                return Ok(&mut *ptr);
+            }
+        }
+
+        let Ok((layout, offset)) = Layout::new::<DropEntry>().extend(Layout::new::<T>()) else {
+            return Err(value);
+        };
+        let Some(entry) = self.cursor.bump(layout) else {
+            return Err(value);
+        };
+        unsafe {
+            let entry = entry.as_ptr() as *mut DropEntry;
+            let ptr = (entry as *mut u8).add(offset) as *mut T;
+            ptr.write(value);
+            entry.write(DropEntry {
+                prev: self.drops.get(),
+                value: ptr as *mut u8,
+                drop: drop_value::<T>,
+            });
+            self.drops.set(entry);
+            Ok(&mut *ptr)
+        }
+    }
+
+    /// A copy of `slice` in the arena, `None` when there is no room.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T: Copy>(&self, slice: &[T]) -> Option<&mut [T]> {
+        let ptr = self.cursor.bump(Layout::for_value(slice))?.as_ptr() as *mut T;
+        unsafe {
+            ptr::copy_nonoverlapping(slice.as_ptr(), ptr, slice.len());
+            Some(core::slice::from_raw_parts_mut(ptr, slice.len()))
+        }
+    }
+
+    /// A copy of `str` in the arena, `None` when there is no room.
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_str(&self, str: &str) -> Option<&mut str> {
+        let bytes = self.alloc_slice_copy(str.as_bytes())?;
+        Some(unsafe { core::str::from_utf8_unchecked_mut(bytes) })
+    }
+
+    /// Bytes handed out, alignment padding and bookkeeping for destructors included.
+    #[inline(always)]
+    pub fn used(&self) -> usize {
+        self.cursor.used()
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.cursor.capacity()
+    }
+
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.used()
+    }
+
+    /// Drops every value, newest first, and makes all of the memory available again.
+    pub fn reset(&mut self) {
+        self.drop_values();
+        self.cursor.reset();
+    }
+
+    fn drop_values(&mut self) {
+        let mut entry = mem::take(self.drops.get_mut());
+        while !entry.is_null() {
+            unsafe {
+                let DropEntry { prev, value, drop } = entry.read();
+                drop(value);
+                entry = prev;
+            }
+        }
+    }
+}
+
+impl Drop for Arena<'_> {
+    fn drop(&mut self) {
+        self.drop_values();
+    }
+}
+
+/// Raw memory, nothing in it is dropped by the arena.
+unsafe impl Allocator for Arena<'_> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        bump::allocate(&self.cursor, layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.cursor.release(ptr, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        bump::grow(&self.cursor, ptr, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        bump::shrink(&self.cursor, ptr, old_layout, new_layout)
+    }
+}
@@ -0,0 +1,240 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::Cell,
+    marker::PhantomData,
+    ptr::{self, NonNull},
+};
+
+/// Memory handed out front to back, what [`Bump`] and [`super::Arena`] are built on.
+pub(super) struct Cursor {
+    base: *mut u8,
+    len: usize,
+    used: Cell<usize>,
+    /// Came from the global heap and goes back to it on drop.
+    #[cfg(feature = "alloc")]
+    owned: bool,
+}
+
+/// Alignment of the memory [`Bump::with_capacity`] and [`super::Arena::with_capacity`] take from
+/// the global heap.
+#[cfg(feature = "alloc")]
+const OWNED_ALIGN: usize = 8;
+
+impl Cursor {
+    pub(super) fn new(buf: &mut [u8]) -> Self {
+        Self {
+            base: buf.as_mut_ptr(),
+            len: buf.len(),
+            used: Cell::new(0),
+            #[cfg(feature = "alloc")]
+            owned: false,
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    pub(super) fn with_capacity(capacity: usize) -> Self {
+        let layout = Layout::from_size_align(capacity.max(1), OWNED_ALIGN).unwrap();
+        let base = unsafe { alloc::alloc::alloc(layout) };
+        if base.is_null() {
+            alloc::alloc::handle_alloc_error(layout);
+        }
+        Self {
+            base,
+            len: capacity,
+            used: Cell::new(0),
+            owned: true,
+        }
+    }
+
+    #[inline(always)]
+    pub(super) fn used(&self) -> usize {
+        self.used.get()
+    }
+
+    #[inline(always)]
+    pub(super) fn capacity(&self) -> usize {
+        self.len
+    }
+
+    #[inline(always)]
+    pub(super) fn reset(&self) {
+        self.used.set(0);
+    }
+
+    /// Takes `layout` from the unused part, `None` when it doesn't fit.
+    pub(super) fn bump(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let addr = self.base as usize + self.used.get();
+        let padding = addr.wrapping_neg() & (layout.align() - 1);
+        let start = self.used.get().checked_add(padding)?;
+        let end = start.checked_add(layout.size())?;
+        if end > self.len {
+            return None;
+        }
+        self.used.set(end);
+        NonNull::new(unsafe { self.base.add(start) })
+    }
+
+    /// Whether `ptr` of `size` bytes is the last thing handed out.
+    #[inline(always)]
+    fn is_last(&self, ptr: NonNull<u8>, size: usize) -> bool {
+        ptr.as_ptr() as usize + size == self.base as usize + self.used.get()
+    }
+
+    /// Takes the last allocation back, nothing else can be.
+    pub(super) fn release(&self, ptr: NonNull<u8>, size: usize) {
+        if self.is_last(ptr, size) {
+            self.used.set(ptr.as_ptr() as usize - self.base as usize);
+        }
+    }
+
+    /// Resizes the last allocation without moving it, `false` for anything else or when there
+    /// is no room.
+    pub(super) fn resize(&self, ptr: NonNull<u8>, old_size: usize, new_size: usize) -> bool {
+        if !self.is_last(ptr, old_size) {
+            return false;
+        }
+        let start = ptr.as_ptr() as usize - self.base as usize;
+        if new_size > self.len - start {
+            return false;
+        }
+        self.used.set(start + new_size);
+        true
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Drop for Cursor {
+    fn drop(&mut self) {
+        if self.owned {
+            let layout = Layout::from_size_align(self.len.max(1), OWNED_ALIGN).unwrap();
+            unsafe { alloc::alloc::dealloc(self.base, layout) };
+        }
+    }
+}
+
+/// [`Allocator`] on top of a [`Cursor`], shared by [`Bump`] and [`super::Arena`].
+pub(super) fn allocate(cursor: &Cursor, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+    let ptr = cursor.bump(layout).ok_or(AllocError)?;
+    Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+}
+
+/// Grows in place when `ptr` is the last allocation, otherwise moves it.
+pub(super) unsafe fn grow(
+    cursor: &Cursor,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+) -> Result<NonNull<[u8]>, AllocError> {
+    if ptr.as_ptr() as usize & (new_layout.align() - 1) == 0
+        && cursor.resize(ptr, old_layout.size(), new_layout.size())
+    {
+        return Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()));
+    }
+    let new = allocate(cursor, new_layout)?;
+    ptr::copy_nonoverlapping(ptr.as_ptr(), new.as_ptr() as *mut u8, old_layout.size());
+    Ok(new)
+}
+
+/// Shrinks in place, only the last allocation gives anything back.
+pub(super) unsafe fn shrink(
+    cursor: &Cursor,
+    ptr: NonNull<u8>,
+    old_layout: Layout,
+    new_layout: Layout,
+) -> Result<NonNull<[u8]>, AllocError> {
+    if ptr.as_ptr() as usize & (new_layout.align() - 1) != 0 {
+        let new = allocate(cursor, new_layout)?;
+        ptr::copy_nonoverlapping(ptr.as_ptr(), new.as_ptr() as *mut u8, new_layout.size());
+        return Ok(new);
+    }
+    cursor.resize(ptr, old_layout.size(), new_layout.size());
+    Ok(NonNull::slice_from_raw_parts(ptr, new_layout.size()))
+}
+
+/// Scratch memory handed out front to back and all given back at once with [`Bump::reset`].
+/// Freeing only returns memory for the last allocation, which is also the one that grows in
+/// place, so a single growing `Vec` never copies.
+///
+/// ```ignore
+/// let mut scratch = Bump::new(unsafe { &mut *core::ptr::addr_of_mut!(SCRATCH) });
+/// loop {
+///     let mut visible = Vec::new_in(&scratch);
+///     // ...
+///     drop(visible);
+///     scratch.reset();
+/// }
+/// ```
+pub struct Bump<'a> {
+    cursor: Cursor,
+    _buf: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> Bump<'a> {
+    /// Hands out `buf`, a static array or anything else that outlives the allocations.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            cursor: Cursor::new(buf),
+            _buf: PhantomData,
+        }
+    }
+
+    /// `capacity` bytes taken from the global heap, given back when the bump is dropped.
+    #[cfg(feature = "alloc")]
+    pub fn with_capacity(capacity: usize) -> Bump<'static> {
+        Bump {
+            cursor: Cursor::with_capacity(capacity),
+            _buf: PhantomData,
+        }
+    }
+
+    /// Bytes handed out, alignment padding included.
+    #[inline(always)]
+    pub fn used(&self) -> usize {
+        self.cursor.used()
+    }
+
+    #[inline(always)]
+    pub fn capacity(&self) -> usize {
+        self.cursor.capacity()
+    }
+
+    #[inline(always)]
+    pub fn remaining(&self) -> usize {
+        self.capacity() - self.used()
+    }
+
+    /// Makes all of it available again, every allocation has to be gone, which the borrow
+    /// checker makes sure of.
+    #[inline(always)]
+    pub fn reset(&mut self) {
+        self.cursor.reset();
+    }
+}
+
+unsafe impl Allocator for Bump<'_> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        allocate(&self.cursor, layout)
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        self.cursor.release(ptr, layout.size());
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        grow(&self.cursor, ptr, old_layout, new_layout)
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        shrink(&self.cursor, ptr, old_layout, new_layout)
+    }
+}
@@ -0,0 +1,354 @@
+//! The C memory functions the compiler emits calls to, a word at a time. `core_rust` exports
+//! them under their C names on the guest, here they are plain functions so they can be tested
+//! against the host's libc.
+//!
+//! Unaligned heads and tails go a byte at a time until the destination is word aligned. When
+//! the source is then aligned too words are copied as they are, otherwise every destination
+//! word is put together from two aligned source words with shifts that depend on the byte
+//! order. Nothing outside of the given ranges is read, except by [`strlen`] which reads whole
+//! aligned words and so never crosses a page. The byte order is a parameter of [`load_head`],
+//! [`load_tail`] and [`merge`] so the big endian shifts the guest uses can be tested on a little
+//! endian host too.
+//!
+//! Everything is `#[inline(always)]` so the bodies end up in the functions named `memcpy` and
+//! friends: LLVM won't turn loops in those into calls to themselves.
+
+use core::mem::size_of;
+
+const WORD: usize = size_of::<usize>();
+const ONES: usize = usize::MAX / 0xff;
+const HIGHS: usize = ONES << 7;
+const BIG_ENDIAN: bool = cfg!(target_endian = "big");
+
+#[inline(always)]
+fn from_bytes(bytes: [u8; WORD], big_endian: bool) -> usize {
+    if big_endian {
+        usize::from_be_bytes(bytes)
+    } else {
+        usize::from_le_bytes(bytes)
+    }
+}
+
+/// The bytes `skip..` of the word a misaligned pointer points into, read a byte at a time and
+/// placed where they would be in the aligned word.
+///
+/// # Safety
+/// `src` has to be readable for `WORD - skip` bytes.
+#[inline(always)]
+pub unsafe fn load_head(src: *const u8, skip: usize, big_endian: bool) -> usize {
+    let mut bytes = [0u8; WORD];
+    for (i, byte) in bytes.iter_mut().enumerate().skip(skip) {
+        *byte = *src.add(i - skip);
+    }
+    from_bytes(bytes, big_endian)
+}
+
+/// The bytes `..keep` of the aligned word at `word`, read a byte at a time.
+///
+/// # Safety
+/// `word` has to be readable for `keep` bytes.
+#[inline(always)]
+pub unsafe fn load_tail(word: *const u8, keep: usize, big_endian: bool) -> usize {
+    let mut bytes = [0u8; WORD];
+    for (i, byte) in bytes.iter_mut().enumerate().take(keep) {
+        *byte = *word.add(i);
+    }
+    from_bytes(bytes, big_endian)
+}
+
+/// The word made of the bytes `offset..` of `low` followed by the bytes `..offset` of `high`,
+/// `low` being the one at the lower address. `offset` is `1..WORD`.
+#[inline(always)]
+pub fn merge(low: usize, high: usize, offset: usize, big_endian: bool) -> usize {
+    let shift = offset * 8;
+    if big_endian {
+        (low << shift) | (high >> (WORD * 8 - shift))
+    } else {
+        (low >> shift) | (high << (WORD * 8 - shift))
+    }
+}
+
+/// Where `ptr` is in its word.
+#[inline(always)]
+fn offset_in_word<T>(ptr: *const T) -> usize {
+    ptr as usize & (WORD - 1)
+}
+
+/// Bytes from `ptr` up to the next word boundary.
+#[inline(always)]
+fn to_word_boundary<T>(ptr: *const T) -> usize {
+    (ptr as usize).wrapping_neg() & (WORD - 1)
+}
+
+/// Reads the words of a misaligned source front to back, each one put together from two
+/// aligned words.
+struct ShiftedWords {
+    next: *const usize,
+    low: usize,
+    offset: usize,
+}
+
+impl ShiftedWords {
+    /// `src` must not be aligned and there have to be at least two words of source left for
+    /// every [`ShiftedWords::read`].
+    #[inline(always)]
+    unsafe fn new(src: *const u8) -> Self {
+        let offset = offset_in_word(src);
+        Self {
+            next: src.sub(offset).add(WORD) as *const usize,
+            low: load_head(src, offset, BIG_ENDIAN),
+            offset,
+        }
+    }
+
+    #[inline(always)]
+    unsafe fn read(&mut self) -> usize {
+        let high = *self.next;
+        let word = merge(self.low, high, self.offset, BIG_ENDIAN);
+        self.low = high;
+        self.next = self.next.add(1);
+        word
+    }
+}
+
+//----------------------------------------------------------------
+
+#[inline(always)]
+unsafe fn copy_bytes_forward(dest: *mut u8, src: *const u8, count: usize) {
+    for i in 0..count {
+        *dest.add(i) = *src.add(i);
+    }
+}
+
+#[inline(always)]
+unsafe fn copy_bytes_backward(dest: *mut u8, src: *const u8, count: usize) {
+    for i in (0..count).rev() {
+        *dest.add(i) = *src.add(i);
+    }
+}
+
+/// Front to back, so it is also a `memmove` when `dest` is below `src`.
+#[inline(always)]
+unsafe fn copy_forward(mut dest: *mut u8, mut src: *const u8, mut count: usize) {
+    if count >= 2 * WORD {
+        let head = to_word_boundary(dest);
+        copy_bytes_forward(dest, src, head);
+        dest = dest.add(head);
+        src = src.add(head);
+        count -= head;
+
+        let mut words = dest as *mut usize;
+        if offset_in_word(src) == 0 {
+            let mut src_words = src as *const usize;
+            while count >= WORD {
+                *words = *src_words;
+                words = words.add(1);
+                src_words = src_words.add(1);
+                count -= WORD;
+            }
+            src = src_words as *const u8;
+        } else {
+            let mut shifted = ShiftedWords::new(src);
+            // the aligned word after the one being finished has to be in the source too
+            while count >= 2 * WORD {
+                *words = shifted.read();
+                words = words.add(1);
+                src = src.add(WORD);
+                count -= WORD;
+            }
+        }
+        dest = words as *mut u8;
+    }
+    copy_bytes_forward(dest, src, count);
+}
+
+/// Back to front, for a `memmove` with `dest` above `src`.
+#[inline(always)]
+unsafe fn copy_backward(dest: *mut u8, src: *const u8, mut count: usize) {
+    if count >= 2 * WORD {
+        let tail = offset_in_word(dest.add(count));
+        count -= tail;
+        copy_bytes_backward(dest.add(count), src.add(count), tail);
+
+        let src_end = src.add(count);
+        let offset = offset_in_word(src_end);
+        let mut words = dest.add(count) as *mut usize;
+        if offset == 0 {
+            let mut src_words = src_end as *const usize;
+            while count >= WORD {
+                words = words.sub(1);
+                src_words = src_words.sub(1);
+                *words = *src_words;
+                count -= WORD;
+            }
+        } else {
+            let mut low_word = src_end.sub(offset) as *const usize;
+            let mut high = load_tail(low_word as *const u8, offset, BIG_ENDIAN);
+            while count >= 2 * WORD {
+                low_word = low_word.sub(1);
+                let low = *low_word;
+                words = words.sub(1);
+                *words = merge(low, high, offset, BIG_ENDIAN);
+                high = low;
+                count -= WORD;
+            }
+        }
+    }
+    copy_bytes_backward(dest, src, count);
+}
+
+/// # Safety
+/// `dest` and `src` valid for `count` bytes and not overlapping.
+#[inline(always)]
+pub unsafe fn memcpy(dest: *mut u8, src: *const u8, count: usize) -> *mut u8 {
+    copy_forward(dest, src, count);
+    dest
+}
+
+/// # Safety
+/// `dest` and `src` valid for `count` bytes.
+#[inline(always)]
+pub unsafe fn memmove(dest: *mut u8, src: *const u8, count: usize) -> *mut u8 {
+    if (dest as usize).wrapping_sub(src as usize) >= count {
+        // below the source or not overlapping at all
+        copy_forward(dest, src, count);
+    } else {
+        copy_backward(dest, src, count);
+    }
+    dest
+}
+
+/// # Safety
+/// `dest` valid for `count` bytes.
+#[inline(always)]
+pub unsafe fn memset(dest: *mut u8, value: i32, mut count: usize) -> *mut u8 {
+    let byte = value as u8;
+    let mut ptr = dest;
+    if count >= 2 * WORD {
+        let head = to_word_boundary(ptr);
+        for i in 0..head {
+            *ptr.add(i) = byte;
+        }
+        ptr = ptr.add(head);
+        count -= head;
+
+        let word = ONES * byte as usize;
+        let mut words = ptr as *mut usize;
+        while count >= WORD {
+            *words = word;
+            words = words.add(1);
+            count -= WORD;
+        }
+        ptr = words as *mut u8;
+    }
+    for i in 0..count {
+        *ptr.add(i) = byte;
+    }
+    dest
+}
+
+//----------------------------------------------------------------
+
+/// -1, 0 or 1 like [`core::cmp::Ordering`].
+#[inline(always)]
+fn compare<T: Ord>(a: T, b: T) -> i32 {
+    (a > b) as i32 - (a < b) as i32
+}
+
+/// Words in memory order, as in the first differing byte decides.
+#[inline(always)]
+fn compare_words(a: usize, b: usize) -> i32 {
+    compare(usize::from_be(a), usize::from_be(b))
+}
+
+#[inline(always)]
+unsafe fn compare_bytes(a: *const u8, b: *const u8, count: usize) -> i32 {
+    for i in 0..count {
+        let (a, b) = (*a.add(i), *b.add(i));
+        if a != b {
+            return compare(a, b);
+        }
+    }
+    0
+}
+
+/// # Safety
+/// `a` and `b` valid for `count` bytes.
+#[inline(always)]
+pub unsafe fn memcmp(mut a: *const u8, mut b: *const u8, mut count: usize) -> i32 {
+    if count >= 2 * WORD {
+        let head = to_word_boundary(a);
+        let result = compare_bytes(a, b, head);
+        if result != 0 {
+            return result;
+        }
+        a = a.add(head);
+        b = b.add(head);
+        count -= head;
+
+        let mut words = a as *const usize;
+        if offset_in_word(b) == 0 {
+            let mut b_words = b as *const usize;
+            while count >= WORD {
+                if *words != *b_words {
+                    return compare_words(*words, *b_words);
+                }
+                words = words.add(1);
+                b_words = b_words.add(1);
+                count -= WORD;
+            }
+            b = b_words as *const u8;
+        } else {
+            let mut shifted = ShiftedWords::new(b);
+            while count >= 2 * WORD {
+                let b_word = shifted.read();
+                if *words != b_word {
+                    return compare_words(*words, b_word);
+                }
+                words = words.add(1);
+                b = b.add(WORD);
+                count -= WORD;
+            }
+        }
+        a = words as *const u8;
+    }
+    compare_bytes(a, b, count)
+}
+
+/// [`memcmp`] where only equal or not matters, 0 when equal.
+///
+/// # Safety
+/// `a` and `b` valid for `count` bytes.
+#[inline(always)]
+pub unsafe fn bcmp(a: *const u8, b: *const u8, count: usize) -> i32 {
+    (memcmp(a, b, count) != 0) as i32
+}
+
+/// # Safety
+/// `str` has to be zero terminated.
+#[inline(always)]
+pub unsafe fn strlen(str: *const u8) -> usize {
+    let mut len = 0;
+    let head = to_word_boundary(str);
+    while len < head {
+        if *str.add(len) == 0 {
+            return len;
+        }
+        len += 1;
+    }
+    let mut words = str.add(len) as *const usize;
+    // a word has a zero byte when subtracting one from every byte borrows into a high bit
+    // that wasn't set before
+    loop {
+        let word = *words;
+        if word.wrapping_sub(ONES) & !word & HIGHS != 0 {
+            break;
+        }
+        words = words.add(1);
+        len += WORD;
+    }
+    while *str.add(len) != 0 {
+        len += 1;
+    }
+    len
+}
@@ -0,0 +1,130 @@
+//! Where everything lives in the guest's memory, from the symbols `mips/link.map` defines, and
+//! allocators that work without the global heap.
+//!
+//! ```text
+//! _ram_start  code, data, bss, symbols
+//! _heap       heap::Heap
+//! _heap_end   the stack, growing down
+//! _ram_end    == _stack_start == _sp == _vectors
+//! _vectors_end
+//! ```
+//!
+//! [`Bump`] and [`Arena`] hand out a static array or a block of the global heap front to back
+//! and give it all back at once, for scratch memory that lives a frame. [`Pool`] keeps a fixed
+//! number of slots of one type. All of them implement [`core::alloc::Allocator`], so
+//! `Vec::new_in(&bump)` and `Box::new_in(value, &pool)` work.
+
+mod arena;
+mod bump;
+pub mod intrinsics;
+mod pool;
+
+pub use arena::Arena;
+pub use bump::Bump;
+pub use pool::Pool;
+
+use core::{fmt, ops::Range};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MemoryMap {
+    /// All of the memory the program may use.
+    pub ram: Range<usize>,
+    pub text: Range<usize>,
+    pub heap: Range<usize>,
+    /// The stack starts at the end and grows down towards the heap.
+    pub stack: Range<usize>,
+    /// The exception vectors [`crate::trap::install`] writes, past the end of `ram`.
+    pub vectors: Range<usize>,
+}
+
+impl MemoryMap {
+    #[inline(always)]
+    pub fn ram_size(&self) -> usize {
+        self.ram.end - self.ram.start
+    }
+
+    #[inline(always)]
+    pub fn heap_size(&self) -> usize {
+        self.heap.end - self.heap.start
+    }
+
+    #[inline(always)]
+    pub fn stack_size(&self) -> usize {
+        self.stack.end - self.stack.start
+    }
+}
+
+impl fmt::Display for MemoryMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let regions = [
+            ("ram", &self.ram),
+            ("text", &self.text),
+            ("heap", &self.heap),
+            ("stack", &self.stack),
+            ("vectors", &self.vectors),
+        ];
+        for (i, (name, region)) in regions.into_iter().enumerate() {
+            if i != 0 {
+                writeln!(f)?;
+            }
+            write!(
+                f,
+                "{:<7} {:08x}..{:08x} {:>8} KiB",
+                name,
+                region.start,
+                region.end,
+                (region.end - region.start) / 1024
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// The layout of the running program, every region is empty off target.
+pub fn memory_map() -> MemoryMap {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let (ram_start, ram_end, text_start, text_end, heap_start, heap_end): (
+            usize,
+            usize,
+            usize,
+            usize,
+            usize,
+            usize,
+        );
+        let (vectors_start, vectors_end): (usize, usize);
+        core::arch::asm!(
+            "la {0}, _ram_start",
+            "la {1}, _ram_end",
+            "la {2}, _stext",
+            "la {3}, _etext",
+            "la {4}, _heap",
+            "la {5}, _heap_end",
+            "la {6}, _vectors",
+            "la {7}, _vectors_end",
+            out(reg) ram_start,
+            out(reg) ram_end,
+            out(reg) text_start,
+            out(reg) text_end,
+            out(reg) heap_start,
+            out(reg) heap_end,
+            out(reg) vectors_start,
+            out(reg) vectors_end,
+        );
+        MemoryMap {
+            ram: ram_start..ram_end,
+            text: text_start..text_end,
+            heap: heap_start..heap_end,
+            stack: heap_end..ram_end,
+            vectors: vectors_start..vectors_end,
+        }
+    }
+    #[cfg(not(target_arch = "mips"))]
+    MemoryMap {
+        ram: 0..0,
+        text: 0..0,
+        heap: 0..0,
+        stack: 0..0,
+        vectors: 0..0,
+    }
+}
@@ -0,0 +1,146 @@
+use core::{
+    alloc::{AllocError, Allocator, Layout},
+    cell::{Cell, UnsafeCell},
+    mem::{size_of, ManuallyDrop, MaybeUninit},
+    ptr::NonNull,
+};
+
+/// Holds a value while handed out and the next free slot while not.
+#[repr(C)]
+union Slot<T> {
+    value: ManuallyDrop<MaybeUninit<T>>,
+    next: usize,
+}
+
+/// `N` slots for a `T` each, kept inline, so a pool can be a local, a field or boxed on the
+/// global heap and never touches any other memory. Taking and returning a slot is constant
+/// time, freed slots are reused newest first.
+///
+/// Every allocation is one slot, so it is for `Box::new_in(particle, &pool)` and the like,
+/// anything asking for more than a `T` gets an error.
+///
+/// ```ignore
+/// let particles: Pool<Particle, 256> = Pool::new();
+/// let spark = Box::try_new_in(Particle::spark(x, y), &particles)?;
+/// ```
+pub struct Pool<T, const N: usize> {
+    slots: UnsafeCell<MaybeUninit<[Slot<T>; N]>>,
+    /// First of the freed slots, `N` when there are none.
+    free: Cell<usize>,
+    /// Slots from here on were never handed out and aren't on the free list.
+    fresh: Cell<usize>,
+    len: Cell<usize>,
+}
+
+impl<T, const N: usize> Default for Pool<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Pool<T, N> {
+    pub const fn new() -> Self {
+        Self {
+            slots: UnsafeCell::new(MaybeUninit::uninit()),
+            free: Cell::new(N),
+            fresh: Cell::new(0),
+            len: Cell::new(0),
+        }
+    }
+
+    #[inline(always)]
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Slots handed out.
+    #[inline(always)]
+    pub fn len(&self) -> usize {
+        self.len.get()
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline(always)]
+    pub fn is_full(&self) -> bool {
+        self.len() == N
+    }
+
+    #[inline(always)]
+    fn slot(&self, index: usize) -> *mut Slot<T> {
+        unsafe { (self.slots.get() as *mut Slot<T>).add(index) }
+    }
+
+    /// The slot `ptr` points to, `None` for pointers that aren't one of them.
+    fn index_of(&self, ptr: NonNull<u8>) -> Option<usize> {
+        let offset = (ptr.as_ptr() as usize).checked_sub(self.slot(0) as usize)?;
+        let index = offset / size_of::<Slot<T>>();
+        (offset % size_of::<Slot<T>>() == 0 && index < N).then_some(index)
+    }
+
+    /// Whether one slot has room for `layout`.
+    #[inline(always)]
+    fn fits(layout: Layout) -> bool {
+        let slot = Layout::new::<Slot<T>>();
+        layout.size() <= slot.size() && layout.align() <= slot.align()
+    }
+}
+
+unsafe impl<T, const N: usize> Allocator for Pool<T, N> {
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        if !Self::fits(layout) {
+            return Err(AllocError);
+        }
+        let index = if self.free.get() != N {
+            let index = self.free.get();
+            self.free.set(unsafe { (*self.slot(index)).next });
+            index
+        } else if self.fresh.get() != N {
+            let index = self.fresh.get();
+            self.fresh.set(index + 1);
+            index
+        } else {
+            return Err(AllocError);
+        };
+        self.len.set(self.len.get() + 1);
+        let ptr = NonNull::new(self.slot(index) as *mut u8).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, size_of::<Slot<T>>()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _layout: Layout) {
+        let Some(index) = self.index_of(ptr) else {
+            panic!("pool: freeing {:p}, not one of its slots", ptr);
+        };
+        (*self.slot(index)).next = self.free.get();
+        self.free.set(index);
+        self.len.set(self.len.get() - 1);
+    }
+
+    unsafe fn grow(
+        &self,
+        ptr: NonNull<u8>,
+        _old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        // a slot is as big as it gets
+        if !Self::fits(new_layout) {
+            return Err(AllocError);
+        }
+        Ok(NonNull::slice_from_raw_parts(ptr, size_of::<Slot<T>>()))
+    }
+
+    unsafe fn shrink(
+        &self,
+        ptr: NonNull<u8>,
+        _old_layout: Layout,
+        new_layout: Layout,
+    ) -> Result<NonNull<[u8]>, AllocError> {
+        if !Self::fits(new_layout) {
+            return Err(AllocError);
+        }
+        Ok(NonNull::slice_from_raw_parts(ptr, size_of::<Slot<T>>()))
+    }
+}
@@ -0,0 +1,359 @@
+//! What a panic handler needs to say more than the message: the registers, a backtrace and
+//! the names of the functions in it.
+//!
+//! MIPS frames don't keep `$fp` and `$ra` at a fixed offset, each function saves them where
+//! its prologue puts them. The backtrace follows the saved frame pointers (`.cargo/config`
+//! forces them on) and reads the `addiu $sp, $sp, -N` / `sw $ra` / `sw $fp` of every function
+//! on the way to find the caller's. It stops at `_start`, or at the first frame that doesn't
+//! look like one.
+//!
+//! Names come from the table in the `.symbols` section. `binary/build.rs` generates it from
+//! the previous build's `mips/bin/com.o` and [`embed_symbols!`](crate::embed_symbols) puts it
+//! there. The section comes after everything else, so the table never moves the code it
+//! describes and building twice is enough to get it right. Without a table, addresses are
+//! printed as they are.
+//!
+//! ```ignore
+//! #[panic_handler]
+//! fn panic(info: &core::panic::PanicInfo) -> ! {
+//!     interface::println!("{}", interface::panic::Report::capture(info));
+//!     interface::sys::halt();
+//! }
+//! ```
+
+use core::{cell::Cell, fmt, panic::PanicInfo};
+
+pub const MAX_FRAMES: usize = 24;
+
+/// Name of the section the symbol table is placed in.
+pub const SYMBOL_SECTION: &str = ".symbols";
+/// Address, size and name offset, each a big endian `u32`. The table is a `u32` count, the
+/// entries sorted by address, then the names, nul terminated.
+pub const SYMBOL_ENTRY_SIZE: usize = 12;
+
+/// How far back from a return address its function's prologue is looked for, in instructions.
+#[cfg(target_arch = "mips")]
+const MAX_FUNCTION_LEN: u32 = 0x4000;
+/// How far into a function the saves of `$ra` and `$fp` are looked for, in instructions.
+#[cfg(target_arch = "mips")]
+const MAX_PROLOGUE_LEN: u32 = 32;
+
+/// `addiu $sp, $sp, imm`
+#[cfg(target_arch = "mips")]
+const ADDIU_SP_SP: u32 = 0x27bd;
+/// `sw $ra, imm($sp)`
+#[cfg(target_arch = "mips")]
+const SW_RA_SP: u32 = 0xafbf;
+/// `sw $fp, imm($sp)`
+#[cfg(target_arch = "mips")]
+const SW_FP_SP: u32 = 0xafbe;
+
+//----------------------------------------------------------------
+
+/// The registers that matter for finding out where things went wrong. `ra` is whatever the
+/// last call left in it, the return address of the panicking function is the first frame of the
+/// [`Backtrace`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub pc: u32,
+    pub sp: u32,
+    pub fp: u32,
+    pub ra: u32,
+    pub gp: u32,
+}
+
+impl Registers {
+    /// The registers at the call site, all zero off target.
+    #[inline(always)]
+    pub fn capture() -> Self {
+        #[cfg(target_arch = "mips")]
+        unsafe {
+            let (pc, sp, fp, ra, gp): (u32, u32, u32, u32, u32);
+            core::arch::asm!(
+                ".set noreorder",
+                "move {ra}, $ra",
+                "bal 1f",
+                "nop",
+                "1:",
+                "move {pc}, $ra",
+                "move $ra, {ra}",
+                ".set reorder",
+                "move {sp}, $sp",
+                "move {fp}, $fp",
+                "move {gp}, $gp",
+                pc = out(reg) pc,
+                sp = out(reg) sp,
+                fp = out(reg) fp,
+                ra = out(reg) ra,
+                gp = out(reg) gp,
+            );
+            Self { pc, sp, fp, ra, gp }
+        }
+        #[cfg(not(target_arch = "mips"))]
+        Self::default()
+    }
+}
+
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "pc {:08x} sp {:08x} fp {:08x} ra {:08x} gp {:08x}",
+            self.pc, self.sp, self.fp, self.ra, self.gp
+        )
+    }
+}
+
+//----------------------------------------------------------------
+
+/// Call sites, innermost first.
+#[derive(Clone, Copy)]
+pub struct Backtrace {
+    frames: [u32; MAX_FRAMES],
+    len: usize,
+}
+
+/// Where a function's prologue saved `$ra` and `$fp`, relative to its `$fp`.
+#[cfg(target_arch = "mips")]
+struct SavedAt {
+    ra: u32,
+    fp: u32,
+}
+
+impl Backtrace {
+    #[inline(always)]
+    pub const fn empty() -> Self {
+        Self {
+            frames: [0; MAX_FRAMES],
+            len: 0,
+        }
+    }
+
+    /// Starts at the caller of `capture`, empty off target.
+    #[inline(never)]
+    pub fn capture() -> Self {
+        let registers = Registers::capture();
+        Self::walk(registers.pc, registers.fp)
+    }
+
+    /// Walks up from the function running `pc` with its frame pointer `fp`.
+    #[cfg(target_arch = "mips")]
+    pub fn walk(mut pc: u32, mut fp: u32) -> Self {
+        let (text_start, text_end, stack_top): (u32, u32, u32);
+        unsafe {
+            core::arch::asm!(
+                "la {0}, _stext",
+                "la {1}, _etext",
+                "la {2}, _sp",
+                out(reg) text_start,
+                out(reg) text_end,
+                out(reg) stack_top,
+            );
+        }
+        let text = text_start..text_end;
+        let read = |addr: u32| unsafe { core::ptr::read_volatile(addr as usize as *const u32) };
+
+        let mut trace = Self::empty();
+        while trace.len < MAX_FRAMES && fp & 3 == 0 && fp < stack_top && text.contains(&pc) {
+            let Some(saved) = Self::saved_at(pc, text_start, read) else {
+                break;
+            };
+            if fp.saturating_add(saved.ra.max(saved.fp)) >= stack_top {
+                break;
+            }
+            let ra = read(fp + saved.ra);
+            let caller_fp = read(fp + saved.fp);
+            if !text.contains(&ra) {
+                break;
+            }
+            // the jal, the emulator has no delay slots so `$ra` is the instruction right after it
+            trace.frames[trace.len] = ra - 4;
+            trace.len += 1;
+            // callers are further up the stack, anything else is garbage
+            if caller_fp <= fp {
+                break;
+            }
+            pc = ra;
+            fp = caller_fp;
+        }
+        trace
+    }
+
+    #[cfg(not(target_arch = "mips"))]
+    pub fn walk(_pc: u32, _fp: u32) -> Self {
+        Self::empty()
+    }
+
+    #[cfg(target_arch = "mips")]
+    fn saved_at(pc: u32, text_start: u32, read: impl Fn(u32) -> u32) -> Option<SavedAt> {
+        let mut start = pc;
+        while start > text_start && pc - start < MAX_FUNCTION_LEN * 4 {
+            start -= 4;
+            let inst = read(start);
+            // epilogues give the frame back with a positive immediate, and calls can move
+            // `$sp` for their arguments too, only the prologue saves `$ra` and `$fp` after
+            if inst >> 16 != ADDIU_SP_SP || inst & 0x8000 == 0 {
+                continue;
+            }
+            let (mut ra, mut fp) = (None, None);
+            for at in (start + 4..pc).step_by(4).take(MAX_PROLOGUE_LEN as usize) {
+                let inst = read(at);
+                // the first ones, `trap::trap_entry` saves the registers again after
+                match inst >> 16 {
+                    SW_RA_SP => _ = ra.get_or_insert(inst & 0xffff),
+                    SW_FP_SP => _ = fp.get_or_insert(inst & 0xffff),
+                    _ => {}
+                }
+            }
+            if let (Some(ra), Some(fp)) = (ra, fp) {
+                return Some(SavedAt { ra, fp });
+            }
+        }
+        None
+    }
+
+    #[inline(always)]
+    pub fn frames(&self) -> &[u32] {
+        &self.frames[..self.len]
+    }
+}
+
+impl fmt::Display for Backtrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, addr) in self.frames().iter().enumerate() {
+            write!(f, "{:>2}: {:08x}", i, addr)?;
+            if let Some(symbol) = symbolize(*addr) {
+                write!(f, " {}+{:#x}", symbol.name, symbol.offset)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+//----------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    /// Demangled, without the hash.
+    pub name: &'static str,
+    /// How far into the function the address is.
+    pub offset: u32,
+}
+
+#[cfg(target_arch = "mips")]
+fn symbol_table() -> &'static [u8] {
+    unsafe {
+        let (start, end): (*const u8, *const u8);
+        core::arch::asm!(
+            "la {0}, _ssymbols",
+            "la {1}, _esymbols",
+            out(reg) start,
+            out(reg) end,
+        );
+        core::slice::from_raw_parts(start, end as usize - start as usize)
+    }
+}
+
+#[cfg(not(target_arch = "mips"))]
+fn symbol_table() -> &'static [u8] {
+    &[]
+}
+
+/// The function `addr` is in, `None` without a symbol table or for addresses outside of every
+/// function in it.
+pub fn symbolize(addr: u32) -> Option<Symbol> {
+    lookup(symbol_table(), addr)
+}
+
+/// [`symbolize`] against any table in the `.symbols` layout.
+pub fn lookup(table: &'static [u8], addr: u32) -> Option<Symbol> {
+    let u32_at = |at: usize| -> Option<u32> {
+        Some(u32::from_be_bytes(table.get(at..at + 4)?.try_into().ok()?))
+    };
+    let count = u32_at(0)? as usize;
+    let entry = |index: usize| 4 + index * SYMBOL_ENTRY_SIZE;
+    let names = entry(count);
+
+    // the last entry starting at or before addr
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let mid = (low + high) / 2;
+        if u32_at(entry(mid))? <= addr {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+    let at = entry(low.checked_sub(1)?);
+    let (start, size, name_at) = (u32_at(at)?, u32_at(at + 4)?, u32_at(at + 8)? as usize);
+    if addr - start >= size {
+        return None;
+    }
+    let name = table.get(names + name_at..)?;
+    let name = &name[..name.iter().position(|byte| *byte == 0)?];
+    Some(Symbol {
+        name: core::str::from_utf8(name).ok()?,
+        offset: addr - start,
+    })
+}
+
+/// Places a table generated by `host::symbols` in [`SYMBOL_SECTION`], the argument is the
+/// path to it like `include_bytes!` takes.
+#[macro_export]
+macro_rules! embed_symbols {
+    ($($path:tt)*) => {
+        #[used]
+        #[link_section = ".symbols"]
+        static SYMBOLS: [u8; include_bytes!($($path)*).len()] = *include_bytes!($($path)*);
+    };
+}
+
+//----------------------------------------------------------------
+
+/// Everything a crash report shows, `Display` renders all of it.
+pub struct Report<'a> {
+    pub info: &'a PanicInfo<'a>,
+    pub registers: Registers,
+    pub backtrace: Backtrace,
+    pub stack: crate::stack::Usage,
+}
+
+impl<'a> Report<'a> {
+    /// Call this first thing in the panic handler, the backtrace starts at the handler's caller.
+    #[inline(always)]
+    pub fn capture(info: &'a PanicInfo<'a>) -> Self {
+        Self {
+            info,
+            registers: Registers::capture(),
+            backtrace: Backtrace::capture(),
+            stack: crate::stack::usage(),
+        }
+    }
+}
+
+impl fmt::Display for Report<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.info)?;
+        writeln!(f, "{}", self.registers)?;
+        writeln!(f, "{}", self.stack)?;
+        writeln!(f, "backtrace:")?;
+        if self.backtrace.frames().is_empty() {
+            writeln!(f, "  unavailable")?;
+        }
+        self.backtrace.fmt(f)
+    }
+}
+
+struct Panicking(Cell<bool>);
+
+// the guest is single threaded
+unsafe impl Sync for Panicking {}
+
+static PANICKING: Panicking = Panicking(Cell::new(false));
+
+/// `true` for the first panic, `false` when panicking again while handling it. Then the
+/// handler should stick to printing the message, whatever it was doing might panic again.
+pub fn first_panic() -> bool {
+    !PANICKING.0.replace(true)
+}
@@ -0,0 +1,282 @@
+//! Seeded pseudo random numbers that don't go through the host, so the same seed always plays
+//! out the same way. `sys::rand_range` is still there for when that doesn't matter.
+//!
+//! [`Pcg32`] is the one to use, [`Xorshift32`] is smaller and faster but weaker and
+//! [`SplitMix64`] turns any `u64`, like [`time_seed`], into well mixed seeds. Everything else
+//! comes from [`Rng`]: ranges without modulo bias, shuffling and picking, also by weight.
+//!
+//! ```ignore
+//! let mut rng = Pcg32::new(replay.seed);
+//! let piece = rng.range(0..7u8);
+//! rng.shuffle(&mut bag);
+//! let enemy = rng.choose_weighted(&[60, 30, 10]);
+//! ```
+
+use core::ops::{Range, RangeInclusive};
+
+pub trait Rng {
+    fn next_u32(&mut self) -> u32;
+
+    fn next_u64(&mut self) -> u64 {
+        (self.next_u32() as u64) << 32 | self.next_u32() as u64
+    }
+
+    fn fill_bytes(&mut self, bytes: &mut [u8]) {
+        for chunk in bytes.chunks_mut(4) {
+            let random = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&random[..chunk.len()]);
+        }
+    }
+
+    /// Uniform in `0..n`, panics when `n` is 0.
+    fn below(&mut self, n: u32) -> u32 {
+        assert!(n != 0, "below(0)");
+        // Lemire's multiply and shift, retried for the few values that would be biased
+        let mut product = self.next_u32() as u64 * n as u64;
+        if (product as u32) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (product as u32) < threshold {
+                product = self.next_u32() as u64 * n as u64;
+            }
+        }
+        (product >> 32) as u32
+    }
+
+    /// [`Rng::below`] for 64 bits.
+    fn below_u64(&mut self, n: u64) -> u64 {
+        assert!(n != 0, "below_u64(0)");
+        if n <= u32::MAX as u64 {
+            return self.below(n as u32) as u64;
+        }
+        let mut product = self.next_u64() as u128 * n as u128;
+        if (product as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (product as u64) < threshold {
+                product = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (product >> 64) as u64
+    }
+
+    /// Uniform in `range`, `a..b` or `a..=b` of any integer type. Panics when it is empty.
+    fn range<T: SampleUniform>(&mut self, range: impl SampleRange<T>) -> T {
+        range.sample(self)
+    }
+
+    /// True `numerator` out of `denominator` times.
+    fn chance(&mut self, numerator: u32, denominator: u32) -> bool {
+        self.below(denominator) < numerator
+    }
+
+    /// Uniform in `0.0..1.0`.
+    fn unit_f32(&mut self) -> f32 {
+        (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+    }
+
+    /// Every order equally likely.
+    fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, below_usize(self, i + 1));
+        }
+    }
+
+    /// `None` when `items` is empty.
+    fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+        Some(&items[below_usize(self, items.len())])
+    }
+
+    /// An index into `weights`, each picked in proportion to its weight. `None` when they add
+    /// up to 0.
+    fn choose_weighted(&mut self, weights: &[u32]) -> Option<usize> {
+        let total: u64 = weights.iter().map(|&weight| weight as u64).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut pick = self.below_u64(total);
+        weights.iter().position(|&weight| {
+            if pick < weight as u64 {
+                return true;
+            }
+            pick -= weight as u64;
+            false
+        })
+    }
+}
+
+fn below_usize<R: Rng + ?Sized>(rng: &mut R, n: usize) -> usize {
+    rng.below_u64(n as u64) as usize
+}
+
+//----------------------------------------------------------------
+
+/// Integers [`Rng::range`] can pick from.
+pub trait SampleUniform: Copy + PartialOrd {
+    /// Uniform in `low..=high`.
+    fn sample_inclusive<R: Rng + ?Sized>(rng: &mut R, low: Self, high: Self) -> Self;
+
+    /// Uniform in `low..high`.
+    fn sample_exclusive<R: Rng + ?Sized>(rng: &mut R, low: Self, high: Self) -> Self;
+}
+
+macro_rules! impl_sample_uniform {
+    ($($ty:ty => $unsigned:ty),* $(,)?) => {$(
+        impl SampleUniform for $ty {
+            fn sample_inclusive<R: Rng + ?Sized>(rng: &mut R, low: Self, high: Self) -> Self {
+                assert!(low <= high, "sampling from an empty range");
+                let span = high.wrapping_sub(low) as $unsigned as u64;
+                let offset = match span.checked_add(1) {
+                    Some(len) => rng.below_u64(len),
+                    None => rng.next_u64(),
+                };
+                low.wrapping_add(offset as $ty)
+            }
+
+            fn sample_exclusive<R: Rng + ?Sized>(rng: &mut R, low: Self, high: Self) -> Self {
+                assert!(low < high, "sampling from an empty range");
+                Self::sample_inclusive(rng, low, high - 1)
+            }
+        }
+    )*};
+}
+
+impl_sample_uniform!(
+    u8 => u8,
+    u16 => u16,
+    u32 => u32,
+    u64 => u64,
+    usize => usize,
+    i8 => u8,
+    i16 => u16,
+    i32 => u32,
+    i64 => u64,
+    isize => usize,
+);
+
+/// `a..b` and `a..=b`, see [`Rng::range`].
+pub trait SampleRange<T> {
+    fn sample<R: Rng + ?Sized>(self, rng: &mut R) -> T;
+}
+
+impl<T: SampleUniform> SampleRange<T> for Range<T> {
+    fn sample<R: Rng + ?Sized>(self, rng: &mut R) -> T {
+        T::sample_exclusive(rng, self.start, self.end)
+    }
+}
+
+impl<T: SampleUniform> SampleRange<T> for RangeInclusive<T> {
+    fn sample<R: Rng + ?Sized>(self, rng: &mut R) -> T {
+        T::sample_inclusive(rng, *self.start(), *self.end())
+    }
+}
+
+//----------------------------------------------------------------
+
+/// A seed that differs from run to run, from the nanosecond clock.
+pub fn time_seed() -> u64 {
+    SplitMix64::new(crate::time::Instant::now().as_nanos()).next_u64()
+}
+
+/// Adds a constant and mixes, every seed is fine. Mostly for making seeds for the others.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub const fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+}
+
+impl Rng for SplitMix64 {
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+}
+
+/// Marsaglia's 32 bit xorshift, three shifts a number. Never returns 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Xorshift32 {
+    state: u32,
+}
+
+impl Xorshift32 {
+    /// A zero seed would only ever give zeros, it is replaced.
+    pub const fn new(seed: u32) -> Self {
+        Self {
+            state: if seed == 0 { 0x9e37_79b9 } else { seed },
+        }
+    }
+
+    pub fn from_time() -> Self {
+        Self::new(time_seed() as u32)
+    }
+}
+
+impl Rng for Xorshift32 {
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+}
+
+/// PCG32 (XSH RR): a 64 bit LCG with a permuted 32 bit output. Seeds that differ only a little
+/// still give unrelated sequences, and each stream is a different sequence for the same seed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pcg32 {
+    state: u64,
+    /// Odd, picks the stream.
+    increment: u64,
+}
+
+impl Pcg32 {
+    const MULTIPLIER: u64 = 6364136223846793005;
+    const DEFAULT_STREAM: u64 = 0x0a02_bdbf_7bb3_c0a7;
+
+    pub const fn new(seed: u64) -> Self {
+        Self::with_stream(seed, Self::DEFAULT_STREAM)
+    }
+
+    /// Seeded like the reference `pcg32_srandom(seed, stream)`.
+    pub const fn with_stream(seed: u64, stream: u64) -> Self {
+        let increment = stream << 1 | 1;
+        let state = increment
+            .wrapping_add(seed)
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(increment);
+        Self { state, increment }
+    }
+
+    pub fn from_time() -> Self {
+        Self::new(time_seed())
+    }
+}
+
+impl Rng for Pcg32 {
+    #[inline(always)]
+    fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old
+            .wrapping_mul(Self::MULTIPLIER)
+            .wrapping_add(self.increment);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
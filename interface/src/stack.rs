@@ -0,0 +1,154 @@
+//! Stack painting and overflow detection. The stack is [`crate::mem::MemoryMap::stack`], right
+//! above the heap, and nothing stops it from growing into it.
+//!
+//! [`paint`] fills the unused part of the stack with [`PAINT`] as early as possible, how far
+//! the paint has been overwritten since is how deep the stack has ever been, see [`usage`]. The
+//! lowest [`GUARD_BYTES`] of it are a canary: with [`set_guard`] on every `update_screen`
+//! checks that they are still painted and panics with a stack overflow if they aren't, instead
+//! of carrying on with the heap corrupted.
+//!
+//! ```ignore
+//! pub fn main() {
+//!     interface::stack::paint();
+//!     interface::stack::set_guard(true);
+//!     // ...
+//! }
+//! ```
+
+use core::{cell::Cell, fmt};
+
+/// What unused stack is filled with.
+pub const PAINT: u32 = 0x5a5a_a5a5;
+/// The bottom of the stack that must never be touched.
+pub const GUARD_BYTES: usize = 256;
+
+struct Guard(Cell<bool>);
+
+// the guest is single threaded
+unsafe impl Sync for Guard {}
+
+static GUARD: Guard = Guard(Cell::new(false));
+
+/// The current stack pointer, 0 off target.
+#[inline(always)]
+pub fn stack_pointer() -> usize {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let sp: usize;
+        core::arch::asm!("move {0}, $sp", out(reg) sp, options(nomem, nostack));
+        sp
+    }
+    #[cfg(not(target_arch = "mips"))]
+    0
+}
+
+/// The stack's words from the bottom up to `end`.
+fn words(end: usize) -> impl Iterator<Item = *mut u32> {
+    let stack = crate::mem::memory_map().stack;
+    let start = (stack.start + 3) & !3;
+    (start..end.min(stack.end))
+        .step_by(4)
+        .map(|addr| addr as *mut u32)
+}
+
+/// Fills everything below the caller's frame with [`PAINT`], call it first thing in `main`.
+#[inline(never)]
+pub fn paint() {
+    // a loop in asm, anything calling out would put its frame where the paint goes
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let stack = crate::mem::memory_map().stack;
+        let start = (stack.start + 3) & !3;
+        let end = stack_pointer().min(stack.end) & !3;
+        if start < end {
+            core::arch::asm!(
+                "1:",
+                "sw {paint}, 0({addr})",
+                "addiu {addr}, {addr}, 4",
+                "sltu {more}, {addr}, {end}",
+                "bnez {more}, 1b",
+                paint = in(reg) PAINT,
+                addr = inout(reg) start => _,
+                end = in(reg) end,
+                more = out(reg) _,
+                options(nostack),
+            );
+        }
+    }
+}
+
+/// Bytes between the top of the stack and the lowest word that isn't [`PAINT`] anymore, the
+/// whole stack when it wasn't painted.
+pub fn high_water_mark() -> usize {
+    let stack = crate::mem::memory_map().stack;
+    let lowest_used = words(stack.end)
+        .find(|word| unsafe { word.read_volatile() } != PAINT)
+        .map_or(stack.end, |word| word as usize);
+    stack.end - lowest_used
+}
+
+/// Whether the bottom [`GUARD_BYTES`] of the stack are still painted.
+pub fn guard_intact() -> bool {
+    let stack = crate::mem::memory_map().stack;
+    words(stack.start + GUARD_BYTES).all(|word| unsafe { word.read_volatile() } == PAINT)
+}
+
+/// Whether [`check_guard`] looks at the guard, needs [`paint`] first.
+pub fn set_guard(enabled: bool) {
+    GUARD.0.set(enabled && guard_intact());
+}
+
+/// Panics with a stack overflow when the guard is on and was overwritten. `update_screen` calls
+/// this every frame.
+#[inline(always)]
+pub fn check_guard() {
+    if GUARD.0.get() {
+        check_guard_slow();
+    }
+}
+
+#[inline(never)]
+fn check_guard_slow() {
+    if !guard_intact() {
+        // only once, the panic handler may well update the screen
+        GUARD.0.set(false);
+        let stack = crate::mem::memory_map().stack;
+        panic!(
+            "stack overflow: the guard at {:08x}..{:08x} was overwritten, {}",
+            stack.start,
+            stack.start + GUARD_BYTES,
+            usage()
+        );
+    }
+}
+
+//----------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Usage {
+    pub size: usize,
+    /// In use right now.
+    pub current: usize,
+    /// The most that was ever in use, see [`high_water_mark`].
+    pub peak: usize,
+}
+
+/// Where the stack is at, scanning for the [`high_water_mark`] takes a while for a large stack.
+pub fn usage() -> Usage {
+    let stack = crate::mem::memory_map().stack;
+    Usage {
+        size: stack.end - stack.start,
+        current: stack.end.saturating_sub(stack_pointer()),
+        peak: high_water_mark(),
+    }
+}
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "stack: {} bytes in use, peak {} of {}",
+            self.current, self.peak, self.size
+        )
+    }
+}
@@ -0,0 +1,1026 @@
+#[cfg(target_arch = "mips")]
+use core::arch::asm;
+
+pub mod table;
+
+use table::syscalls;
+
+pub mod external_screen {
+
+    #[repr(C)]
+    pub struct ScreenData<
+        L1: LayerImpl = BitMapExpand,
+        L2: LayerImpl = Dissabled,
+        L3: LayerImpl = Dissabled,
+        L4: LayerImpl = Dissabled,
+    > {
+        pub layer1: LayerWrapper<L1>,
+        pub layer2: LayerWrapper<L2>,
+        pub layer3: LayerWrapper<L3>,
+        pub layer4: LayerWrapper<L4>,
+    }
+
+    impl<L1: LayerImpl, L2: LayerImpl, L3: LayerImpl, L4: LayerImpl> Default
+        for ScreenData<L1, L2, L3, L4>
+    {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<L1: LayerImpl, L2: LayerImpl, L3: LayerImpl, L4: LayerImpl> ScreenData<L1, L2, L3, L4> {
+        pub fn new() -> Self {
+            Self {
+                layer1: L1::create_wrapper(),
+                layer2: L2::create_wrapper(),
+                layer3: L3::create_wrapper(),
+                layer4: L4::create_wrapper(),
+            }
+        }
+    }
+
+    #[repr(C)]
+    pub struct LayerWrapper<L: LayerImpl> {
+        layer_id: u32,
+        pub layer_data: L,
+    }
+
+    pub trait LayerImpl {
+        fn new_layer() -> Self;
+        fn layer_id() -> u32;
+    }
+    trait LayerWrapperImpl<T: LayerImpl + Sized> {
+        fn create_wrapper() -> LayerWrapper<T>;
+    }
+    impl<T: LayerImpl + Sized> LayerWrapperImpl<T> for T {
+        fn create_wrapper() -> LayerWrapper<T> {
+            LayerWrapper {
+                layer_id: T::layer_id(),
+                layer_data: T::new_layer(),
+            }
+        }
+    }
+
+    //--------------------------------------------------------------------------------------------------------
+    pub struct Dissabled {}
+    impl LayerImpl for Dissabled {
+        fn new_layer() -> Self {
+            Self {}
+        }
+
+        fn layer_id() -> u32 {
+            0
+        }
+    }
+    //--------------------------------------------------------------------------------------------------------
+
+    //--------------------------------------------------------------------------------------------------------
+    #[repr(C)]
+    pub struct BitMapExpand {
+        pub size: [i16; 2],
+        pub ptr: u32,
+    }
+    impl LayerImpl for BitMapExpand {
+        fn new_layer() -> Self {
+            Self {
+                ptr: 0,
+                size: [0, 0],
+            }
+        }
+
+        fn layer_id() -> u32 {
+            1
+        }
+    }
+    //--------------------------------------------------------------------------------------------------------
+
+    //--------------------------------------------------------------------------------------------------------
+    #[repr(C)]
+    pub struct BitMapScroll {
+        pub bmp_ptr: u32,
+        pub bmp_size: [i16; 2],
+        pub visible_size: [i16; 2],
+        pub scroll: [i16; 2],
+    }
+    impl LayerImpl for BitMapScroll {
+        fn new_layer() -> Self {
+            Self {
+                bmp_ptr: 0,
+                bmp_size: [0, 0],
+                visible_size: [0, 0],
+                scroll: [0, 0],
+            }
+        }
+
+        fn layer_id() -> u32 {
+            2
+        }
+    }
+    //--------------------------------------------------------------------------------------------------------
+
+    //--------------------------------------------------------------------------------------------------------
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Tile {
+        pub index_rot: u32,
+        pub tint: [u8; 4],
+    }
+
+    #[repr(C)]
+    pub struct TileMap<const W: i16, const H: i16>
+    where
+        [(); W as usize * H as usize]:,
+    {
+        pub tile_map_ptr: u32,
+        pub visible_size: [i16; 2],
+        pub scroll: [i16; 2],
+        tile_map_size: [i16; 2],
+        pub tiles: [Tile; W as usize * H as usize],
+    }
+    impl<const W: i16, const H: i16> LayerImpl for TileMap<W, H>
+    where
+        [(); W as usize * H as usize]:,
+    {
+        fn new_layer() -> Self {
+            Self {
+                tile_map_ptr: 0,
+                tile_map_size: [W, H],
+                visible_size: [0, 0],
+                scroll: [0, 0],
+                tiles: [Tile {
+                    index_rot: 0,
+                    tint: [0, 0, 0, 0],
+                }; W as usize * H as usize],
+            }
+        }
+
+        fn layer_id() -> u32 {
+            3
+        }
+    }
+    //--------------------------------------------------------------------------------------------------------
+
+    //--------------------------------------------------------------------------------------------------------
+    #[repr(C)]
+    #[derive(Clone, Copy, Debug)]
+    pub struct Sprite {
+        pub sp_pos: [i16; 2],
+        pub screen_pos: [i16; 2],
+        pub tint: [u8; 4],
+        pub x_size: u16,
+        pub y_size: u16,
+        pub rot: i16,
+    }
+
+    #[repr(C)]
+    pub struct Sprites<const S: i16>
+    where
+        [(); S as usize]:,
+    {
+        pub sprite_sheet_ptr: u32,
+        pub sprite_sheet_size: [i16; 2],
+        pub resolition: [i16; 2],
+        pub scroll: [i16; 2],
+        num_sprites: i16,
+        sprites: [Sprite; S as usize],
+    }
+    impl<const S: i16> LayerImpl for Sprites<S>
+    where
+        [(); S as usize]:,
+    {
+        fn new_layer() -> Self {
+            Self {
+                sprite_sheet_ptr: 0,
+                sprite_sheet_size: [0, 0],
+                resolition: [0, 0],
+                scroll: [0, 0],
+                num_sprites: S,
+                sprites: [Sprite {
+                    sp_pos: [0, 0],
+                    screen_pos: [0, 0],
+                    tint: [0, 0, 0, 0],
+                    x_size: 0,
+                    y_size: 0,
+                    rot: 0,
+                }; S as usize],
+            }
+        }
+
+        fn layer_id() -> u32 {
+            4
+        }
+    }
+    //--------------------------------------------------------------------------------------------------------
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Why a syscall failed.
+///
+/// Calls that can fail return a status word: non negative is success (and may carry a small
+/// result like a byte count or handle), negative is one of these codes. It goes in `$2`, except
+/// for `read_i32` and `rand_range` which always returned their value in `$2` and now put the
+/// status in `$3`. Everything else is fire and forget.
+///
+/// This is a host visible ABI change: `init_screen`, the pixel, `update_screen*` and
+/// `fill_screen` calls used to return nothing and now return a status in `$2`, `read_i32` and
+/// `rand_range` a status in `$3`. The guest zeroes `$2` and `$3` before every call that reads
+/// them back, so a host that doesn't know about statuses and leaves them alone reads as success.
+///
+/// The safe wrappers below and [`raw`] turn the status into a [`SysResult`], the raw
+/// `syscall_{in}_{out}` helpers hand back the registers untouched.
+#[repr(i32)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum SysError {
+    /// An argument is out of its valid range (`rand_range` with `min > max`, zero sized
+    /// screens, unknown audio channels..).
+    InvalidArgument = -1,
+    /// A pixel index or coordinate is outside of the screen.
+    OutOfBounds = -2,
+    /// The call needs something set up first, like drawing before `init_screen`.
+    NotInitialized = -3,
+    /// The host doesn't implement this call.
+    Unsupported = -4,
+    NotFound = -5,
+    PermissionDenied = -6,
+    AlreadyExists = -7,
+    /// The path is malformed or tries to leave the file system sandbox.
+    InvalidPath = -8,
+    /// The handle was never opened, already closed, or is the wrong kind for the call.
+    BadHandle = -9,
+    IsADirectory = -10,
+    NotADirectory = -11,
+    TooManyOpenFiles = -12,
+    /// The file is larger than the 32 bit offsets used by the syscalls can address.
+    TooLarge = -13,
+    /// A read or write moved fewer bytes than had to be moved.
+    UnexpectedEof = -14,
+    /// Input from the host couldn't be used, like `read_i32` on something that isn't a number.
+    InvalidData = -15,
+    Other = -16,
+}
+
+pub type SysResult<T> = Result<T, SysError>;
+
+impl SysError {
+    pub const ALL: [SysError; 16] = [
+        SysError::InvalidArgument,
+        SysError::OutOfBounds,
+        SysError::NotInitialized,
+        SysError::Unsupported,
+        SysError::NotFound,
+        SysError::PermissionDenied,
+        SysError::AlreadyExists,
+        SysError::InvalidPath,
+        SysError::BadHandle,
+        SysError::IsADirectory,
+        SysError::NotADirectory,
+        SysError::TooManyOpenFiles,
+        SysError::TooLarge,
+        SysError::UnexpectedEof,
+        SysError::InvalidData,
+        SysError::Other,
+    ];
+
+    #[inline(always)]
+    pub const fn code(self) -> i32 {
+        self as i32
+    }
+
+    /// Unknown negative codes map to [`SysError::Other`].
+    pub fn from_code(code: i32) -> Self {
+        Self::ALL
+            .into_iter()
+            .find(|err| err.code() == code)
+            .unwrap_or(SysError::Other)
+    }
+
+    /// Splits a status word into the non negative value and the error.
+    #[inline(always)]
+    pub fn check(status: u32) -> SysResult<u32> {
+        if (status as i32) < 0 {
+            Err(Self::from_code(status as i32))
+        } else {
+            Ok(status)
+        }
+    }
+
+    /// Status word for a result, what a host writes back into `$2`.
+    #[inline(always)]
+    pub fn status(result: SysResult<u32>) -> u32 {
+        match result {
+            Ok(value) => value.min(i32::MAX as u32),
+            Err(err) => err.code() as u32,
+        }
+    }
+}
+
+impl core::fmt::Display for SysError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(match self {
+            SysError::InvalidArgument => "invalid argument",
+            SysError::OutOfBounds => "out of bounds",
+            SysError::NotInitialized => "not initialized",
+            SysError::Unsupported => "unsupported syscall",
+            SysError::NotFound => "no such file or directory",
+            SysError::PermissionDenied => "permission denied",
+            SysError::AlreadyExists => "file already exists",
+            SysError::InvalidPath => "invalid path",
+            SysError::BadHandle => "bad handle",
+            SysError::IsADirectory => "is a directory",
+            SysError::NotADirectory => "not a directory",
+            SysError::TooManyOpenFiles => "too many open files",
+            SysError::TooLarge => "file too large",
+            SysError::UnexpectedEof => "unexpected end of file",
+            SysError::InvalidData => "invalid data",
+            SysError::Other => "host error",
+        })
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+// Every syscall, the guest side `raw` functions, the host side `SyscallHandler` and the
+// `SYSCALLS` reference are all generated from this, see `table` for what each part does.
+syscalls! {
+    /// Stops the program, never returns.
+    0 => halt();
+    /// Prints `num` in decimal to the console.
+    1 => print_i32(num: i32);
+    /// Prints the zero terminated string at `str_ptr` to the console.
+    4 => print_zero_term_str(str_ptr: u32);
+    /// Reads a number from the console, [`SysError::InvalidData`] if what was typed isn't one.
+    5 => read_i32() -> SysResult<i32>;
+    /// Random number in `min..=max`, [`SysError::InvalidArgument`] when `min > max`.
+    99 => rand_range(min: i32, max: i32) -> SysResult<i32>;
+    /// Prints a unicode scalar value to the console.
+    101 => print_char(char: u32);
+    /// Whether the key for the character `key` is held down.
+    104 => is_key_pressed(key: u32) -> bool;
+    /// Sleeps for `mills` milliseconds.
+    105 => sleep_mills(mills: u32);
+    /// Sleeps until `mills` milliseconds after the last time this returned, for frame pacing.
+    106 => sleep_delta_mills(mills: u32);
+    /// Microseconds since the program started.
+    108 => get_micros() -> u64;
+    /// Nanoseconds since the program started.
+    109 => get_nanos() -> u64;
+    /// Pauses the emulator like a breakpoint would.
+    111 => old_breakpoint();
+    /// [`SysError::InvalidArgument`] for a zero sized screen or one larger than the host allows.
+    150 => init_screen(width: u32, height: u32) -> SysResult<()>;
+    /// [`SysError::OutOfBounds`] off the screen, [`SysError::NotInitialized`] before
+    /// `init_screen`.
+    151 => set_pixel_coords(x: u32, y: u32, color: u32) -> SysResult<()>;
+    /// [`SysError::OutOfBounds`] off the screen, [`SysError::NotInitialized`] before
+    /// `init_screen`.
+    152 => set_pixel_index(index: u32, color: u32) -> SysResult<()>;
+    /// Presents the screen, [`SysError::NotInitialized`] before `init_screen`.
+    153 => update_screen() -> SysResult<()>;
+    /// Presents the screen on the next vsync, [`SysError::NotInitialized`] before
+    /// `init_screen`.
+    154 => update_screen_vsync() -> SysResult<()>;
+    /// Converts a packed hsv color to rgb.
+    155 => hsv_to_rgb(hsv: u32) -> u32;
+    /// [`SysError::NotInitialized`] before `init_screen`.
+    156 => fill_screen(color: u32) -> SysResult<()>;
+    /// Pops the next keyboard event from the host queue. Returns `0` when the queue is empty,
+    /// otherwise `kind << 24 | modifiers << 16 | key_code`, see [`crate::input::KeyEvent`].
+    160 => poll_key_event() -> u32;
+    /// Pops the next typed character from the host text input stream. Returns `0` when the
+    /// queue is empty, like `poll_key_event`, otherwise the character as a `u32`.
+    /// This is separate from the key events so layouts, shift and dead keys are already
+    /// resolved by the host.
+    161 => poll_text_input() -> u32;
+    /// Cursor position in screen pixels as set up by `init_screen` as two `i32`s, the host does
+    /// the window to framebuffer scaling. Values can be negative or past the screen size while
+    /// the cursor is outside of it.
+    162 => mouse_position() -> (u32, u32);
+    /// Bitmask of held buttons, bit 31 is set while the cursor is over the screen.
+    163 => mouse_buttons() -> u32;
+    /// Wheel notches scrolled since the last call as two `i32`s `horizontal, vertical`,
+    /// positive is right/down.
+    164 => mouse_wheel() -> (u32, u32);
+    /// Fills the [`crate::input::gamepad::RawGamepadState`] at `state_ptr` with controller
+    /// `index`, returns `false` (and leaves it alone) when no controller is connected there.
+    166 => gamepad_state(index: u32, state_ptr: u32) -> bool;
+    /// Sets the registers of a channel from the [`crate::audio::ChannelRegs`] at `regs_ptr`,
+    /// [`SysError::InvalidArgument`] for channels past [`crate::audio::NUM_CHANNELS`].
+    170 => audio_write_channel(channel: u32, regs_ptr: u32) -> SysResult<()>;
+    /// Copies the 32 byte wave table at `table_ptr`.
+    171 => audio_set_wave(table_ptr: u32);
+    172 => audio_set_master_volume(volume: u32);
+    /// [`SysError::InvalidArgument`] for a zero sample rate or anything but 1 or 2 channels.
+    173 => audio_pcm_configure(sample_rate: u32, channels: u32) -> SysResult<()>;
+    /// Queues `len` `i16` samples from `samples_ptr`, returns how many the host ring buffer
+    /// accepted.
+    174 => audio_pcm_write(samples_ptr: u32, len: u32) -> u32;
+    /// Samples the host ring buffer has room for.
+    175 => audio_pcm_available() -> u32;
+    /// Opens a path inside the host's sandbox directory with [`crate::fs::OpenOptions`] flags
+    /// and returns a handle.
+    180 => fs_open(path_ptr: u32, path_len: u32, flags: u32) -> SysResult<u32>;
+    181 => fs_close(handle: u32) -> SysResult<()>;
+    /// Returns the number of bytes read, `0` at the end of the file.
+    182 => fs_read(handle: u32, buf_ptr: u32, len: u32) -> SysResult<u32>;
+    /// Returns the number of bytes written.
+    183 => fs_write(handle: u32, buf_ptr: u32, len: u32) -> SysResult<u32>;
+    /// `whence` is `0` from the start (`offset` is unsigned), `1` from the current position
+    /// and `2` from the end. Returns the new position.
+    184 => fs_seek(handle: u32, offset: i32, whence: u32) -> SysResult<u32>;
+    /// Fills the [`crate::fs::RawStat`] at `stat_ptr` for a path.
+    185 => fs_stat(path_ptr: u32, path_len: u32, stat_ptr: u32) -> SysResult<()>;
+    /// Fills the [`crate::fs::RawDirEntry`] at `entry_ptr` with the next entry of a directory
+    /// handle. Returns `true` when an entry was written, `false` once the directory is
+    /// exhausted.
+    186 => fs_read_dir(handle: u32, entry_ptr: u32) -> SysResult<bool>;
+    /// Hands a [`crate::binlog`] frame of `len` bytes at `frame_ptr` to the host, which stores
+    /// or decodes it against the `.binlog` section of the running ELF.
+    190 => binlog_write(frame_ptr: u32, len: u32);
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[inline(always)]
+pub fn halt() -> ! {
+    unsafe {
+        raw::halt();
+    }
+
+    unsafe {
+        core::hint::unreachable_unchecked();
+    }
+}
+
+#[inline(always)]
+pub fn print_i32(num: i32) {
+    unsafe { raw::print_i32(num) }
+}
+
+#[inline(always)]
+pub fn print_zero_term_str(str: &str) {
+    unsafe { raw::print_zero_term_str(str.as_ptr().addr() as u32) }
+}
+
+#[inline(always)]
+pub fn print_str(str: &str) {
+    for char in str.chars() {
+        print_char(char);
+    }
+}
+
+/// Reads a number from the console, [`SysError::InvalidData`] if what was typed isn't one.
+#[inline(always)]
+pub fn read_i32() -> SysResult<i32> {
+    unsafe { raw::read_i32() }
+}
+
+/// Random number in `min..=max`, [`SysError::InvalidArgument`] when `min > max`.
+pub fn rand_range(min: i32, max: i32) -> SysResult<i32> {
+    unsafe { raw::rand_range(min, max) }
+}
+
+#[inline(always)]
+pub fn print_char(char: char) {
+    unsafe { raw::print_char(char as u32) }
+}
+
+pub fn is_key_pressed(key: char) -> bool {
+    unsafe { raw::is_key_pressed(key as u32) }
+}
+
+/// Pops the next keyboard event from the host queue. Returns `0` when the queue is empty,
+/// otherwise `kind << 24 | modifiers << 16 | key_code`, see [`crate::input::KeyEvent`].
+#[inline(always)]
+pub fn poll_key_event() -> u32 {
+    unsafe { raw::poll_key_event() }
+}
+
+/// Pops the next typed character from the host text input stream, this is separate from the
+/// key events so layouts, shift and dead keys are already resolved by the host. `None` when the
+/// queue is empty, which the host says with `0`.
+#[inline(always)]
+pub fn poll_text_input() -> Option<char> {
+    match unsafe { raw::poll_text_input() } {
+        0 => None,
+        // not a char either, don't let a confused host keep `Keyboard::update` spinning
+        raw => char::from_u32(raw),
+    }
+}
+
+/// Cursor position in screen pixels as set up by [`init_screen`], the host does the window to
+/// framebuffer scaling. Values can be negative or past the screen size while the cursor is
+/// outside of it.
+#[inline(always)]
+pub fn mouse_position() -> [i32; 2] {
+    let (x, y) = unsafe { raw::mouse_position() };
+    [x as i32, y as i32]
+}
+
+/// Bitmask of held buttons, bit 31 is set while the cursor is over the screen.
+#[inline(always)]
+pub fn mouse_buttons() -> u32 {
+    unsafe { raw::mouse_buttons() }
+}
+
+/// Wheel notches scrolled since the last call as `[horizontal, vertical]`, positive is
+/// right/down.
+#[inline(always)]
+pub fn mouse_wheel() -> [i32; 2] {
+    let (x, y) = unsafe { raw::mouse_wheel() };
+    [x as i32, y as i32]
+}
+
+/// Fills `state` with controller `index`, returns `false` (and leaves `state` alone) when no
+/// controller is connected at that index.
+#[inline(always)]
+pub fn gamepad_state(index: u32, state: &mut crate::input::gamepad::RawGamepadState) -> bool {
+    unsafe { raw::gamepad_state(index, (state as *mut _ as *mut u8).addr() as u32) }
+}
+
+/// [`SysError::InvalidArgument`] for channels past [`crate::audio::NUM_CHANNELS`].
+#[inline(always)]
+pub fn audio_write_channel(channel: u32, regs: &crate::audio::ChannelRegs) -> SysResult<()> {
+    unsafe { raw::audio_write_channel(channel, (regs as *const _ as *const u8).addr() as u32) }
+}
+
+#[inline(always)]
+pub fn audio_set_wave(table: &[u8; 32]) {
+    unsafe { raw::audio_set_wave(table.as_ptr().addr() as u32) }
+}
+
+#[inline(always)]
+pub fn audio_set_master_volume(volume: u32) {
+    unsafe { raw::audio_set_master_volume(volume) }
+}
+
+/// [`SysError::InvalidArgument`] for a zero sample rate or anything but 1 or 2 channels.
+#[inline(always)]
+pub fn audio_pcm_configure(sample_rate: u32, channels: u32) -> SysResult<()> {
+    unsafe { raw::audio_pcm_configure(sample_rate, channels) }
+}
+
+/// Returns the number of samples the host ring buffer accepted.
+#[inline(always)]
+pub fn audio_pcm_write(samples: &[i16]) -> u32 {
+    unsafe { raw::audio_pcm_write(samples.as_ptr().addr() as u32, samples.len() as u32) }
+}
+
+#[inline(always)]
+pub fn audio_pcm_available() -> u32 {
+    unsafe { raw::audio_pcm_available() }
+}
+
+/// Opens `path` inside the host's sandbox directory with [`crate::fs::OpenOptions`] flags and
+/// returns a handle.
+#[inline(always)]
+pub fn fs_open(path: &str, flags: u32) -> SysResult<u32> {
+    unsafe { raw::fs_open(path.as_ptr().addr() as u32, path.len() as u32, flags) }
+}
+
+#[inline(always)]
+pub fn fs_close(handle: u32) -> SysResult<()> {
+    unsafe { raw::fs_close(handle) }
+}
+
+/// Returns the number of bytes read, `0` at the end of the file.
+#[inline(always)]
+pub fn fs_read(handle: u32, buf: &mut [u8]) -> SysResult<u32> {
+    unsafe { raw::fs_read(handle, buf.as_mut_ptr().addr() as u32, buf.len() as u32) }
+}
+
+/// Returns the number of bytes written.
+#[inline(always)]
+pub fn fs_write(handle: u32, buf: &[u8]) -> SysResult<u32> {
+    unsafe { raw::fs_write(handle, buf.as_ptr().addr() as u32, buf.len() as u32) }
+}
+
+/// `whence` is `0` from the start (`offset` is unsigned), `1` from the current position and `2`
+/// from the end. Returns the new position.
+#[inline(always)]
+pub fn fs_seek(handle: u32, offset: i32, whence: u32) -> SysResult<u32> {
+    unsafe { raw::fs_seek(handle, offset, whence) }
+}
+
+/// Fills `stat` for `path`.
+#[inline(always)]
+pub fn fs_stat(path: &str, stat: &mut crate::fs::RawStat) -> SysResult<()> {
+    unsafe {
+        raw::fs_stat(
+            path.as_ptr().addr() as u32,
+            path.len() as u32,
+            (stat as *mut _ as *mut u8).addr() as u32,
+        )
+    }
+}
+
+/// Fills `entry` with the next entry of a directory handle. Returns `true` when an entry was
+/// written, `false` once the directory is exhausted.
+#[inline(always)]
+pub fn fs_read_dir(handle: u32, entry: &mut crate::fs::RawDirEntry) -> SysResult<bool> {
+    unsafe { raw::fs_read_dir(handle, (entry as *mut _ as *mut u8).addr() as u32) }
+}
+
+#[inline(always)]
+pub fn binlog_write(frame: &[u8]) {
+    unsafe { raw::binlog_write(frame.as_ptr().addr() as u32, frame.len() as u32) }
+}
+
+#[inline(always)]
+pub fn sleep_delta_mills(mills: u32) {
+    unsafe { raw::sleep_delta_mills(mills) }
+}
+
+#[inline(always)]
+pub fn sleep_mills(mills: u32) {
+    unsafe { raw::sleep_mills(mills) }
+}
+
+#[inline(always)]
+pub fn get_micros() -> u64 {
+    unsafe { raw::get_micros() }
+}
+#[inline(always)]
+pub fn get_nanos() -> u64 {
+    unsafe { raw::get_nanos() }
+}
+
+#[inline(always)]
+pub fn old_breakpoint() {
+    unsafe { raw::old_breakpoint() }
+}
+
+/// [`SysError::InvalidArgument`] for a zero sized screen or one larger than the host allows.
+#[inline(always)]
+pub fn init_screen(width: u32, height: u32) -> SysResult<()> {
+    unsafe { raw::init_screen(width, height) }
+}
+
+/// [`SysError::OutOfBounds`] off the screen, [`SysError::NotInitialized`] before
+/// [`init_screen`].
+#[inline(always)]
+pub fn set_pixel_coords(x: u32, y: u32, color: u32) -> SysResult<()> {
+    unsafe { raw::set_pixel_coords(x, y, color) }
+}
+
+/// [`SysError::OutOfBounds`] off the screen, [`SysError::NotInitialized`] before
+/// [`init_screen`].
+#[inline(always)]
+pub fn set_pixel_index(index: u32, color: u32) -> SysResult<()> {
+    unsafe { raw::set_pixel_index(index, color) }
+}
+
+/// [`SysError::NotInitialized`] before [`init_screen`].
+#[inline(always)]
+pub fn update_screen() -> SysResult<()> {
+    crate::stack::check_guard();
+    unsafe { raw::update_screen() }
+}
+
+/// [`SysError::NotInitialized`] before [`init_screen`].
+#[inline(always)]
+pub fn update_screen_vsync() -> SysResult<()> {
+    crate::stack::check_guard();
+    unsafe { raw::update_screen_vsync() }
+}
+
+#[inline(always)]
+pub fn hsv_to_rgb(hsv: u32) -> u32 {
+    unsafe { raw::hsv_to_rgb(hsv) }
+}
+
+/// [`SysError::NotInitialized`] before [`init_screen`].
+#[inline(always)]
+pub fn fill_screen(color: u32) -> SysResult<()> {
+    unsafe { raw::fill_screen(color) }
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_0_0<const CALL_ID: u32>() {
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+    )
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_1_0<const CALL_ID: u32>(arg1: u32) {
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+    )
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_0_1<const CALL_ID: u32>() -> u32 {
+    let ret1;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        out("$2") ret1,
+    );
+    ret1
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_1_1<const CALL_ID: u32>(arg1: u32) -> u32 {
+    let ret1;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        out("$2") ret1,
+    );
+    ret1
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_2_0<const CALL_ID: u32>(arg1: u32, arg2: u32) {
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+    );
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_3_0<const CALL_ID: u32>(arg1: u32, arg2: u32, arg3: u32) {
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+    );
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_2_1<const CALL_ID: u32>(arg1: u32, arg2: u32) -> u32 {
+    let ret1;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        out("$2") ret1,
+    );
+    ret1
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_3_1<const CALL_ID: u32>(arg1: u32, arg2: u32, arg3: u32) -> u32 {
+    let ret1;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        out("$2") ret1,
+    );
+    ret1
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_2_2<const CALL_ID: u32>(arg1: u32, arg2: u32) -> (u32, u32) {
+    let ret1: u32;
+    let ret2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        inout("$2") 0u32 => ret1,
+        inout("$3") 0u32 => ret2,
+    );
+    (ret1, ret2)
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_1_2<const CALL_ID: u32>(arg1: u32) -> (u32, u32) {
+    let ret1: u32;
+    let ret2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        inout("$2") 0u32 => ret1,
+        inout("$3") 0u32 => ret2,
+    );
+    (ret1, ret2)
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_3_2<const CALL_ID: u32>(arg1: u32, arg2: u32, arg3: u32) -> (u32, u32) {
+    let ret1: u32;
+    let ret2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        inout("$2") 0u32 => ret1,
+        inout("$3") 0u32 => ret2,
+    );
+    (ret1, ret2)
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_4_2<const CALL_ID: u32>(
+    arg1: u32,
+    arg2: u32,
+    arg3: u32,
+    arg4: u32,
+) -> (u32, u32) {
+    let ret1: u32;
+    let ret2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        in("$4") arg1,
+        in("$5") arg2,
+        in("$6") arg3,
+        in("$7") arg4,
+        inout("$2") 0u32 => ret1,
+        inout("$3") 0u32 => ret2,
+    );
+    (ret1, ret2)
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_0_2<const CALL_ID: u32>() -> (u32, u32) {
+    let ret1: u32;
+    let ret2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        inout("$2") 0u32 => ret1,
+        inout("$3") 0u32 => ret2,
+    );
+    (ret1, ret2)
+}
+
+/// # Safety
+/// 
+/// If you have to read this then you shouldnt be using this. This is a raw System Call, using it 
+/// incorrectly can break pretty much anything. 
+#[inline(always)]
+#[cfg(target_arch = "mips")]
+pub unsafe fn syscall_0_2_s<const CALL_ID: u32>() -> u64 {
+    let tmp1: u32;
+    let tmp2: u32;
+    asm!(
+        "syscall {0}",
+        const(CALL_ID),
+        out("$2") tmp1,
+        out("$3") tmp2,
+    );
+    (tmp1 as u64) | ((tmp2 as u64) << 32)
+}
+
+/// Off the mips target there is no `syscall` instruction. These stand-ins keep the crate
+/// building for host side tools, calling one is a bug and panics.
+#[cfg(not(target_arch = "mips"))]
+pub use off_target::*;
+
+#[cfg(not(target_arch = "mips"))]
+#[allow(clippy::missing_safety_doc)]
+mod off_target {
+    #[cold]
+    fn unavailable(call_id: u32) -> ! {
+        panic!("syscall {} is only available on the mips target", call_id)
+    }
+
+    pub unsafe fn syscall_0_0<const CALL_ID: u32>() {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_1_0<const CALL_ID: u32>(_arg1: u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_0_1<const CALL_ID: u32>() -> u32 {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_1_1<const CALL_ID: u32>(_arg1: u32) -> u32 {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_2_0<const CALL_ID: u32>(_arg1: u32, _arg2: u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_3_0<const CALL_ID: u32>(_arg1: u32, _arg2: u32, _arg3: u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_2_1<const CALL_ID: u32>(_arg1: u32, _arg2: u32) -> u32 {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_3_1<const CALL_ID: u32>(_arg1: u32, _arg2: u32, _arg3: u32) -> u32 {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_2_2<const CALL_ID: u32>(_arg1: u32, _arg2: u32) -> (u32, u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_1_2<const CALL_ID: u32>(_arg1: u32) -> (u32, u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_3_2<const CALL_ID: u32>(
+        _arg1: u32,
+        _arg2: u32,
+        _arg3: u32,
+    ) -> (u32, u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_4_2<const CALL_ID: u32>(
+        _arg1: u32,
+        _arg2: u32,
+        _arg3: u32,
+        _arg4: u32,
+    ) -> (u32, u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_0_2<const CALL_ID: u32>() -> (u32, u32) {
+        unavailable(CALL_ID)
+    }
+
+    pub unsafe fn syscall_0_2_s<const CALL_ID: u32>() -> u64 {
+        unavailable(CALL_ID)
+    }
+}
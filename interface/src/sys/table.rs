@@ -0,0 +1,485 @@
+//! The machinery behind the syscall table in [`crate::sys`].
+//!
+//! Every syscall is declared once with [`syscalls!`] as its id, name, register arguments and
+//! return type. From that one declaration the macro generates
+//!
+//! * `sys::id`, the call ids as constants,
+//! * `sys::raw`, one unsafe function per call that puts the arguments in `$4..$7`, zeroes
+//!   `$2`/`$3`, issues the `syscall` and turns them back into the return type,
+//! * `sys::SyscallHandler`, a trait with a method per call and a `dispatch` that decodes a
+//!   register file, for emulators and mocks,
+//! * `sys::SYSCALLS`, a description of every call that [`write_markdown`] and [`write_json`]
+//!   render into a reference.
+//!
+//! Arguments are `u32` or `i32`, one register each, at most four of them. How a return type
+//! maps to `$2` and `$3` is up to its [`SysRet`] impl.
+
+use core::fmt;
+
+use super::{SysError, SysResult};
+
+/// A return type a syscall can be declared with.
+pub trait SysRet: Sized {
+    /// Registers the value lives in, for the reference.
+    const REGS: &'static str;
+
+    /// Guest side, from the `$2` and `$3` the host left behind.
+    fn from_regs(v0: u32, v1: u32) -> Self;
+
+    /// Host side, what to write into `$2` and `$3`, `None` leaves the register alone.
+    fn into_regs(self) -> [Option<u32>; 2];
+
+    /// What a [`SyscallHandler`](super::SyscallHandler) returns for calls it doesn't implement.
+    fn unsupported() -> Self;
+}
+
+impl SysRet for () {
+    const REGS: &'static str = "";
+
+    #[inline(always)]
+    fn from_regs(_: u32, _: u32) -> Self {}
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [None, None]
+    }
+
+    fn unsupported() -> Self {}
+}
+
+impl SysRet for u32 {
+    const REGS: &'static str = "$2";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, _: u32) -> Self {
+        v0
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(self), None]
+    }
+
+    fn unsupported() -> Self {
+        0
+    }
+}
+
+impl SysRet for bool {
+    const REGS: &'static str = "$2 (0 or 1)";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, _: u32) -> Self {
+        v0 != 0
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(self as u32), None]
+    }
+
+    fn unsupported() -> Self {
+        false
+    }
+}
+
+impl SysRet for (u32, u32) {
+    const REGS: &'static str = "$2, $3";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, v1: u32) -> Self {
+        (v0, v1)
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(self.0), Some(self.1)]
+    }
+
+    fn unsupported() -> Self {
+        (0, 0)
+    }
+}
+
+impl SysRet for u64 {
+    const REGS: &'static str = "$2 low, $3 high";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, v1: u32) -> Self {
+        (v0 as u64) | ((v1 as u64) << 32)
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(self as u32), Some((self >> 32) as u32)]
+    }
+
+    fn unsupported() -> Self {
+        0
+    }
+}
+
+impl SysRet for SysResult<()> {
+    const REGS: &'static str = "$2 status";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, _: u32) -> Self {
+        SysError::check(v0).map(|_| ())
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(SysError::status(self.map(|_| 0))), None]
+    }
+
+    fn unsupported() -> Self {
+        Err(SysError::Unsupported)
+    }
+}
+
+/// The value shares `$2` with the status so it has to stay below `i32::MAX`.
+impl SysRet for SysResult<u32> {
+    const REGS: &'static str = "$2 status or value";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, _: u32) -> Self {
+        SysError::check(v0)
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(SysError::status(self)), None]
+    }
+
+    fn unsupported() -> Self {
+        Err(SysError::Unsupported)
+    }
+}
+
+impl SysRet for SysResult<bool> {
+    const REGS: &'static str = "$2 status or 0/1";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, _: u32) -> Self {
+        SysError::check(v0).map(|value| value != 0)
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        [Some(SysError::status(self.map(|value| value as u32))), None]
+    }
+
+    fn unsupported() -> Self {
+        Err(SysError::Unsupported)
+    }
+}
+
+/// A signed value can't share a register with the status. It stays in `$2` where `read_i32` and
+/// `rand_range` always had it and the status goes in `$3`, which the guest zeroes before the call
+/// so hosts that predate statuses read as success.
+impl SysRet for SysResult<i32> {
+    const REGS: &'static str = "$2 value, $3 status";
+
+    #[inline(always)]
+    fn from_regs(v0: u32, v1: u32) -> Self {
+        SysError::check(v1).map(|_| v0 as i32)
+    }
+
+    fn into_regs(self) -> [Option<u32>; 2] {
+        match self {
+            Ok(value) => [Some(value as u32), Some(0)],
+            Err(err) => [Some(0), Some(err.code() as u32)],
+        }
+    }
+
+    fn unsupported() -> Self {
+        Err(SysError::Unsupported)
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+#[derive(Copy, Clone, Debug)]
+pub struct ArgInfo {
+    pub name: &'static str,
+    pub ty: &'static str,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct SyscallInfo {
+    pub id: u32,
+    pub name: &'static str,
+    /// In register order starting at `$4`.
+    pub args: &'static [ArgInfo],
+    pub ret: &'static str,
+    /// See [`SysRet::REGS`], empty when nothing is returned.
+    pub ret_regs: &'static str,
+    /// The doc comment lines of the declaration.
+    pub docs: &'static [&'static str],
+}
+
+impl SyscallInfo {
+    /// Doc comment lines without the space `///` leaves in front of them.
+    pub fn doc_lines(&self) -> impl Iterator<Item = &'static str> {
+        self.docs
+            .iter()
+            .map(|line| line.strip_prefix(' ').unwrap_or(line))
+    }
+}
+
+/// Reads the argument registers of a call in order, used by the generated `dispatch`.
+pub struct ArgRegs<'a> {
+    regs: &'a [u32; 32],
+    next: usize,
+}
+
+impl<'a> ArgRegs<'a> {
+    #[inline(always)]
+    pub fn new(regs: &'a [u32; 32]) -> Self {
+        Self { regs, next: 4 }
+    }
+
+    #[inline(always)]
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(&mut self) -> u32 {
+        self.next += 1;
+        self.regs[self.next - 1]
+    }
+}
+
+/// Writes what a handler returned into `$2` and `$3`.
+#[inline(always)]
+pub fn write_ret<R: SysRet>(regs: &mut [u32; 32], ret: R) {
+    let [v0, v1] = ret.into_regs();
+    if let Some(v0) = v0 {
+        regs[2] = v0;
+    }
+    if let Some(v1) = v1 {
+        regs[3] = v1;
+    }
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Renders `calls` as a Markdown reference, a summary table followed by a section per call.
+pub fn write_markdown(out: &mut impl fmt::Write, calls: &[SyscallInfo]) -> fmt::Result {
+    writeln!(out, "# Syscalls")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "Arguments go in `$4..$7` in order, the call id is the `syscall` instruction's code."
+    )?;
+    writeln!(
+        out,
+        "A negative status is a `SysError` code, see `interface::sys::SysError`."
+    )?;
+    writeln!(
+        out,
+        "`$2` and `$3` are zeroed before the call, what the host leaves alone reads as `0`."
+    )?;
+    writeln!(out)?;
+    writeln!(out, "| id | name | arguments | returns | registers |")?;
+    writeln!(out, "|---:|------|-----------|---------|-----------|")?;
+    for call in calls {
+        write!(out, "| {} | [`{}`](#{}) | ", call.id, call.name, call.name)?;
+        write_args(out, call)?;
+        writeln!(out, " | `{}` | {} |", call.ret, call.ret_regs)?;
+    }
+    for call in calls {
+        writeln!(out)?;
+        writeln!(out, "## {}", call.name)?;
+        writeln!(out)?;
+        write!(out, "`syscall {}` `{}(", call.id, call.name)?;
+        write_args(out, call)?;
+        writeln!(out, ") -> {}`", call.ret)?;
+        writeln!(out)?;
+        for line in call.doc_lines() {
+            writeln!(out, "{}", line)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_args(out: &mut impl fmt::Write, call: &SyscallInfo) -> fmt::Result {
+    for (i, arg) in call.args.iter().enumerate() {
+        if i != 0 {
+            write!(out, ", ")?;
+        }
+        write!(out, "{}: {} (${})", arg.name, arg.ty, i + 4)?;
+    }
+    Ok(())
+}
+
+/// Renders `calls` as a JSON array, one object per call.
+pub fn write_json(out: &mut impl fmt::Write, calls: &[SyscallInfo]) -> fmt::Result {
+    writeln!(out, "[")?;
+    for (i, call) in calls.iter().enumerate() {
+        write!(out, "  {{\"id\": {}, \"name\": ", call.id)?;
+        write_json_str(out, call.name)?;
+        write!(out, ", \"args\": [")?;
+        for (i, arg) in call.args.iter().enumerate() {
+            if i != 0 {
+                write!(out, ", ")?;
+            }
+            write!(out, "{{\"name\": ")?;
+            write_json_str(out, arg.name)?;
+            write!(out, ", \"type\": ")?;
+            write_json_str(out, arg.ty)?;
+            write!(out, ", \"register\": {}}}", i + 4)?;
+        }
+        write!(out, "], \"returns\": ")?;
+        write_json_str(out, call.ret)?;
+        write!(out, ", \"registers\": ")?;
+        write_json_str(out, call.ret_regs)?;
+        write!(out, ", \"doc\": \"")?;
+        for (i, line) in call.doc_lines().enumerate() {
+            if i != 0 {
+                write!(out, "\\n")?;
+            }
+            write_json_escaped(out, line)?;
+        }
+        writeln!(out, "\"}}{}", if i + 1 == calls.len() { "" } else { "," })?;
+    }
+    writeln!(out, "]")
+}
+
+fn write_json_str(out: &mut impl fmt::Write, str: &str) -> fmt::Result {
+    out.write_char('"')?;
+    write_json_escaped(out, str)?;
+    out.write_char('"')
+}
+
+fn write_json_escaped(out: &mut impl fmt::Write, str: &str) -> fmt::Result {
+    for char in str.chars() {
+        match char {
+            '"' => out.write_str("\\\"")?,
+            '\\' => out.write_str("\\\\")?,
+            '\n' => out.write_str("\\n")?,
+            char if (char as u32) < 0x20 => write!(out, "\\u{:04x}", char as u32)?,
+            char => out.write_char(char)?,
+        }
+    }
+    Ok(())
+}
+
+//--------------------------------------------------------------------------------------------------------
+
+/// Picks the raw helper for the number of arguments, more than four don't fit in `$4..$7`. They
+/// zero `$2` and `$3` before the call, a host that returns nothing leaves a success status.
+macro_rules! __syscall_regs {
+    ($id:literal;) => {
+        $crate::sys::syscall_0_2::<$id>()
+    };
+    ($id:literal; $a1:expr) => {
+        $crate::sys::syscall_1_2::<$id>($a1)
+    };
+    ($id:literal; $a1:expr, $a2:expr) => {
+        $crate::sys::syscall_2_2::<$id>($a1, $a2)
+    };
+    ($id:literal; $a1:expr, $a2:expr, $a3:expr) => {
+        $crate::sys::syscall_3_2::<$id>($a1, $a2, $a3)
+    };
+    ($id:literal; $a1:expr, $a2:expr, $a3:expr, $a4:expr) => {
+        $crate::sys::syscall_4_2::<$id>($a1, $a2, $a3, $a4)
+    };
+}
+
+macro_rules! __syscall_ret {
+    () => {
+        ()
+    };
+    ($ret:ty) => {
+        $ret
+    };
+}
+
+macro_rules! __syscall_ret_name {
+    () => {
+        "()"
+    };
+    ($ret:ty) => {
+        stringify!($ret)
+    };
+}
+
+/// Declares the syscall table, see the [module docs](self).
+///
+/// ```ignore
+/// syscalls! {
+///     /// Doc comments end up on the generated items and in the reference.
+///     150 => init_screen(width: u32, height: u32) -> SysResult<()>;
+/// }
+/// ```
+macro_rules! syscalls {
+    ($(
+        $(#[doc = $doc:literal])*
+        $id:literal => $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;
+    )*) => {
+        /// Call ids, the immediate of the `syscall` instruction.
+        #[allow(non_upper_case_globals)]
+        pub mod id {
+            $(
+                pub const $name: u32 = $id;
+            )*
+        }
+
+        /// One function per syscall, arguments and return values exactly as they go through
+        /// the registers. Pointers are passed as addresses so these are all unsafe, prefer the
+        /// safe wrappers in [`crate::sys`].
+        #[allow(clippy::missing_safety_doc, clippy::unused_unit, clippy::unnecessary_cast)]
+        pub mod raw {
+            #[allow(unused_imports)]
+            use super::*;
+
+            $(
+                $(#[doc = $doc])*
+                #[inline(always)]
+                pub unsafe fn $name($($arg: $ty),*) -> $crate::sys::table::__syscall_ret!($($ret)?) {
+                    let (v0, v1) = $crate::sys::table::__syscall_regs!($id; $($arg as u32),*);
+                    <$crate::sys::table::__syscall_ret!($($ret)?) as $crate::sys::table::SysRet>::from_regs(
+                        v0, v1,
+                    )
+                }
+            )*
+        }
+
+        /// The host side of the table. Every call defaults to
+        /// [`SysRet::unsupported`](crate::sys::table::SysRet::unsupported) so a mock only has
+        /// to implement what it uses. Pointer arguments are guest addresses.
+        #[allow(unused_variables, clippy::unused_unit, clippy::unnecessary_cast)]
+        pub trait SyscallHandler {
+            $(
+                $(#[doc = $doc])*
+                fn $name(&mut self, $($arg: $ty),*) -> $crate::sys::table::__syscall_ret!($($ret)?) {
+                    <$crate::sys::table::__syscall_ret!($($ret)?) as $crate::sys::table::SysRet>::unsupported()
+                }
+            )*
+
+            /// Runs call `call_id` with the arguments in `regs` and writes the result back.
+            /// Returns `false` without touching `regs` for ids that aren't in the table.
+            fn dispatch(&mut self, call_id: u32, regs: &mut [u32; 32]) -> bool {
+                match call_id {
+                    $(
+                        $id => {
+                            #[allow(unused_mut, unused_variables)]
+                            let mut args = $crate::sys::table::ArgRegs::new(regs);
+                            let ret = self.$name($(args.next() as $ty),*);
+                            $crate::sys::table::write_ret(regs, ret);
+                        }
+                    )*
+                    _ => return false,
+                }
+                true
+            }
+        }
+
+        /// Every syscall in the table, in declaration order.
+        pub static SYSCALLS: &[$crate::sys::table::SyscallInfo] = &[$(
+            $crate::sys::table::SyscallInfo {
+                id: $id,
+                name: stringify!($name),
+                args: &[$($crate::sys::table::ArgInfo {
+                    name: stringify!($arg),
+                    ty: stringify!($ty),
+                }),*],
+                ret: $crate::sys::table::__syscall_ret_name!($($ret)?),
+                ret_regs: <$crate::sys::table::__syscall_ret!($($ret)?) as $crate::sys::table::SysRet>::REGS,
+                docs: &[$($doc),*],
+            }
+        ),*];
+    };
+}
+
+pub(crate) use {__syscall_regs, __syscall_ret, __syscall_ret_name, syscalls};
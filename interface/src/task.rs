@@ -0,0 +1,473 @@
+//! Cooperative green threads. Every task has its own stack from the global allocator and runs
+//! until it gives the CPU up with [`yield_now`], [`sleep_until`] or by waiting on a
+//! [`JoinHandle`], nothing ever preempts it. Switching saves the callee saved registers, `$gp`,
+//! `$fp` and `$ra` on the stack being left and the stack pointer in its task, then picks up the
+//! next one the same way.
+//!
+//! `main` becomes a task too the first time anything is spawned, it keeps the program stack.
+//! Tasks are run round robin, sleeping ones once their time has come. When every task is asleep
+//! the program sleeps until the first one has to wake up. The lowest [`crate::stack::GUARD_BYTES`]
+//! of each task stack are painted like the program stack's guard and checked whenever the task
+//! switches away, an overflow panics instead of running into the heap.
+//!
+//! Off target there is no switching, [`spawn`] runs the task to the end right away.
+//!
+//! ```ignore
+//! let music = interface::task::Builder::new()
+//!     .name("music")
+//!     .spawn(|| loop {
+//!         play_next_note();
+//!         interface::task::sleep(Duration::from_millis(10));
+//!     });
+//! let level = interface::task::spawn(|| decode_level(bytes));
+//! while !level.is_finished() {
+//!     draw_loading_screen();
+//!     interface::task::yield_now();
+//! }
+//! let level = level.join();
+//! ```
+
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{
+    cell::{Cell, UnsafeCell},
+    fmt,
+};
+
+use crate::time::{Duration, Instant};
+
+/// Stack of a task unless [`Builder::stack_size`] says otherwise.
+pub const DEFAULT_STACK_SIZE: usize = 16 * 1024;
+/// Anything smaller can't even hold the saved registers and the guard.
+pub const MIN_STACK_SIZE: usize = 1024;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TaskId(u32);
+
+impl TaskId {
+    /// The task `main` runs in.
+    pub const MAIN: TaskId = TaskId(0);
+
+    #[inline(always)]
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Display for TaskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "task {}", self.0)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Ready,
+    Sleeping(Instant),
+    Finished,
+}
+
+struct Task {
+    id: TaskId,
+    name: Option<&'static str>,
+    /// Where its registers were saved while it isn't running.
+    sp: usize,
+    state: State,
+    /// None for `main`, which runs on the program stack. Words so it is aligned for `$sp`.
+    stack: Option<Box<[u64]>>,
+    /// Taken when the task first runs. Off target `start` runs the entry right away instead.
+    #[cfg_attr(not(target_arch = "mips"), allow(dead_code))]
+    entry: Option<Box<dyn FnOnce()>>,
+}
+
+impl Task {
+    /// Whether the bottom of its stack is still painted.
+    fn guard_intact(&self) -> bool {
+        let Some(stack) = &self.stack else {
+            return true;
+        };
+        stack[..crate::stack::GUARD_BYTES / 8]
+            .iter()
+            .all(|word| unsafe { core::ptr::read_volatile(word) } == GUARD_PAINT)
+    }
+}
+
+impl fmt::Display for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        if let Some(name) = self.name {
+            write!(f, " ({})", name)?;
+        }
+        Ok(())
+    }
+}
+
+const GUARD_PAINT: u64 = (crate::stack::PAINT as u64) << 32 | crate::stack::PAINT as u64;
+
+struct Scheduler {
+    /// Boxed so the `sp` handed to [`switch`] stays put when the vec grows.
+    #[allow(clippy::vec_box)]
+    tasks: UnsafeCell<Vec<Box<Task>>>,
+    /// Index of the running task in `tasks`.
+    current: Cell<usize>,
+    next_id: Cell<u32>,
+}
+
+// the guest is single threaded and tasks only switch in here
+unsafe impl Sync for Scheduler {}
+
+static SCHEDULER: Scheduler = Scheduler {
+    tasks: UnsafeCell::new(Vec::new()),
+    current: Cell::new(0),
+    next_id: Cell::new(1),
+};
+
+impl Scheduler {
+    /// Nothing in here switches tasks, so the borrow never outlives a switch.
+    fn with_tasks<R>(&self, f: impl FnOnce(&mut Vec<Box<Task>>) -> R) -> R {
+        f(unsafe { &mut *self.tasks.get() })
+    }
+}
+
+enum Next {
+    /// The running task goes on.
+    Stay,
+    /// Save into the first, continue from the second.
+    Switch(*mut usize, usize),
+    /// Nothing can run before then.
+    Idle(Instant),
+}
+
+/// The first task after the running one that can run now, the running one last.
+fn pick(now: Instant) -> Next {
+    let current = SCHEDULER.current.get();
+    SCHEDULER.with_tasks(|tasks| {
+        let len = tasks.len();
+        let next = (1..=len)
+            .map(|i| (current + i) % len)
+            .find(|&i| match tasks[i].state {
+                State::Ready => true,
+                State::Sleeping(until) => until <= now,
+                State::Finished => false,
+            });
+        let Some(next) = next else {
+            let wake = tasks.iter().filter_map(|task| match task.state {
+                State::Sleeping(until) => Some(until),
+                _ => None,
+            });
+            return Next::Idle(wake.min().expect("every task has finished"));
+        };
+        tasks[next].state = State::Ready;
+        if next == current {
+            return Next::Stay;
+        }
+        let to = tasks[next].sp;
+        let from = &mut tasks[current];
+        if !from.guard_intact() {
+            panic!("stack overflow in {}: its guard was overwritten", from);
+        }
+        SCHEDULER.current.set(next);
+        Next::Switch(&mut from.sp, to)
+    })
+}
+
+/// Frees the tasks that finished, none of them is running anymore.
+fn reap() {
+    let current = SCHEDULER.current.get();
+    SCHEDULER.with_tasks(|tasks| {
+        let id = tasks[current].id;
+        tasks.retain(|task| task.state != State::Finished || task.id == id);
+        SCHEDULER
+            .current
+            .set(tasks.iter().position(|task| task.id == id).unwrap_or(0));
+    })
+}
+
+/// Switches to the next task that can run, back here once it is this one's turn again.
+fn schedule() {
+    loop {
+        match pick(Instant::now()) {
+            Next::Stay => return,
+            Next::Switch(from, to) => {
+                unsafe { switch(from, to) };
+                reap();
+                return;
+            }
+            Next::Idle(until) => crate::time::sleep_until(until),
+        }
+    }
+}
+
+/// Whether anything was spawned yet, before that there is nothing to switch to.
+fn started() -> bool {
+    SCHEDULER.with_tasks(|tasks| !tasks.is_empty())
+}
+
+//----------------------------------------------------------------
+
+/// Lets every other task that can run have a turn first.
+pub fn yield_now() {
+    if started() {
+        schedule();
+    }
+}
+
+/// Runs other tasks until `deadline`, the program sleeps when none of them has anything to do.
+pub fn sleep_until(deadline: Instant) {
+    if !started() {
+        crate::time::sleep_until(deadline);
+        return;
+    }
+    let current = SCHEDULER.current.get();
+    SCHEDULER.with_tasks(|tasks| tasks[current].state = State::Sleeping(deadline));
+    schedule();
+}
+
+/// [`sleep_until`] `duration` from now.
+pub fn sleep(duration: Duration) {
+    sleep_until(Instant::now().saturating_add(duration));
+}
+
+/// The running task, [`TaskId::MAIN`] before anything was spawned.
+pub fn current() -> TaskId {
+    if !started() {
+        return TaskId::MAIN;
+    }
+    let current = SCHEDULER.current.get();
+    SCHEDULER.with_tasks(|tasks| tasks[current].id)
+}
+
+/// Tasks that haven't finished, `main` included.
+pub fn count() -> usize {
+    SCHEDULER.with_tasks(|tasks| {
+        tasks
+            .iter()
+            .filter(|task| task.state != State::Finished)
+            .count()
+            .max(1)
+    })
+}
+
+/// Runs `f` in a new task with the defaults of [`Builder`].
+pub fn spawn<T: 'static>(f: impl FnOnce() -> T + 'static) -> JoinHandle<T> {
+    Builder::new().spawn(f)
+}
+
+//----------------------------------------------------------------
+
+pub struct Builder {
+    name: Option<&'static str>,
+    stack_size: usize,
+}
+
+impl Default for Builder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Builder {
+    pub const fn new() -> Self {
+        Self {
+            name: None,
+            stack_size: DEFAULT_STACK_SIZE,
+        }
+    }
+
+    /// Shows up in stack overflow panics.
+    pub const fn name(mut self, name: &'static str) -> Self {
+        self.name = Some(name);
+        self
+    }
+
+    /// In bytes, at least [`MIN_STACK_SIZE`].
+    pub const fn stack_size(mut self, stack_size: usize) -> Self {
+        self.stack_size = stack_size;
+        self
+    }
+
+    pub fn spawn<T: 'static>(self, f: impl FnOnce() -> T + 'static) -> JoinHandle<T> {
+        let result = Rc::new(Cell::new(None));
+        let sender = result.clone();
+        let id = TaskId(SCHEDULER.next_id.get());
+        SCHEDULER.next_id.set(id.0 + 1);
+        self.start(id, Box::new(move || sender.set(Some(f()))));
+        JoinHandle { id, result }
+    }
+
+    #[cfg(target_arch = "mips")]
+    fn start(self, id: TaskId, entry: Box<dyn FnOnce()>) {
+        let words = self.stack_size.max(MIN_STACK_SIZE).div_ceil(8);
+        let mut stack = alloc::vec![GUARD_PAINT; words].into_boxed_slice();
+
+        // what `switch` pops off when it first switches to the task, with the argument space
+        // of `task_main` above it
+        let top = stack.as_mut_ptr_range().end as usize - 16;
+        let sp = top - core::mem::size_of::<Saved>();
+        let gp: usize;
+        unsafe {
+            core::arch::asm!("move {0}, $gp", out(reg) gp, options(nomem, nostack));
+        }
+
+        let mut task = Box::new(Task {
+            id,
+            name: self.name,
+            sp,
+            state: State::Ready,
+            stack: None,
+            entry: Some(entry),
+        });
+        unsafe {
+            (sp as *mut Saved).write(Saved {
+                s: [&mut *task as *mut Task as usize, 0, 0, 0, 0, 0, 0, 0],
+                gp,
+                // ends backtraces at `task_main`
+                fp: 0,
+                ra: task_entry as unsafe extern "C" fn() -> ! as usize,
+                _pad: 0,
+            });
+        }
+        task.stack = Some(stack);
+
+        SCHEDULER.with_tasks(|tasks| {
+            if tasks.is_empty() {
+                tasks.push(Box::new(Task {
+                    id: TaskId::MAIN,
+                    name: Some("main"),
+                    sp: 0,
+                    state: State::Ready,
+                    stack: None,
+                    entry: None,
+                }));
+            }
+            tasks.push(task);
+        });
+    }
+
+    #[cfg(not(target_arch = "mips"))]
+    fn start(self, _id: TaskId, entry: Box<dyn FnOnce()>) {
+        entry();
+    }
+}
+
+/// Owns the result of a spawned task, dropping it lets the task run on unobserved.
+pub struct JoinHandle<T> {
+    id: TaskId,
+    result: Rc<Cell<Option<T>>>,
+}
+
+impl<T> JoinHandle<T> {
+    #[inline(always)]
+    pub fn id(&self) -> TaskId {
+        self.id
+    }
+
+    /// Whether the task has returned, [`JoinHandle::join`] won't wait then.
+    pub fn is_finished(&self) -> bool {
+        let result = self.result.take();
+        let finished = result.is_some();
+        self.result.set(result);
+        finished
+    }
+
+    /// Runs other tasks until this one has returned.
+    pub fn join(self) -> T {
+        loop {
+            if let Some(result) = self.result.take() {
+                return result;
+            }
+            if current() == self.id {
+                panic!("{} tried to join itself", self.id);
+            }
+            yield_now();
+        }
+    }
+}
+
+//----------------------------------------------------------------
+
+/// What [`switch`] keeps on the stack it leaves, the callee saved registers of the o32 ABI.
+#[cfg(target_arch = "mips")]
+#[repr(C)]
+struct Saved {
+    /// `$s0` to `$s7`, a new task has itself in `$s0`.
+    s: [usize; 8],
+    gp: usize,
+    fp: usize,
+    ra: usize,
+    /// Keeps `$sp` 8 byte aligned.
+    _pad: usize,
+}
+
+#[cfg(target_arch = "mips")]
+const SAVED_GP: usize = 8 * 4;
+#[cfg(target_arch = "mips")]
+const SAVED_FP: usize = SAVED_GP + 4;
+#[cfg(target_arch = "mips")]
+const SAVED_RA: usize = SAVED_FP + 4;
+
+/// Pushes the callee saved registers, stores `$sp` in `from`, then pops them off the stack at
+/// `to` and returns to wherever that one was saved.
+#[cfg(target_arch = "mips")]
+#[naked]
+unsafe extern "C" fn switch(from: *mut usize, to: usize) {
+    core::arch::asm! {
+        ".set noreorder",
+        "addiu $sp, $sp, -{size}",
+        ".irp n, 16,17,18,19,20,21,22,23",
+        "sw $\\n, ((\\n - 16) * 4)($sp)",
+        ".endr",
+        "sw $gp, {gp}($sp)",
+        "sw $fp, {fp}($sp)",
+        "sw $ra, {ra}($sp)",
+        "sw $sp, 0($a0)",
+
+        "move $sp, $a1",
+        ".irp n, 16,17,18,19,20,21,22,23",
+        "lw $\\n, ((\\n - 16) * 4)($sp)",
+        ".endr",
+        "lw $gp, {gp}($sp)",
+        "lw $fp, {fp}($sp)",
+        "lw $ra, {ra}($sp)",
+        // also the load delay, the emulator has no delay slots so nothing goes after the `jr`
+        "addiu $sp, $sp, {size}",
+        "jr $ra",
+        "nop",
+        ".set reorder",
+        size = const core::mem::size_of::<Saved>(),
+        gp = const SAVED_GP,
+        fp = const SAVED_FP,
+        ra = const SAVED_RA,
+        options(noreturn),
+    }
+}
+
+#[cfg(not(target_arch = "mips"))]
+unsafe fn switch(_from: *mut usize, _to: usize) {
+    unreachable!("tasks only switch on the mips target")
+}
+
+/// Where a new task's `$ra` points, hands the task in `$s0` to [`task_main`].
+#[cfg(target_arch = "mips")]
+#[naked]
+unsafe extern "C" fn task_entry() -> ! {
+    core::arch::asm! {
+        ".set noreorder",
+        "move $a0, $s0",
+        "jal {main}",
+        "nop",
+        ".set reorder",
+        main = sym task_main,
+        options(noreturn),
+    }
+}
+
+#[cfg(target_arch = "mips")]
+extern "C" fn task_main(task: *mut Task) -> ! {
+    if let Some(entry) = unsafe { (*task).entry.take() } {
+        entry();
+    }
+    unsafe { (*task).state = State::Finished };
+    // the next task frees the stack this is running on
+    schedule();
+    unreachable!("a finished task was switched to")
+}
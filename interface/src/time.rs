@@ -0,0 +1,342 @@
+//! Points in time and spans between them instead of raw `get_micros`/`get_nanos` numbers, and
+//! [`FixedTimestep`] for game loops.
+//!
+//! [`Instant`] counts nanoseconds since the program started, like [`crate::sys::get_nanos`],
+//! [`Instant::from_micros`] turns a [`crate::sys::get_micros`] reading into one. [`sleep_until`]
+//! sleeps whole milliseconds and spins for the rest, so waking up isn't late by up to a
+//! millisecond like with `sleep_mills`.
+//!
+//! ```ignore
+//! let mut timestep = FixedTimestep::from_hz(60);
+//! loop {
+//!     for _ in 0..timestep.advance() {
+//!         world.update(timestep.step());
+//!     }
+//!     world.render(timestep.alpha());
+//!     let _ = interface::sys::update_screen();
+//!     timestep.sleep();
+//! }
+//! ```
+
+use core::{
+    fmt,
+    ops::{Add, AddAssign, Sub, SubAssign},
+};
+
+pub use core::time::Duration;
+
+/// Nanoseconds since the program started.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Instant(u64);
+
+impl Instant {
+    /// When the program started.
+    pub const START: Instant = Instant(0);
+
+    #[inline(always)]
+    pub fn now() -> Self {
+        Self(crate::sys::get_nanos())
+    }
+
+    #[inline(always)]
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self(nanos)
+    }
+
+    /// A [`crate::sys::get_micros`] reading.
+    #[inline(always)]
+    pub const fn from_micros(micros: u64) -> Self {
+        Self(micros.saturating_mul(1000))
+    }
+
+    #[inline(always)]
+    pub const fn as_nanos(self) -> u64 {
+        self.0
+    }
+
+    #[inline(always)]
+    pub const fn as_micros(self) -> u64 {
+        self.0 / 1000
+    }
+
+    /// Zero when `earlier` is later.
+    pub fn duration_since(self, earlier: Instant) -> Duration {
+        Duration::from_nanos(self.0.saturating_sub(earlier.0))
+    }
+
+    pub fn checked_duration_since(self, earlier: Instant) -> Option<Duration> {
+        self.0.checked_sub(earlier.0).map(Duration::from_nanos)
+    }
+
+    /// Time since `self`, zero when it is in the future.
+    pub fn elapsed(self) -> Duration {
+        Self::now().duration_since(self)
+    }
+
+    pub fn checked_add(self, duration: Duration) -> Option<Instant> {
+        let nanos = u64::try_from(duration.as_nanos()).ok()?;
+        self.0.checked_add(nanos).map(Self)
+    }
+
+    pub fn checked_sub(self, duration: Duration) -> Option<Instant> {
+        let nanos = u64::try_from(duration.as_nanos()).ok()?;
+        self.0.checked_sub(nanos).map(Self)
+    }
+
+    /// Stops at the end of time instead of overflowing.
+    pub fn saturating_add(self, duration: Duration) -> Instant {
+        self.checked_add(duration).unwrap_or(Self(u64::MAX))
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        self.checked_add(duration)
+            .expect("overflow when adding a duration to an instant")
+    }
+}
+
+impl AddAssign<Duration> for Instant {
+    fn add_assign(&mut self, duration: Duration) {
+        *self = *self + duration;
+    }
+}
+
+impl Sub<Duration> for Instant {
+    type Output = Instant;
+
+    fn sub(self, duration: Duration) -> Instant {
+        self.checked_sub(duration)
+            .expect("overflow when subtracting a duration from an instant")
+    }
+}
+
+impl SubAssign<Duration> for Instant {
+    fn sub_assign(&mut self, duration: Duration) {
+        *self = *self - duration;
+    }
+}
+
+/// [`Instant::duration_since`], zero when `earlier` is later.
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
+
+impl fmt::Display for Instant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}.{:06}s",
+            self.0 / 1_000_000_000,
+            self.0 % 1_000_000_000 / 1000
+        )
+    }
+}
+
+/// Sleeps whole milliseconds while there are any left and spins for the rest.
+pub fn sleep_until(deadline: Instant) {
+    loop {
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        let mills = (deadline - now).as_millis();
+        if mills > 0 {
+            crate::sys::sleep_mills(mills.min(u32::MAX as u128) as u32);
+        }
+    }
+}
+
+/// [`sleep_until`] `duration` from now.
+pub fn sleep(duration: Duration) {
+    sleep_until(Instant::now().saturating_add(duration));
+}
+
+//----------------------------------------------------------------
+
+/// Running averages of how long frames take and how much of that wasn't spent sleeping, each
+/// new frame weighs 1/16th.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    frames: u64,
+    frame_nanos: u64,
+    busy_nanos: u64,
+}
+
+impl FrameStats {
+    pub const fn new() -> Self {
+        Self {
+            frames: 0,
+            frame_nanos: 0,
+            busy_nanos: 0,
+        }
+    }
+
+    /// A frame that took `frame`, of which it spent `busy` not sleeping.
+    pub fn record(&mut self, frame: Duration, busy: Duration) {
+        let frame = frame.as_nanos().min(u64::MAX as u128) as u64;
+        let busy = (busy.as_nanos().min(u64::MAX as u128) as u64).min(frame);
+        if self.frames == 0 {
+            self.frame_nanos = frame;
+            self.busy_nanos = busy;
+        } else {
+            self.frame_nanos = self.frame_nanos - self.frame_nanos / 16 + frame / 16;
+            self.busy_nanos = self.busy_nanos - self.busy_nanos / 16 + busy / 16;
+        }
+        self.frames += 1;
+    }
+
+    /// Frames recorded so far.
+    #[inline(always)]
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    #[inline(always)]
+    pub fn frame_time(&self) -> Duration {
+        Duration::from_nanos(self.frame_nanos)
+    }
+
+    /// The part of [`FrameStats::frame_time`] that wasn't spent sleeping.
+    #[inline(always)]
+    pub fn busy_time(&self) -> Duration {
+        Duration::from_nanos(self.busy_nanos)
+    }
+
+    /// Frames per second, 0 before any were recorded.
+    pub fn fps(&self) -> u32 {
+        1_000_000_000u64
+            .checked_div(self.frame_nanos)
+            .unwrap_or_default() as u32
+    }
+
+    /// How much of the frame time was busy, in hundredths of a percent.
+    pub fn cpu_usage(&self) -> u32 {
+        (self.busy_nanos as u128 * 10000)
+            .checked_div(self.frame_nanos as u128)
+            .unwrap_or_default() as u32
+    }
+}
+
+impl fmt::Display for FrameStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let cpu = self.cpu_usage();
+        write!(
+            f,
+            "{} fps, {} us a frame, cpu {}.{:02}%",
+            self.fps(),
+            self.frame_time().as_micros(),
+            cpu / 100,
+            cpu % 100
+        )
+    }
+}
+
+//----------------------------------------------------------------
+
+/// Steps a simulation at a fixed rate no matter how long frames take.
+///
+/// [`FixedTimestep::advance`] once a frame says how many steps are due since the last one,
+/// slow frames get more than one to catch up. A frame that falls more than
+/// [`FixedTimestep::with_max_steps`] behind drops the rest instead of taking even longer to
+/// catch up next time. [`FixedTimestep::alpha`] is how far into the next step the frame is, for
+/// drawing between the last two states, and [`FixedTimestep::sleep`] waits until the next step
+/// is due. Every `advance` also goes into [`FrameStats`].
+#[derive(Clone, Debug)]
+pub struct FixedTimestep {
+    step: Duration,
+    max_steps: u32,
+    /// Time that hasn't been stepped through yet.
+    pending: Duration,
+    /// The last `advance`.
+    last: Option<Instant>,
+    /// Slept since the last `advance`.
+    slept: Duration,
+    stats: FrameStats,
+}
+
+impl FixedTimestep {
+    /// A step every `step`, at most 4 of them in a frame.
+    pub const fn new(step: Duration) -> Self {
+        Self {
+            step,
+            max_steps: 4,
+            pending: Duration::ZERO,
+            last: None,
+            slept: Duration::ZERO,
+            stats: FrameStats::new(),
+        }
+    }
+
+    /// `hz` steps a second, panics when it is 0.
+    pub const fn from_hz(hz: u32) -> Self {
+        assert!(hz != 0, "FixedTimestep::from_hz(0)");
+        Self::new(Duration::from_nanos(1_000_000_000 / hz as u64))
+    }
+
+    /// Most steps [`FixedTimestep::advance`] returns at once, at least 1.
+    pub const fn with_max_steps(mut self, max_steps: u32) -> Self {
+        self.max_steps = if max_steps == 0 { 1 } else { max_steps };
+        self
+    }
+
+    #[inline(always)]
+    pub fn step(&self) -> Duration {
+        self.step
+    }
+
+    #[inline(always)]
+    pub fn stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    /// How far into the next step this frame is, 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        self.pending.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// Steps to run this frame, 1 the first time.
+    pub fn advance(&mut self) -> u32 {
+        self.advance_to(Instant::now())
+    }
+
+    /// [`FixedTimestep::advance`] with the frame starting at `now`.
+    pub fn advance_to(&mut self, now: Instant) -> u32 {
+        let Some(last) = self.last.replace(now) else {
+            return 1;
+        };
+        let frame = now - last;
+        self.stats.record(
+            frame,
+            frame.saturating_sub(core::mem::take(&mut self.slept)),
+        );
+
+        self.pending += frame;
+        let mut steps = 0;
+        while self.pending >= self.step && steps < self.max_steps {
+            self.pending -= self.step;
+            steps += 1;
+        }
+        if self.pending >= self.step {
+            let behind = self.pending.as_nanos() % self.step.as_nanos().max(1);
+            self.pending = Duration::from_nanos(behind as u64);
+        }
+        steps
+    }
+
+    /// Sleeps until the next step is due, right away when it already is.
+    pub fn sleep(&mut self) {
+        let Some(last) = self.last else {
+            return;
+        };
+        let start = Instant::now();
+        sleep_until(last.saturating_add(self.step.saturating_sub(self.pending)));
+        self.slept += start.elapsed();
+    }
+}
@@ -0,0 +1,512 @@
+//! CPU exceptions and interrupts, with the coprocessor 0 of the MIPS I/II target: `Status`
+//! ($12), `Cause` ($13), `EPC` ($14) and `BadVAddr` ($8), vectors in kseg0 and `rfe` to
+//! return.
+//!
+//! [`install`] points the exception vectors at [`trap_entry`], which saves everything into a
+//! [`TrapFrame`] on the stack and hands it to the handler registered for the cause code. When
+//! the handler returns, the registers are restored from the frame and execution continues at
+//! [`TrapFrame::epc`], which the handler may change. Without a handler, [`default_handler`]
+//! panics with the cause, EPC and BadVAddr, so it ends up in the panic handler like any other
+//! bug. Backtraces taken in a handler continue into the code that trapped.
+//!
+//! ```ignore
+//! interface::trap::install();
+//! interface::trap::set_handler(Cause::Breakpoint, |frame| frame.skip_instruction());
+//! ```
+//!
+//! Syscalls are serviced by the host and never get here.
+
+use core::{cell::Cell, fmt};
+
+/// Where the CPU jumps, TLB refill (unused but harmless) and everything else, as offsets into
+/// [`MemoryMap::vectors`](crate::mem::MemoryMap::vectors). That page sits just above the stack,
+/// outside of the RAM the program uses otherwise.
+#[cfg(target_arch = "mips")]
+const VECTORS: [usize; 2] = [0x00, 0x80];
+
+/// `Status` bits
+pub const STATUS_IE: u32 = 1 << 0;
+pub const STATUS_IM_SHIFT: u32 = 8;
+/// Vectors in the boot ROM instead of RAM.
+pub const STATUS_BEV: u32 = 1 << 22;
+
+/// `Cause` bits
+pub const CAUSE_EXC_SHIFT: u32 = 2;
+pub const CAUSE_EXC_MASK: u32 = 0x1f << CAUSE_EXC_SHIFT;
+pub const CAUSE_IP_SHIFT: u32 = 8;
+/// The trapping instruction is in the delay slot of the branch at EPC.
+pub const CAUSE_BD: u32 = 1 << 31;
+
+/// Interrupt lines, 0 and 1 are the software ones.
+pub const INTERRUPT_LINES: usize = 8;
+const CAUSE_CODES: usize = 32;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Cause {
+    Interrupt = 0,
+    TlbModified = 1,
+    TlbLoad = 2,
+    TlbStore = 3,
+    AddressLoad = 4,
+    AddressStore = 5,
+    BusInstruction = 6,
+    BusData = 7,
+    Syscall = 8,
+    Breakpoint = 9,
+    ReservedInstruction = 10,
+    CoprocessorUnusable = 11,
+    Overflow = 12,
+    /// `teq` and friends, MIPS II.
+    Trap = 13,
+    FloatingPoint = 15,
+}
+
+impl Cause {
+    pub const fn from_code(code: u32) -> Option<Self> {
+        Some(match code {
+            0 => Self::Interrupt,
+            1 => Self::TlbModified,
+            2 => Self::TlbLoad,
+            3 => Self::TlbStore,
+            4 => Self::AddressLoad,
+            5 => Self::AddressStore,
+            6 => Self::BusInstruction,
+            7 => Self::BusData,
+            8 => Self::Syscall,
+            9 => Self::Breakpoint,
+            10 => Self::ReservedInstruction,
+            11 => Self::CoprocessorUnusable,
+            12 => Self::Overflow,
+            13 => Self::Trap,
+            15 => Self::FloatingPoint,
+            _ => return None,
+        })
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Interrupt => "interrupt",
+            Self::TlbModified => "TLB modified",
+            Self::TlbLoad => "TLB miss on load",
+            Self::TlbStore => "TLB miss on store",
+            Self::AddressLoad => "address error on load",
+            Self::AddressStore => "address error on store",
+            Self::BusInstruction => "bus error on fetch",
+            Self::BusData => "bus error on data",
+            Self::Syscall => "syscall",
+            Self::Breakpoint => "breakpoint",
+            Self::ReservedInstruction => "reserved instruction",
+            Self::CoprocessorUnusable => "coprocessor unusable",
+            Self::Overflow => "overflow",
+            Self::Trap => "trap",
+            Self::FloatingPoint => "floating point",
+        }
+    }
+}
+
+impl fmt::Display for Cause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(self.as_str())
+    }
+}
+
+//----------------------------------------------------------------
+
+/// Everything [`trap_entry`] saves, written back when the handler returns.
+#[derive(Clone, Copy, Debug, Default)]
+#[repr(C)]
+pub struct TrapFrame {
+    /// `$0` to `$31` as they were, `$26` and `$27` (`$k0`, `$k1`) are used by the entry and not
+    /// restored.
+    pub regs: [u32; 32],
+    pub hi: u32,
+    pub lo: u32,
+    pub status: u32,
+    pub cause: u32,
+    /// Where execution continues.
+    pub epc: u32,
+    pub bad_vaddr: u32,
+}
+
+#[cfg(target_arch = "mips")]
+const FRAME_REGS: usize = 0;
+const FRAME_HI: usize = 32 * 4;
+const FRAME_LO: usize = FRAME_HI + 4;
+const FRAME_STATUS: usize = FRAME_LO + 4;
+const FRAME_CAUSE: usize = FRAME_STATUS + 4;
+const FRAME_EPC: usize = FRAME_CAUSE + 4;
+const FRAME_BAD_VADDR: usize = FRAME_EPC + 4;
+const _: () = assert!(FRAME_BAD_VADDR + 4 == core::mem::size_of::<TrapFrame>());
+
+/// The stack [`trap_entry`] uses: the argument area of the call into Rust, the frame, then
+/// where it says the trapped code called it from for [`crate::panic::Backtrace`].
+#[cfg(target_arch = "mips")]
+const STACK_FRAME: usize = 16;
+#[cfg(target_arch = "mips")]
+const STACK_RA: usize = STACK_FRAME + core::mem::size_of::<TrapFrame>();
+#[cfg(target_arch = "mips")]
+const STACK_FP: usize = STACK_RA + 4;
+#[cfg(target_arch = "mips")]
+const STACK_SIZE: usize = (STACK_FP + 4 + 7) & !7;
+
+impl TrapFrame {
+    #[inline(always)]
+    pub const fn cause_code(&self) -> u32 {
+        (self.cause & CAUSE_EXC_MASK) >> CAUSE_EXC_SHIFT
+    }
+
+    #[inline(always)]
+    pub const fn cause(&self) -> Option<Cause> {
+        Cause::from_code(self.cause_code())
+    }
+
+    #[inline(always)]
+    pub const fn in_delay_slot(&self) -> bool {
+        self.cause & CAUSE_BD != 0
+    }
+
+    /// Pending interrupt lines, bit `n` for line `n`.
+    #[inline(always)]
+    pub const fn pending_interrupts(&self) -> u8 {
+        (self.cause >> CAUSE_IP_SHIFT) as u8
+    }
+
+    /// Continue after the instruction that trapped instead of running it again, like a handler
+    /// for `break` wants.
+    ///
+    /// # Panics
+    /// In a branch delay slot, the branch would have to be emulated to know where to go.
+    pub fn skip_instruction(&mut self) {
+        assert!(
+            !self.in_delay_slot(),
+            "can't skip an instruction in a branch delay slot"
+        );
+        self.epc = self.epc.wrapping_add(4);
+    }
+}
+
+impl fmt::Display for TrapFrame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.cause() {
+            Some(cause) => write!(f, "{}", cause)?,
+            None => write!(f, "cause {}", self.cause_code())?,
+        }
+        write!(f, " at {:08x}", self.epc)?;
+        if let Some(symbol) = crate::panic::symbolize(self.epc) {
+            write!(f, " {}+{:#x}", symbol.name, symbol.offset)?;
+        }
+        if self.in_delay_slot() {
+            write!(f, " (delay slot)")?;
+        }
+        write!(
+            f,
+            "\nbadvaddr {:08x} sp {:08x} fp {:08x} ra {:08x}",
+            self.bad_vaddr, self.regs[29], self.regs[30], self.regs[31]
+        )
+    }
+}
+
+//----------------------------------------------------------------
+
+pub type Handler = fn(&mut TrapFrame);
+
+struct Handlers {
+    causes: Cell<[Option<Handler>; CAUSE_CODES]>,
+    interrupts: Cell<[Option<Handler>; INTERRUPT_LINES]>,
+    installed: Cell<bool>,
+}
+
+// the guest is single threaded, handlers are only swapped with interrupts off
+unsafe impl Sync for Handlers {}
+
+static HANDLERS: Handlers = Handlers {
+    causes: Cell::new([None; CAUSE_CODES]),
+    interrupts: Cell::new([None; INTERRUPT_LINES]),
+    installed: Cell::new(false),
+};
+
+/// Runs `handler` for `cause`, returns the one it replaces. Interrupts are dispatched per line
+/// with [`set_interrupt_handler`], a handler for [`Cause::Interrupt`] gets the ones without.
+pub fn set_handler(cause: Cause, handler: Handler) -> Option<Handler> {
+    without_interrupts(|| {
+        let mut causes = HANDLERS.causes.get();
+        let old = causes[cause as usize].replace(handler);
+        HANDLERS.causes.set(causes);
+        old
+    })
+}
+
+pub fn remove_handler(cause: Cause) -> Option<Handler> {
+    without_interrupts(|| {
+        let mut causes = HANDLERS.causes.get();
+        let old = causes[cause as usize].take();
+        HANDLERS.causes.set(causes);
+        old
+    })
+}
+
+/// Runs `handler` while interrupt `line` is pending, it has to acknowledge the interrupt or it
+/// fires again right away. Returns the one it replaces.
+///
+/// # Panics
+/// When `line` isn't below [`INTERRUPT_LINES`].
+pub fn set_interrupt_handler(line: usize, handler: Handler) -> Option<Handler> {
+    without_interrupts(|| {
+        let mut interrupts = HANDLERS.interrupts.get();
+        let old = interrupts[line].replace(handler);
+        HANDLERS.interrupts.set(interrupts);
+        old
+    })
+}
+
+/// Panics with what the frame says went wrong, for everything without a handler.
+pub fn default_handler(frame: &mut TrapFrame) {
+    panic!("unhandled exception: {}", frame);
+}
+
+#[cfg(target_arch = "mips")]
+extern "C" fn trap_dispatch(frame: &mut TrapFrame) {
+    let code = frame.cause_code() as usize;
+    if code == Cause::Interrupt as usize {
+        let interrupts = HANDLERS.interrupts.get();
+        let enabled = (frame.status >> STATUS_IM_SHIFT) as u8;
+        let pending = frame.pending_interrupts() & enabled;
+        let mut unhandled = 0;
+        for (line, handler) in interrupts.iter().enumerate() {
+            if pending & (1 << line) != 0 {
+                match handler {
+                    Some(handler) => handler(frame),
+                    None => unhandled |= 1 << line,
+                }
+            }
+        }
+        // nothing pending is a spurious one
+        if unhandled == 0 {
+            return;
+        }
+    }
+    match HANDLERS.causes.get().get(code).copied().flatten() {
+        Some(handler) => handler(frame),
+        None => default_handler(frame),
+    }
+}
+
+//----------------------------------------------------------------
+
+/// Where the vectors jump to. Saves the trapped state into a [`TrapFrame`] on the stack, calls
+/// the handler and returns to [`TrapFrame::epc`] with `rfe`.
+///
+/// Before the frame it saves `EPC + 4` as `$ra` and the trapped `$fp`, like a prologue would.
+/// Walking the stack then goes through here into the code that trapped, as if that had called
+/// `trap_entry` from EPC.
+///
+/// The `rfe` has to come before the `jr` back since there are no delay slots, an interrupt that
+/// lands on that `jr` returns to where it was going instead.
+///
+/// # Safety
+/// Only for the vectors, it returns with `rfe` to wherever EPC points.
+#[cfg(target_arch = "mips")]
+#[naked]
+pub unsafe extern "C" fn trap_entry() -> ! {
+    core::arch::asm! {
+        ".set noreorder",
+        ".set noat",
+        "addiu $sp, $sp, -{size}",
+        "move $k1, $ra",
+        "mfc0 $ra, $14",
+        "nop",
+        "addiu $ra, $ra, 4",
+        "sw $ra, {ra}($sp)",
+        "sw $fp, {fp}($sp)",
+        "move $ra, $k1",
+        ".irp n, 1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,26,27,28,30,31",
+        "sw $\\n, ({regs} + \\n * 4)($sp)",
+        ".endr",
+        "addiu $k0, $sp, {size}",
+        "sw $k0, ({regs} + 29 * 4)($sp)",
+        "mfhi $k0",
+        "sw $k0, {hi}($sp)",
+        "mflo $k0",
+        "sw $k0, {lo}($sp)",
+        "mfc0 $k0, $12",
+        "nop",
+        "sw $k0, {status}($sp)",
+        "mfc0 $k0, $13",
+        "nop",
+        "sw $k0, {cause}($sp)",
+        "mfc0 $k0, $14",
+        "nop",
+        // interrupted between the `rfe` and the `jr` at the end, go where that `jr` was going
+        "la $k1, 2f",
+        "bne $k0, $k1, 1f",
+        "nop",
+        "lw $k0, ({regs} + 26 * 4)($sp)",
+        "nop",
+        "1:",
+        "sw $k0, {epc}($sp)",
+        "mfc0 $k0, $8",
+        "nop",
+        "sw $k0, {bad_vaddr}($sp)",
+
+        "move $fp, $sp",
+        "addiu $a0, $sp, {regs}",
+        "jal {dispatch}",
+        "nop",
+
+        "lw $k0, {hi}($sp)",
+        "nop",
+        "mthi $k0",
+        "lw $k0, {lo}($sp)",
+        "nop",
+        "mtlo $k0",
+        ".irp n, 1,2,3,4,5,6,7,8,9,10,11,12,13,14,15,16,17,18,19,20,21,22,23,24,25,28,30,31",
+        "lw $\\n, ({regs} + \\n * 4)($sp)",
+        ".endr",
+        "lw $k0, {epc}($sp)",
+        // also the load delay
+        "addiu $sp, $sp, {size}",
+        // rfe, the emulator has no delay slots so it can't go after the `jr`
+        ".word 0x42000010",
+        "2:",
+        "jr $k0",
+        "nop",
+        ".set at",
+        ".set reorder",
+        size = const STACK_SIZE,
+        ra = const STACK_RA,
+        fp = const STACK_FP,
+        regs = const STACK_FRAME + FRAME_REGS,
+        hi = const STACK_FRAME + FRAME_HI,
+        lo = const STACK_FRAME + FRAME_LO,
+        status = const STACK_FRAME + FRAME_STATUS,
+        cause = const STACK_FRAME + FRAME_CAUSE,
+        epc = const STACK_FRAME + FRAME_EPC,
+        bad_vaddr = const STACK_FRAME + FRAME_BAD_VADDR,
+        dispatch = sym trap_dispatch,
+        options(noreturn),
+    }
+}
+
+/// Points the exception vectors at [`trap_entry`] and moves them out of the boot ROM. Does
+/// nothing off target.
+pub fn install() {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        // lui $k0, %hi(trap_entry); ori $k0, $k0, %lo(trap_entry); jr $k0; nop
+        let entry = trap_entry as unsafe extern "C" fn() -> ! as usize as u32;
+        let stub = [
+            0x3c1a_0000 | (entry >> 16),
+            0x375a_0000 | (entry & 0xffff),
+            0x0340_0008,
+            0,
+        ];
+        let base = crate::mem::memory_map().vectors.start;
+        for offset in VECTORS {
+            let vector = (base + offset) as *mut u32;
+            for (i, inst) in stub.iter().enumerate() {
+                core::ptr::write_volatile(vector.add(i), *inst);
+            }
+        }
+        write_status(read_status() & !STATUS_BEV);
+    }
+    HANDLERS.installed.set(true);
+}
+
+/// Whether [`install`] ran, before that nothing can interrupt and COP0 shouldn't be touched.
+#[inline(always)]
+pub fn is_installed() -> bool {
+    HANDLERS.installed.get()
+}
+
+//----------------------------------------------------------------
+
+#[inline(always)]
+pub fn read_status() -> u32 {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let status;
+        core::arch::asm!("mfc0 {0}, $12", "nop", out(reg) status);
+        status
+    }
+    #[cfg(not(target_arch = "mips"))]
+    0
+}
+
+/// # Safety
+/// Can turn interrupts on without handlers for them and move the vectors.
+#[inline(always)]
+pub unsafe fn write_status(status: u32) {
+    #[cfg(target_arch = "mips")]
+    core::arch::asm!("mtc0 {0}, $12", "nop", in(reg) status);
+    #[cfg(not(target_arch = "mips"))]
+    let _ = status;
+}
+
+#[inline(always)]
+pub fn read_cause() -> u32 {
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let cause;
+        core::arch::asm!("mfc0 {0}, $13", "nop", out(reg) cause);
+        cause
+    }
+    #[cfg(not(target_arch = "mips"))]
+    0
+}
+
+/// Unmasks the interrupt `lines` (bit `n` for line `n`) and turns interrupts on.
+pub fn enable_interrupts(lines: u8) {
+    unsafe { write_status(read_status() | ((lines as u32) << STATUS_IM_SHIFT) | STATUS_IE) }
+}
+
+/// Masks the interrupt `lines`, the others stay as they are.
+pub fn mask_interrupts(lines: u8) {
+    unsafe { write_status(read_status() & !((lines as u32) << STATUS_IM_SHIFT)) }
+}
+
+/// Turns interrupts off, returns whether they were on for [`restore_interrupts`].
+#[inline(always)]
+pub fn disable_interrupts() -> bool {
+    let status = read_status();
+    unsafe { write_status(status & !STATUS_IE) };
+    status & STATUS_IE != 0
+}
+
+#[inline(always)]
+pub fn restore_interrupts(enabled: bool) {
+    if enabled {
+        unsafe { write_status(read_status() | STATUS_IE) }
+    }
+}
+
+#[inline(always)]
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+    let enabled = disable_interrupts();
+    let ret = f();
+    restore_interrupts(enabled);
+    ret
+}
+
+/// Raises software interrupt `line`, 0 or 1. Its handler has to clear it again with
+/// [`clear_software_interrupt`].
+pub fn raise_software_interrupt(line: u32) {
+    set_software_interrupt(line, true);
+}
+
+pub fn clear_software_interrupt(line: u32) {
+    set_software_interrupt(line, false);
+}
+
+fn set_software_interrupt(line: u32, raised: bool) {
+    assert!(line < 2, "only interrupt lines 0 and 1 are software ones");
+    let bit = 1 << (CAUSE_IP_SHIFT + line);
+    #[cfg(target_arch = "mips")]
+    unsafe {
+        let cause = read_cause();
+        let cause = if raised { cause | bit } else { cause & !bit };
+        core::arch::asm!("mtc0 {0}, $13", "nop", in(reg) cause);
+    }
+    #[cfg(not(target_arch = "mips"))]
+    let _ = (bit, raised);
+}
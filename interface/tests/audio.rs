@@ -0,0 +1,254 @@
+//! What `interface::host::audio::Synth` renders for known channel registers and PCM streams, and
+//! the header `WavWriter` writes.
+
+use std::io::Cursor;
+
+use interface::{
+    audio::{Channel, ChannelRegs, Duty, OUTPUT_SAMPLE_RATE, SWEEP_RATE},
+    host::audio::{Synth, WavWriter, PCM_CAPACITY},
+};
+
+/// One period every 32 output samples, a waveform step per sample.
+const PERIOD_32: u32 = (OUTPUT_SAMPLE_RATE << 8) / 32;
+/// A single channel at full volume, a fifth of full scale.
+const FULL: i16 = (i16::MAX as f32 / 5.0) as i16;
+
+/// A level in `-1.0..=1.0` of one channel at full volume.
+fn level(level: f32) -> i16 {
+    (level / 5.0 * i16::MAX as f32) as i16
+}
+
+fn render(synth: &mut Synth, len: usize) -> Vec<i16> {
+    let mut out = vec![0; len];
+    synth.render(&mut out);
+    out
+}
+
+fn play(channel: Channel, regs: ChannelRegs, len: usize) -> Vec<i16> {
+    let mut synth = Synth::new();
+    synth.write_channel(channel as u32, regs);
+    render(&mut synth, len)
+}
+
+#[test]
+fn silent_until_something_plays() {
+    let mut synth = Synth::new();
+    assert!(render(&mut synth, 256).iter().all(|&sample| sample == 0));
+
+    // disabled or at volume 0 is silent too
+    let mut regs = ChannelRegs::tone(PERIOD_32, 15);
+    regs.enabled = 0;
+    assert!(play(Channel::Pulse1, regs, 256).iter().all(|&s| s == 0));
+    let regs = ChannelRegs::tone(PERIOD_32, 0);
+    assert!(play(Channel::Pulse1, regs, 256).iter().all(|&s| s == 0));
+}
+
+#[test]
+fn pulse_duty_is_the_high_part_of_the_period() {
+    for (duty, high) in [
+        (Duty::Eighth, 4),
+        (Duty::Quarter, 8),
+        (Duty::Half, 16),
+        (Duty::ThreeQuarters, 24),
+    ] {
+        let regs = ChannelRegs::tone(PERIOD_32, 15).with_duty(duty);
+        let out = play(Channel::Pulse2, regs, 64);
+        for (i, &sample) in out.iter().enumerate() {
+            let expected = if i % 32 < high { FULL } else { -FULL };
+            assert_eq!(sample, expected, "{duty:?} sample {i}");
+        }
+    }
+}
+
+#[test]
+fn volume_scales_the_channel() {
+    let regs = ChannelRegs::tone(PERIOD_32, 5);
+    assert_eq!(play(Channel::Pulse1, regs, 1), [level(5.0 / 15.0)]);
+
+    let mut synth = Synth::new();
+    synth.write_channel(Channel::Pulse1 as u32, ChannelRegs::tone(PERIOD_32, 15));
+    synth.set_master_volume(0);
+    assert!(render(&mut synth, 64).iter().all(|&s| s == 0));
+}
+
+#[test]
+fn triangle_steps_down_and_up_again() {
+    let out = play(Channel::Triangle, ChannelRegs::tone(PERIOD_32, 15), 64);
+    for (i, &sample) in out.iter().enumerate() {
+        let step = i % 32;
+        let height = if step < 16 { 15 - step } else { step - 16 };
+        assert_eq!(sample, level(height as f32 / 7.5 - 1.0), "sample {i}");
+    }
+    assert_eq!(out[0], FULL);
+    assert_eq!(out[15], -FULL);
+}
+
+#[test]
+fn wave_plays_the_table() {
+    let mut table = [0; 32];
+    for (i, step) in table.iter_mut().enumerate() {
+        *step = (i * 7 % 16) as u8;
+    }
+    let mut synth = Synth::new();
+    synth.set_wave(&table);
+    synth.write_channel(Channel::Wave as u32, ChannelRegs::tone(PERIOD_32, 15));
+    let out = render(&mut synth, 64);
+    for (i, &sample) in out.iter().enumerate() {
+        assert_eq!(
+            sample,
+            level(table[i % 32] as f32 / 7.5 - 1.0),
+            "sample {i}"
+        );
+    }
+}
+
+#[test]
+fn noise_repeats_after_the_length_of_its_lfsr() {
+    // one LFSR clock per output sample
+    let clocked = ChannelRegs::tone(OUTPUT_SAMPLE_RATE << 8, 15);
+
+    let short = play(Channel::Noise, clocked.with_short_noise(true), 1000);
+    assert!(short.iter().all(|&s| s == FULL || s == -FULL));
+    assert!(short.contains(&FULL) && short.contains(&-FULL));
+    // the first few clocks shift the long register's bits out before it is in the 7 bit loop
+    assert_eq!(short[200..327], short[327..454]);
+
+    let long = play(Channel::Noise, clocked, 40000);
+    assert!(long.contains(&FULL) && long.contains(&-FULL));
+    assert_ne!(long[200..327], long[327..454]);
+    assert_eq!(long[..5000], long[32767..32767 + 5000]);
+}
+
+#[test]
+fn sweep_moves_the_frequency_at_the_sweep_rate() {
+    let start = 1000 << 8;
+    let mut synth = Synth::new();
+    synth.write_channel(
+        Channel::Pulse1 as u32,
+        ChannelRegs::tone(start, 15).with_sweep(1, 3),
+    );
+    synth.write_channel(
+        Channel::Pulse2 as u32,
+        ChannelRegs::tone(start, 15).with_sweep(2, -2),
+    );
+
+    let tick = (OUTPUT_SAMPLE_RATE / SWEEP_RATE) as usize;
+    render(&mut synth, tick);
+    assert_eq!(synth.channel(Channel::Pulse1).frequency, start);
+    render(&mut synth, 1);
+    assert_eq!(
+        synth.channel(Channel::Pulse1).frequency,
+        start + (start >> 3)
+    );
+    // every other tick
+    assert_eq!(synth.channel(Channel::Pulse2).frequency, start);
+    render(&mut synth, tick + 1);
+    assert_eq!(
+        synth.channel(Channel::Pulse2).frequency,
+        start - (start >> 2)
+    );
+}
+
+//----------------------------------------------------------------
+
+/// A PCM sample as it comes out of the mix.
+fn pcm(sample: i16) -> i16 {
+    (sample as f32 / 32768.0 * i16::MAX as f32) as i16
+}
+
+#[test]
+fn pcm_is_resampled_to_the_output_rate() {
+    let mut synth = Synth::new();
+    synth.pcm_configure(OUTPUT_SAMPLE_RATE / 2, 1);
+    let samples = [1000, -2000, 3000, i16::MIN, i16::MAX];
+    assert_eq!(synth.pcm_write(&samples), samples.len());
+    assert_eq!(synth.pcm_available(), PCM_CAPACITY - samples.len());
+
+    // each sample twice, a frame later than it was written, then silence once it runs dry
+    let out = render(&mut synth, 14);
+    let mut expected = vec![0];
+    for sample in samples {
+        expected.extend([pcm(sample); 2]);
+    }
+    expected.extend([0; 3]);
+    assert_eq!(out, expected);
+    assert_eq!(synth.pcm_available(), PCM_CAPACITY);
+}
+
+#[test]
+fn stereo_pcm_is_mixed_down() {
+    let mut synth = Synth::new();
+    synth.pcm_configure(OUTPUT_SAMPLE_RATE, 2);
+    synth.pcm_write(&[1000, 3000, -4000, 0]);
+    assert_eq!(render(&mut synth, 3), [pcm(2000), pcm(-2000), 0]);
+}
+
+#[test]
+fn pcm_write_only_takes_what_fits() {
+    let mut synth = Synth::new();
+    assert_eq!(
+        synth.pcm_write(&vec![1; PCM_CAPACITY - 10]),
+        PCM_CAPACITY - 10
+    );
+    assert_eq!(synth.pcm_write(&[2; 100]), 10);
+    assert_eq!(synth.pcm_available(), 0);
+    // configuring drops what is queued
+    synth.pcm_configure(8000, 1);
+    assert_eq!(synth.pcm_available(), PCM_CAPACITY);
+}
+
+//----------------------------------------------------------------
+
+fn u16_at(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(bytes[at..at + 4].try_into().unwrap())
+}
+
+#[test]
+fn wav_header_has_the_format_and_sizes() {
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), 22050, 2).unwrap();
+    wav.write_samples(&[1, -1, 0x1234]).unwrap();
+    wav.write_samples(&[i16::MIN]).unwrap();
+    let bytes = wav.finish().unwrap().into_inner();
+
+    assert_eq!(bytes.len(), 44 + 8);
+    assert_eq!(&bytes[0..4], b"RIFF");
+    assert_eq!(u32_at(&bytes, 4), bytes.len() as u32 - 8);
+    assert_eq!(&bytes[8..16], b"WAVEfmt ");
+    assert_eq!(u32_at(&bytes, 16), 16);
+    // PCM, channels, sample rate, bytes a second, bytes a frame, bits a sample
+    assert_eq!(u16_at(&bytes, 20), 1);
+    assert_eq!(u16_at(&bytes, 22), 2);
+    assert_eq!(u32_at(&bytes, 24), 22050);
+    assert_eq!(u32_at(&bytes, 28), 22050 * 4);
+    assert_eq!(u16_at(&bytes, 32), 4);
+    assert_eq!(u16_at(&bytes, 34), 16);
+    assert_eq!(&bytes[36..40], b"data");
+    assert_eq!(u32_at(&bytes, 40), 8);
+    assert_eq!(
+        bytes[44..],
+        [0x01, 0x00, 0xff, 0xff, 0x34, 0x12, 0x00, 0x80]
+    );
+}
+
+#[test]
+fn wav_of_a_rendered_tone() {
+    let mut synth = Synth::new();
+    synth.write_channel(Channel::Pulse1 as u32, ChannelRegs::tone(PERIOD_32, 15));
+    let samples = render(&mut synth, OUTPUT_SAMPLE_RATE as usize / 10);
+
+    let mut wav = WavWriter::new(Cursor::new(Vec::new()), OUTPUT_SAMPLE_RATE, 1).unwrap();
+    wav.write_samples(&samples).unwrap();
+    let bytes = wav.finish().unwrap().into_inner();
+
+    assert_eq!(u32_at(&bytes, 40) as usize, samples.len() * 2);
+    assert_eq!(u32_at(&bytes, 28), OUTPUT_SAMPLE_RATE * 2);
+    let written: Vec<i16> = bytes[44..]
+        .chunks_exact(2)
+        .map(|sample| i16::from_le_bytes([sample[0], sample[1]]))
+        .collect();
+    assert_eq!(written, samples);
+}
@@ -0,0 +1,231 @@
+//! Frames built with `interface::binlog` decoded again by `interface::host::binlog`, against a
+//! `.binlog` section laid out like the linker does it.
+
+use interface::{
+    binlog::{Encode, Frame, FRAME_MAX, TAG_U32},
+    host::binlog::{format_message, DecodeError, Entry, Table, TableError, Value},
+    log::Level,
+};
+
+/// Where `mips/link.map` puts the section.
+const ADDR: u32 = 0;
+/// The first entry, behind the pad byte that keeps it off the null address.
+const FIRST: u32 = ADDR + 1;
+
+fn section(entries: &[&str]) -> (Vec<u8>, Vec<u32>) {
+    let mut data = vec![0];
+    let mut ids = Vec::new();
+    for entry in entries {
+        ids.push(ADDR + data.len() as u32);
+        data.extend(entry.as_bytes());
+        data.push(0);
+        // alignment padding between entries
+        data.extend([0; 3]);
+    }
+    (data, ids)
+}
+
+fn table(entries: &[&str]) -> (Table, Vec<u32>) {
+    let (data, ids) = section(entries);
+    (Table::from_section(ADDR, &data).unwrap(), ids)
+}
+
+fn frame(id: u32, args: &[&dyn Encode]) -> Frame {
+    let mut frame = Frame::new(id);
+    for arg in args {
+        arg.encode(&mut frame);
+    }
+    frame
+}
+
+#[test]
+fn entries_are_found_at_their_address() {
+    let (table, ids) = table(&[
+        "Info\x1fgame::logic\x1fsrc/logic.rs:12\x1fscore {}",
+        "Trace\x1fgame\x1fsrc/main.rs:3\x1fframe",
+    ]);
+    assert_eq!(ids[0], FIRST);
+    assert_eq!(table.len(), 2);
+    assert_eq!(
+        table.get(FIRST),
+        Some(&Entry {
+            level: Level::Info,
+            module: "game::logic".to_string(),
+            location: "src/logic.rs:12".to_string(),
+            format: "score {}".to_string(),
+        })
+    );
+    assert_eq!(table.get(ids[1]).unwrap().level, Level::Trace);
+    assert_eq!(table.get(ADDR), None);
+    assert_eq!(table.get(FIRST + 1), None);
+}
+
+#[test]
+fn malformed_entries_are_rejected() {
+    let (data, _) = section(&["Loud\x1fgame\x1fsrc/main.rs:3\x1fframe"]);
+    assert_eq!(
+        Table::from_section(ADDR, &data).err(),
+        Some(TableError::BadEntry(FIRST))
+    );
+    let (data, _) = section(&["Info\x1fgame"]);
+    assert_eq!(
+        Table::from_section(ADDR, &data).err(),
+        Some(TableError::BadEntry(FIRST))
+    );
+    // no terminating nul
+    assert_eq!(
+        Table::from_section(ADDR, b"\0Info\x1fgame\x1fsrc/main.rs:3\x1fframe").err(),
+        Some(TableError::BadEntry(FIRST))
+    );
+}
+
+#[test]
+fn every_tag_round_trips() {
+    let (table, ids) = table(&["Debug\x1fgame\x1fsrc/main.rs:1\x1f{} {} {} {} {} {} {} {} {}"]);
+    let frame = frame(
+        ids[0],
+        &[
+            &200u8,
+            &-3i16,
+            &u64::MAX,
+            &i64::MIN,
+            &true,
+            &'é',
+            &1.5f32,
+            &-0.25f64,
+            &"text",
+        ],
+    );
+    let message = table.decode(frame.as_bytes()).unwrap();
+    assert_eq!(
+        message.args,
+        [
+            Value::U32(200),
+            Value::I32(-3),
+            Value::U64(u64::MAX),
+            Value::I64(i64::MIN),
+            Value::Bool(true),
+            Value::Char('é' as u32),
+            Value::F32(1.5),
+            Value::F64(-0.25),
+            Value::Str("text".to_string()),
+        ]
+    );
+    assert_eq!(
+        message.text,
+        "200 -3 18446744073709551615 -9223372036854775808 true é 1.5 -0.25 text"
+    );
+    assert_eq!(
+        message.to_string(),
+        format!("DEBUG game: {} (src/main.rs:1)", message.text)
+    );
+}
+
+#[test]
+fn usize_and_isize_go_as_32_bits() {
+    let mut frame = Frame::new(FIRST);
+    7usize.encode(&mut frame);
+    (-7isize).encode(&mut frame);
+    let bytes = frame.as_bytes();
+    assert_eq!(bytes.len(), 4 + 5 + 5);
+    assert_eq!(bytes[..4], FIRST.to_le_bytes());
+    assert_eq!(bytes[4], TAG_U32);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn strings_are_cut_at_the_end_of_the_frame() {
+    let (table, ids) = table(&["Info\x1fgame\x1fsrc/main.rs:1\x1f{} {} {}"]);
+    let long = "x".repeat(FRAME_MAX * 2);
+    let frame = frame(ids[0], &[&1u32, &long.as_str(), &2u32]);
+    assert_eq!(frame.as_bytes().len(), FRAME_MAX);
+
+    let message = table.decode(frame.as_bytes()).unwrap();
+    let cut = FRAME_MAX - 4 - 5 - 3;
+    assert_eq!(message.args, [Value::U32(1), Value::Str("x".repeat(cut))]);
+    // the value that didn't fit shows up as missing
+    assert!(message.text.ends_with(" {?}"));
+}
+
+#[test]
+fn values_after_the_first_that_did_not_fit_are_dropped() {
+    let (table, ids) = table(&["Info\x1fgame\x1fsrc/main.rs:1\x1f{}"]);
+    let mut frame = Frame::new(ids[0]);
+    let mut sent = 0;
+    while frame.as_bytes().len() + 9 <= FRAME_MAX {
+        u64::MAX.encode(&mut frame);
+        sent += 1;
+    }
+    // a `u64` doesn't fit anymore, a `bool` would but comes after it
+    let len = frame.as_bytes().len();
+    1u64.encode(&mut frame);
+    true.encode(&mut frame);
+    assert_eq!(frame.as_bytes().len(), len);
+    assert_eq!(table.decode(frame.as_bytes()).unwrap().args.len(), sent);
+
+    // a frame cut off in the middle of a value loses only that value
+    let bytes = &frame.as_bytes()[..len - 2];
+    assert_eq!(table.decode(bytes).unwrap().args.len(), sent - 1);
+}
+
+#[test]
+fn bad_frames_are_errors() {
+    let (table, ids) = table(&["Info\x1fgame\x1fsrc/main.rs:1\x1f{}"]);
+    assert_eq!(table.decode(&[1, 0]).err(), Some(DecodeError::Truncated));
+    assert_eq!(
+        table.decode(&0x40u32.to_le_bytes()).err(),
+        Some(DecodeError::UnknownEntry(0x40))
+    );
+
+    let mut bytes = frame(ids[0], &[&5u32]).as_bytes().to_vec();
+    bytes.extend([0xEE, 1, 2]);
+    assert_eq!(
+        table.decode(&bytes).err(),
+        Some(DecodeError::UnknownTag(0xEE))
+    );
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn format_message_marks_missing_arguments() {
+    assert_eq!(
+        format_message("{} of {} at {:>4}", &[Value::U32(3)]),
+        "3 of {?} at {?}"
+    );
+    assert_eq!(format_message("{{}} {}", &[]), "{} {?}");
+    // extra arguments are left out
+    assert_eq!(
+        format_message("{}", &[Value::Bool(false), Value::U32(1)]),
+        "false"
+    );
+}
+
+#[test]
+fn format_message_follows_the_spec() {
+    let cases: &[(&str, Value, &str)] = &[
+        ("{:x}", Value::U32(255), "ff"),
+        ("{:#X}", Value::U32(255), "0xFF"),
+        ("{:#010b}", Value::U32(5), "0b00000101"),
+        ("{:o}", Value::U64(8), "10"),
+        ("{:+}", Value::I32(4), "+4"),
+        ("{:05}", Value::I64(-42), "-0042"),
+        ("{:>6}", Value::Str("ab".to_string()), "    ab"),
+        ("{:*^7}", Value::Str("ab".to_string()), "**ab***"),
+        ("{:.2}", Value::F32(1.0 / 3.0), "0.33"),
+        ("{:8.1}", Value::F64(2.25), "     2.2"),
+        ("{:e}", Value::F64(1500.0), "1.5e3"),
+        ("{:?}", Value::Str("a\"b".to_string()), "\"a\\\"b\""),
+        ("{:?}", Value::Char('x' as u32), "'x'"),
+        ("{}", Value::Char(0xD800), "\u{FFFD}"),
+        ("{:.3}", Value::Str("abcdef".to_string()), "abc"),
+    ];
+    for (format, value, expected) in cases {
+        assert_eq!(
+            format_message(format, std::slice::from_ref(value)),
+            *expected,
+            "{format}"
+        );
+    }
+}
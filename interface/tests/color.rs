@@ -0,0 +1,253 @@
+//! `interface::color::Color`: the packings, the blend modes, `lerp` and the round trips
+//! through HSV, HSL and RGB565.
+
+use interface::color::{BlendMode, Color, Hsl, Hsv};
+
+/// Every 17th value of each channel, white and black included.
+fn samples() -> impl Iterator<Item = Color> {
+    let steps = || (0..=255u8).step_by(17);
+    steps().flat_map(move |r| {
+        steps().flat_map(move |g| steps().map(move |b| Color::from_rgb(r, g, b)))
+    })
+}
+
+/// How far the HSV and HSL round trips may be off. One of the 256 hues moves a channel by up to
+/// 6, the conversions to them round down and their sixths of the wheel are 43 hues wide.
+const HUE_ERROR: u8 = 12;
+
+/// The largest difference of any rgb channel.
+fn distance(a: Color, b: Color) -> u8 {
+    [
+        a.r().abs_diff(b.r()),
+        a.g().abs_diff(b.g()),
+        a.b().abs_diff(b.b()),
+    ]
+    .into_iter()
+    .max()
+    .unwrap()
+}
+
+#[test]
+fn packs_as_abgr_with_the_alpha_on_top() {
+    let color = Color::from_rgba(0x11, 0x22, 0x33, 0x44);
+    assert_eq!(color.to_u32(), 0x4433_2211);
+    assert_eq!(u32::from(color), 0x4433_2211);
+    assert_eq!(u32::from(Color::WHITE), 0xffff_ffff);
+    assert_eq!(u32::from(Color::clear()), 0);
+    assert_eq!(u32::from(Color::from_rgb_additive(1, 2, 3)), 0x0003_0201);
+
+    for alpha in [0, 1, 0x80, 0xfe, 0xff] {
+        let color = color.with_alpha(alpha);
+        assert_eq!(Color::from_u32(color.to_u32()), color);
+        assert_eq!(color.to_u32() >> 24, alpha as u32);
+    }
+}
+
+#[test]
+fn values_from_the_host_are_opaque() {
+    // the host leaves the top byte alone, whatever is in it
+    assert_eq!(
+        Color::from(0x4433_2211u32),
+        Color::from_rgb(0x11, 0x22, 0x33)
+    );
+    assert_eq!(Color::from_u32_rgb(0x0033_2211).get_alpha(), 255);
+    assert!(Color::from(0u32).is_opaque());
+}
+
+#[test]
+fn hsv_packs_for_the_syscall() {
+    assert_eq!(
+        u32::from(Hsv {
+            h: 0x12,
+            s: 0x34,
+            v: 0x56
+        }),
+        0x0056_3412
+    );
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn replace_and_opaque_over_keep_the_source() {
+    let src = Color::from_rgba(10, 20, 30, 40);
+    let dst = Color::from_rgb(200, 150, 100);
+    assert_eq!(src.blend(dst, BlendMode::Replace), src);
+
+    let opaque = Color::from_rgb(1, 2, 3);
+    assert_eq!(opaque.blend(dst, BlendMode::AlphaOver), opaque);
+    assert_eq!(opaque.composite(dst), opaque);
+}
+
+#[test]
+fn over_mixes_by_the_source_alpha() {
+    let dst = Color::from_rgb(200, 150, 100);
+    // fully transparent leaves the destination as it was
+    assert_eq!(
+        Color::from_rgba(9, 9, 9, 0).blend(dst, BlendMode::AlphaOver),
+        dst
+    );
+
+    let half = Color::WHITE.with_alpha(128).over(Color::BLACK);
+    assert_eq!(half, Color::from_rgb(128, 128, 128));
+
+    let out = Color::from_rgba(0, 0, 255, 64).over(dst);
+    assert_eq!(out, Color::from_rgb(150, 112, 139));
+    // over a transparent destination the alpha is the source's
+    assert_eq!(
+        Color::from_rgba(0, 0, 255, 64)
+            .over(Color::clear())
+            .get_alpha(),
+        64
+    );
+}
+
+#[test]
+fn additive_saturates_and_keeps_the_destination_alpha() {
+    let dst = Color::from_rgba(100, 100, 100, 77);
+    assert_eq!(
+        Color::from_rgb(200, 100, 0).blend(dst, BlendMode::Additive),
+        Color::from_rgba(255, 200, 100, 77)
+    );
+    // scaled by the source alpha
+    assert_eq!(
+        Color::from_rgba(100, 50, 0, 128).additive(dst),
+        Color::from_rgba(150, 125, 100, 77)
+    );
+
+    // zero alpha rgb is light, clear does nothing
+    let light = Color::from_rgb_additive(20, 30, 40);
+    assert_eq!(light.composite(dst), Color::from_rgba(120, 130, 140, 77));
+    assert_eq!(Color::clear().composite(dst), dst);
+}
+
+#[test]
+fn multiply_by_white_is_the_identity() {
+    for dst in samples() {
+        let dst = dst.with_alpha(99);
+        assert_eq!(Color::WHITE.blend(dst, BlendMode::Multiply), dst);
+        assert_eq!(Color::BLACK.multiply(dst), Color::from_rgba(0, 0, 0, 99));
+    }
+    assert_eq!(
+        Color::from_rgb(128, 255, 0).multiply(Color::from_rgb(200, 200, 200)),
+        Color::from_rgb(100, 200, 0)
+    );
+}
+
+#[test]
+fn lerp_goes_from_one_end_to_the_other() {
+    let a = Color::from_rgba(0, 100, 255, 0);
+    let b = Color::from_rgba(255, 200, 0, 255);
+    assert_eq!(a.lerp(b, 0), a);
+    assert_eq!(a.lerp(b, 255), b);
+    assert_eq!(a.lerp(b, 128), Color::from_rgba(128, 150, 127, 128));
+    for t in 0..=255 {
+        let mid = a.lerp(b, t);
+        assert!(mid.r() <= b.r() && mid.b() >= b.b());
+        assert_eq!(a.lerp(a, t), a);
+    }
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn hsv_of_the_primaries() {
+    let cases = [
+        (Color::from_rgb(255, 0, 0), 0),
+        (Color::from_rgb(0, 255, 0), 85),
+        (Color::from_rgb(0, 0, 255), 171),
+    ];
+    for (color, h) in cases {
+        let hsv = Hsv { h, s: 255, v: 255 };
+        assert_eq!(color.to_hsv(), hsv);
+        // the sixths of the wheel are 43 wide, a little more than a sixth of 256
+        assert!(distance(Color::from(hsv), color) <= 3);
+    }
+    assert_eq!(
+        Color::from(Hsv {
+            h: 0,
+            s: 255,
+            v: 255
+        }),
+        Color::from_rgb(255, 0, 0)
+    );
+    assert_eq!(Color::BLACK.to_hsv(), Hsv::default());
+    assert_eq!(
+        Color::from_rgb(90, 90, 90).to_hsv(),
+        Hsv { h: 0, s: 0, v: 90 }
+    );
+}
+
+#[test]
+fn hsv_round_trips_closely() {
+    for color in samples() {
+        let back = Color::from_hsv(color.to_hsv());
+        assert!(
+            distance(color, back) <= HUE_ERROR,
+            "{color:?} came back as {back:?}"
+        );
+    }
+    for v in 0..=255 {
+        let gray = Color::from_rgb(v, v, v);
+        assert_eq!(Color::from_hsv(gray.to_hsv()), gray);
+    }
+}
+
+#[test]
+fn hsl_of_the_primaries() {
+    let red = Color::from_rgb(255, 0, 0);
+    assert_eq!(
+        red.to_hsl(),
+        Hsl {
+            h: 0,
+            s: 255,
+            l: 127
+        }
+    );
+    assert_eq!(Color::WHITE.to_hsl(), Hsl { h: 0, s: 0, l: 255 });
+    assert_eq!(Color::from(Hsl { h: 0, s: 0, l: 255 }), Color::WHITE);
+    assert_eq!(Color::from(Hsl { h: 0, s: 0, l: 0 }), Color::BLACK);
+    assert_eq!(Color::from(Hsl::default()), Color::BLACK);
+}
+
+#[test]
+fn hsl_round_trips_closely() {
+    for color in samples() {
+        let back = Color::from_hsl(color.to_hsl());
+        assert!(
+            distance(color, back) <= HUE_ERROR,
+            "{color:?} came back as {back:?}"
+        );
+    }
+    for l in 0..=255 {
+        let gray = Color::from_rgb(l, l, l);
+        assert_eq!(Color::from_hsl(gray.to_hsl()), gray);
+    }
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn rgb565_round_trips() {
+    for packed in 0..=u16::MAX {
+        assert_eq!(Color::from_rgb565(packed).to_rgb565(), packed);
+    }
+    assert_eq!(Color::WHITE.to_rgb565(), 0xffff);
+    assert_eq!(Color::from_rgb565(0xffff), Color::WHITE);
+    assert_eq!(Color::from_rgb565(0), Color::BLACK);
+    assert_eq!(Color::from_rgb(255, 0, 0).to_rgb565(), 0xf800);
+    assert_eq!(Color::from_rgb(0, 255, 0).to_rgb565(), 0x07e0);
+    // alpha is dropped and comes back opaque
+    assert_eq!(
+        Color::from_rgb565(Color::from_rgba(255, 0, 255, 3).to_rgb565()),
+        Color::from_rgb(255, 0, 255)
+    );
+
+    for color in samples() {
+        let back = Color::from_rgb565(color.to_rgb565());
+        assert!(
+            distance(color, back) <= 7,
+            "{color:?} came back as {back:?}"
+        );
+    }
+}
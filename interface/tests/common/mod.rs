@@ -0,0 +1,27 @@
+//! Helpers shared by the test files that touch the host file system.
+
+use std::path::{Path, PathBuf};
+
+/// A fresh directory in the system temp directory, removed with everything in it when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `name` keeps the tests apart, the process id the test runs.
+    pub fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("interface-{}-{}", name, std::process::id()));
+        // left over from a run that didn't get to clean up
+        let _ = std::fs::remove_dir_all(&path);
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -0,0 +1,158 @@
+//! `interface::executor` through `block_on` and `Executor::run`. The futures here only ever
+//! yield, `next_frame` and `sleep` would wait on the host.
+
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use interface::executor::{block_on, join, select, yield_now, Either, Executor};
+
+async fn after_yields<T>(yields: usize, value: T) -> T {
+    for _ in 0..yields {
+        yield_now().await;
+    }
+    value
+}
+
+/// Sets the flag when dropped.
+struct DropFlag(Rc<Cell<bool>>);
+
+impl Drop for DropFlag {
+    fn drop(&mut self) {
+        self.0.set(true);
+    }
+}
+
+#[test]
+fn block_on_returns_the_output() {
+    assert_eq!(block_on(async { 5 }), 5);
+    assert_eq!(block_on(after_yields(3, "done")), "done");
+}
+
+#[test]
+fn join_waits_for_both() {
+    assert_eq!(
+        block_on(join(after_yields(2, 1), after_yields(0, 'b'))),
+        (1, 'b')
+    );
+    assert_eq!(
+        block_on(join(after_yields(0, 1), after_yields(4, 'b'))),
+        (1, 'b')
+    );
+}
+
+#[test]
+fn select_takes_the_first_and_drops_the_other() {
+    assert_eq!(
+        block_on(select(after_yields(2, 'a'), after_yields(1, 'b'))),
+        Either::Right('b')
+    );
+    // `a` is polled first
+    assert_eq!(
+        block_on(select(after_yields(0, 'a'), after_yields(0, 'b'))),
+        Either::Left('a')
+    );
+
+    let dropped = Rc::new(Cell::new(false));
+    let flag = DropFlag(dropped.clone());
+    let slow = async move {
+        let _flag = flag;
+        after_yields(10, ()).await
+    };
+    assert_eq!(
+        block_on(select(slow, after_yields(1, ()))),
+        Either::Right(())
+    );
+    assert!(dropped.get());
+}
+
+#[test]
+fn yield_now_lets_the_spawned_tasks_take_turns() {
+    let order = Rc::new(RefCell::new(Vec::new()));
+    let mut buf = [0u8; 1024];
+    let mut executor: Executor<4> = Executor::new(&mut buf);
+    for task in 0..3 {
+        let order = order.clone();
+        let spawned = executor.spawn(async move {
+            for step in 0..2 {
+                order.borrow_mut().push((task, step));
+                yield_now().await;
+            }
+        });
+        assert!(spawned.is_ok());
+    }
+    assert_eq!(executor.pending(), 3);
+
+    executor.run(after_yields(5, ()));
+    assert_eq!(executor.pending(), 0);
+    assert_eq!(
+        *order.borrow(),
+        [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1)]
+    );
+}
+
+#[test]
+fn pending_tasks_carry_on_with_the_next_run() {
+    let steps = Rc::new(Cell::new(0));
+    let mut buf = [0u8; 256];
+    let mut executor: Executor<1> = Executor::new(&mut buf);
+    let counter = steps.clone();
+    assert!(executor
+        .spawn(async move {
+            loop {
+                counter.set(counter.get() + 1);
+                yield_now().await;
+            }
+        })
+        .is_ok());
+
+    // `run` returns as soon as its own future is done, the tasks need it to yield once
+    executor.run(after_yields(1, ()));
+    let first = steps.get();
+    assert!(first > 0);
+    executor.run(after_yields(3, ()));
+    assert!(steps.get() > first);
+    assert_eq!(executor.pending(), 1);
+}
+
+#[test]
+fn spawn_gives_the_future_back_when_the_slots_are_taken() {
+    let mut buf = [0u8; 256];
+    let mut executor: Executor<2> = Executor::new(&mut buf);
+    assert!(executor.spawn(after_yields(1, ())).is_ok());
+    assert!(executor.spawn(after_yields(1, ())).is_ok());
+
+    let ran = Rc::new(Cell::new(false));
+    let flag = ran.clone();
+    let Err(future) = executor.spawn(async move { flag.set(true) }) else {
+        panic!("spawned into a third slot");
+    };
+    assert_eq!(executor.pending(), 2);
+
+    // finished tasks free their slot
+    executor.run(after_yields(2, ()));
+    assert_eq!(executor.pending(), 0);
+    assert!(executor.spawn(future).is_ok());
+    executor.run(after_yields(1, ()));
+    assert!(ran.get());
+}
+
+#[test]
+fn spawn_gives_the_future_back_when_the_arena_is_full() {
+    let mut buf = [0u8; 64];
+    let mut executor: Executor<4> = Executor::new(&mut buf);
+    let remaining = executor.remaining();
+
+    let big = [0u8; 128];
+    let spawned = executor.spawn(async move {
+        std::hint::black_box(big);
+    });
+    assert!(spawned.is_err());
+    assert_eq!(executor.remaining(), remaining);
+    assert_eq!(executor.pending(), 0);
+
+    assert!(executor.spawn(async {}).is_ok());
+    executor.run(after_yields(1, ()));
+    assert_eq!(executor.pending(), 0);
+}
@@ -0,0 +1,188 @@
+//! `interface::host::fs::Sandbox`: path resolution, the calls on a real directory and the
+//! error codes.
+
+mod common;
+
+use std::path::Path;
+
+use common::TempDir;
+use interface::{
+    fs::{
+        Error, RawDirEntry, RawStat, KIND_DIRECTORY, KIND_FILE, OPEN_CREATE, OPEN_DIRECTORY,
+        OPEN_READ, OPEN_WRITE,
+    },
+    host::fs::Sandbox,
+};
+
+fn sandbox(name: &str) -> (Sandbox, TempDir) {
+    let dir = TempDir::new(&format!("fs-{name}"));
+    (Sandbox::new(dir.path()), dir)
+}
+
+#[test]
+fn resolve_stays_below_the_root() {
+    let sandbox = Sandbox::new("root");
+    let root = Path::new("root");
+
+    assert_eq!(sandbox.resolve("a/b.txt"), Ok(root.join("a").join("b.txt")));
+    assert_eq!(sandbox.resolve("./a//b/"), Ok(root.join("a").join("b")));
+    assert_eq!(sandbox.resolve(""), Ok(root.to_path_buf()));
+    // absolute guest paths are relative to the root too
+    assert_eq!(
+        sandbox.resolve("/etc/passwd"),
+        Ok(root.join("etc").join("passwd"))
+    );
+    assert_eq!(sandbox.resolve("//a"), Ok(root.join("a")));
+}
+
+#[test]
+fn resolve_rejects_traversal() {
+    let sandbox = Sandbox::new("root");
+
+    for path in [
+        "..",
+        "../a",
+        "a/../../b",
+        "a/..",
+        "/../etc/passwd",
+        "a/./../b",
+    ] {
+        assert_eq!(sandbox.resolve(path), Err(Error::InvalidPath), "{path:?}");
+    }
+}
+
+#[test]
+fn resolve_rejects_other_roots_and_separators() {
+    let sandbox = Sandbox::new("root");
+
+    for path in [
+        "C:/Windows",
+        "c:",
+        "a/C:b",
+        "\\\\server\\share",
+        "\\etc\\passwd",
+        "a\\..\\..\\b",
+        "a\0b",
+        "a/\0",
+    ] {
+        assert_eq!(sandbox.resolve(path), Err(Error::InvalidPath), "{path:?}");
+    }
+}
+
+#[test]
+fn calls_with_bad_paths_fail_with_invalid_path() {
+    let (mut sandbox, dir) = sandbox("bad-paths");
+
+    let invalid = Err(Error::InvalidPath);
+    assert_eq!(sandbox.open("../outside", OPEN_READ), invalid);
+    assert_eq!(sandbox.open("C:/outside", OPEN_READ), invalid);
+    assert!(!dir.path().parent().unwrap().join("outside").exists());
+}
+
+/// The names and kinds `read_dir` lists for `path`, sorted.
+fn list(sandbox: &mut Sandbox, path: &str) -> Vec<(String, u8, u32)> {
+    let dir = sandbox.open(path, OPEN_DIRECTORY).unwrap();
+    let mut entries = Vec::new();
+    let mut entry = RawDirEntry::default();
+    while sandbox.read_dir(dir, &mut entry).unwrap() {
+        let name = std::str::from_utf8(&entry.name[..entry.name_len as usize]).unwrap();
+        entries.push((name.to_string(), entry.kind, entry.size));
+    }
+    sandbox.close(dir).unwrap();
+    entries.sort();
+    entries
+}
+
+#[test]
+fn files_round_trip() {
+    let (mut sandbox, dir) = sandbox("round-trip");
+    std::fs::create_dir(dir.path().join("saves")).unwrap();
+
+    let file = sandbox
+        .open("saves/slot1.bin", OPEN_READ | OPEN_WRITE | OPEN_CREATE)
+        .unwrap();
+    assert_eq!(sandbox.write(file, b"hello world"), Ok(11));
+    // whence 0, 1 and 2 are start, current and end
+    assert_eq!(sandbox.seek(file, 6, 0), Ok(6));
+    assert_eq!(sandbox.write(file, b"there"), Ok(5));
+    assert_eq!(sandbox.seek(file, -5, 2), Ok(6));
+    let mut buf = [0; 16];
+    assert_eq!(sandbox.read(file, &mut buf), Ok(5));
+    assert_eq!(&buf[..5], b"there");
+    assert_eq!(sandbox.read(file, &mut buf), Ok(0));
+    assert_eq!(sandbox.seek(file, -11, 1), Ok(0));
+    assert_eq!(sandbox.read(file, &mut buf[..5]), Ok(5));
+    assert_eq!(&buf[..5], b"hello");
+    assert_eq!(sandbox.seek(file, 0, 3), Err(Error::InvalidArgument));
+    assert_eq!(sandbox.close(file), Ok(()));
+    assert_eq!(sandbox.read(file, &mut buf), Err(Error::BadHandle));
+    assert_eq!(
+        std::fs::read(dir.path().join("saves/slot1.bin")).unwrap(),
+        b"hello there"
+    );
+
+    let mut stat = RawStat::default();
+    assert_eq!(sandbox.stat("saves/slot1.bin", &mut stat), Ok(()));
+    assert_eq!((stat.size, stat.kind), (11, KIND_FILE));
+    assert_eq!(sandbox.stat("saves", &mut stat), Ok(()));
+    assert_eq!(stat.kind, KIND_DIRECTORY);
+
+    let notes = sandbox.open("notes.txt", OPEN_WRITE | OPEN_CREATE).unwrap();
+    sandbox.close(notes).unwrap();
+    assert_eq!(
+        list(&mut sandbox, "/"),
+        [
+            ("notes.txt".to_string(), KIND_FILE, 0),
+            ("saves".to_string(), KIND_DIRECTORY, stat.size),
+        ]
+    );
+    assert_eq!(
+        list(&mut sandbox, "saves"),
+        [("slot1.bin".to_string(), KIND_FILE, 11)]
+    );
+
+    // removed on the host, gone for the guest
+    std::fs::remove_file(dir.path().join("saves/slot1.bin")).unwrap();
+    assert_eq!(list(&mut sandbox, "saves"), []);
+    assert_eq!(
+        sandbox.stat("saves/slot1.bin", &mut stat),
+        Err(Error::NotFound)
+    );
+    assert_eq!(
+        sandbox.open("saves/slot1.bin", OPEN_READ),
+        Err(Error::NotFound)
+    );
+}
+
+#[test]
+fn opening_the_wrong_kind_fails() {
+    let (mut sandbox, dir) = sandbox("kinds");
+    std::fs::create_dir(dir.path().join("dir")).unwrap();
+    std::fs::write(dir.path().join("file"), b"").unwrap();
+
+    assert_eq!(sandbox.open("dir", OPEN_READ), Err(Error::IsADirectory));
+    assert_eq!(
+        sandbox.open("file", OPEN_DIRECTORY),
+        Err(Error::NotADirectory)
+    );
+    assert_eq!(sandbox.open("none", OPEN_DIRECTORY), Err(Error::NotFound));
+    let file = sandbox.open("file", OPEN_READ).unwrap();
+    let mut entry = RawDirEntry::default();
+    assert_eq!(sandbox.read_dir(file, &mut entry), Err(Error::BadHandle));
+}
+
+#[test]
+fn error_codes_round_trip() {
+    for err in Error::ALL {
+        assert!(err.code() < 0);
+        assert_eq!(Error::from_code(err.code()), err);
+        assert_eq!(Error::check(Error::status(Err(err))), Err(err));
+    }
+    assert_eq!(Error::from_code(-1000), Error::Other);
+    assert_eq!(Error::check(Error::status(Ok(7))), Ok(7));
+    // values too large for the status word are clamped instead of reading as errors
+    assert_eq!(
+        Error::check(Error::status(Ok(u32::MAX))),
+        Ok(i32::MAX as u32)
+    );
+}
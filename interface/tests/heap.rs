@@ -0,0 +1,376 @@
+//! The TLSF allocator in `interface::heap`, run over buffers of its own through `Heap::init`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+};
+
+use interface::heap::{ignore_oom, BlockInfo, Heap, OutOfMemory, Stats, ALIGN, MIN_BLOCK};
+
+/// A heap over a buffer of `bytes`, running out returns null instead of halting.
+struct TestHeap {
+    heap: Heap,
+    _buf: Vec<u64>,
+}
+
+impl TestHeap {
+    fn new(bytes: usize) -> Self {
+        let mut buf = vec![0u64; bytes / 8];
+        let heap = Heap::new();
+        let start = buf.as_mut_ptr() as usize;
+        unsafe { heap.init(start, start + bytes) };
+        heap.set_oom_handler(ignore_oom);
+        Self { heap, _buf: buf }
+    }
+
+    fn alloc(&self, size: usize, align: usize) -> *mut u8 {
+        unsafe {
+            self.heap
+                .alloc(Layout::from_size_align(size, align).unwrap())
+        }
+    }
+
+    fn free(&self, ptr: *mut u8) {
+        // the layout isn't looked at
+        unsafe { self.heap.dealloc(ptr, Layout::new::<u8>()) }
+    }
+
+    fn blocks(&self) -> Vec<BlockInfo> {
+        let mut blocks = Vec::new();
+        self.heap.walk(|block| blocks.push(block));
+        blocks
+    }
+
+    /// `(used, size)` of every block.
+    fn layout(&self) -> Vec<(bool, usize)> {
+        self.blocks()
+            .iter()
+            .map(|block| (block.used, block.size))
+            .collect()
+    }
+
+    /// All of it one free block, like right after `init`.
+    fn assert_empty(&self) {
+        let heap_bytes = self.heap.stats().heap_bytes;
+        assert_eq!(self.layout(), [(false, heap_bytes)]);
+    }
+}
+
+/// The block header, what `heap_bytes` is short of the buffer for the sentinel at the end.
+fn header() -> usize {
+    let heap = TestHeap::new(1024);
+    1024 - heap.heap.stats().heap_bytes
+}
+
+#[test]
+fn freeing_merges_with_both_neighbours() {
+    let heap = TestHeap::new(4096);
+    heap.assert_empty();
+
+    let ptrs: Vec<_> = (0..4).map(|_| heap.alloc(40, 8)).collect();
+    for ptr in &ptrs {
+        assert!(!ptr.is_null());
+        assert_eq!(*ptr as usize % ALIGN, 0);
+        unsafe { ptr.write_bytes(0xaa, 40) };
+    }
+    let size = heap.blocks()[0].size;
+
+    heap.free(ptrs[0]);
+    heap.free(ptrs[2]);
+    let rest = heap.blocks()[4].size;
+    assert_eq!(
+        heap.layout(),
+        [
+            (false, size),
+            (true, size),
+            (false, size),
+            (true, size),
+            (false, rest)
+        ]
+    );
+
+    // between two free blocks, all three become one
+    heap.free(ptrs[1]);
+    assert_eq!(
+        heap.layout(),
+        [(false, 3 * size), (true, size), (false, rest)]
+    );
+    heap.free(ptrs[3]);
+    heap.assert_empty();
+}
+
+#[test]
+fn over_aligned_allocations_free_what_is_in_front() {
+    let heap = TestHeap::new(64 * 1024);
+    let mut ptrs = Vec::new();
+    for align in [16, 32, 64, 256, 4096] {
+        // a small one first so the aligned payload isn't where the free block starts
+        ptrs.push(heap.alloc(8, 8));
+        let ptr = heap.alloc(24, align);
+        assert!(!ptr.is_null());
+        assert_eq!(ptr as usize % align, 0, "aligned to {align}");
+        unsafe { ptr.write_bytes(0x55, 24) };
+        ptrs.push(ptr);
+    }
+
+    let blocks = heap.blocks();
+    let total: usize = blocks.iter().map(|block| block.size).sum();
+    assert_eq!(total, heap.heap.stats().heap_bytes);
+    assert!(blocks.iter().all(|block| block.size >= MIN_BLOCK));
+    // whatever was cut off in front of an aligned payload went back on the lists
+    assert_eq!(blocks.iter().filter(|block| block.used).count(), ptrs.len());
+
+    for ptr in ptrs {
+        heap.free(ptr);
+    }
+    heap.assert_empty();
+}
+
+#[test]
+fn realloc_grows_and_shrinks_in_place_before_it_copies() {
+    let heap = TestHeap::new(4096);
+    let layout = Layout::from_size_align(32, 8).unwrap();
+    let ptr = heap.alloc(32, 8);
+    for i in 0..32 {
+        unsafe { *ptr.add(i) = i as u8 };
+    }
+
+    // into the free rest of the heap
+    let grown = unsafe { heap.heap.realloc(ptr, layout, 512) };
+    assert_eq!(grown, ptr);
+    let used = heap.heap.stats().used_bytes;
+    assert!(used >= 512);
+
+    let layout = Layout::from_size_align(512, 8).unwrap();
+    let shrunk = unsafe { heap.heap.realloc(ptr, layout, 64) };
+    assert_eq!(shrunk, ptr);
+    assert!(heap.heap.stats().used_bytes < used);
+    assert_eq!(heap.layout().len(), 2);
+
+    // a used block right behind it, so growing has to move
+    let behind = heap.alloc(8, 8);
+    let layout = Layout::from_size_align(64, 8).unwrap();
+    let moved = unsafe { heap.heap.realloc(ptr, layout, 1024) };
+    assert!(!moved.is_null());
+    assert_ne!(moved, ptr);
+    for i in 0..32 {
+        assert_eq!(unsafe { *moved.add(i) }, i as u8);
+    }
+    assert_eq!(heap.heap.stats().allocations, 2);
+
+    heap.free(moved);
+    heap.free(behind);
+    heap.assert_empty();
+}
+
+#[test]
+fn allocations_past_the_end_return_null() {
+    let heap = TestHeap::new(1024);
+    assert!(heap.alloc(1024, 8).is_null());
+    assert!(heap.alloc(usize::MAX / 4, 8).is_null());
+    assert!(heap.alloc(64, 1 << 20).is_null());
+
+    let mut ptrs = Vec::new();
+    loop {
+        let ptr = heap.alloc(100, 8);
+        if ptr.is_null() {
+            break;
+        }
+        ptrs.push(ptr);
+    }
+    assert!(!ptrs.is_empty());
+    assert!(heap.heap.stats().largest_free < 100);
+    // still usable afterwards
+    for ptr in ptrs {
+        heap.free(ptr);
+    }
+    heap.assert_empty();
+}
+
+#[test]
+fn a_block_only_a_little_larger_than_asked_for_is_used() {
+    // a single free block of 104 bytes, the list above the one it is in is empty, so only the
+    // first fit search through its own list finds it
+    let header = header();
+    let heap = TestHeap::new(104 + header);
+    assert_eq!(heap.layout(), [(false, 104)]);
+
+    let ptr = heap.alloc(104 - header, 8);
+    assert!(!ptr.is_null());
+    assert_eq!(heap.layout(), [(true, 104)]);
+    heap.free(ptr);
+    heap.assert_empty();
+}
+
+#[test]
+#[should_panic(expected = "twice")]
+fn double_free_panics() {
+    let heap = TestHeap::new(1024);
+    let ptr = heap.alloc(16, 8);
+    heap.free(ptr);
+    heap.free(ptr);
+}
+
+#[test]
+#[should_panic(expected = "outside of the heap")]
+fn freeing_a_foreign_pointer_panics() {
+    let heap = TestHeap::new(1024);
+    let mut foreign = 0u64;
+    heap.free(&mut foreign as *mut u64 as *mut u8);
+}
+
+#[test]
+#[should_panic(expected = "not aligned like an allocation")]
+fn freeing_into_the_middle_of_an_allocation_panics() {
+    let heap = TestHeap::new(1024);
+    let ptr = heap.alloc(16, 8);
+    heap.free(unsafe { ptr.add(4) });
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn stats_follow_allocations_and_frees() {
+    let heap = TestHeap::new(4096);
+    let heap_bytes = 4096 - header();
+    let empty = Stats {
+        heap_bytes,
+        allocations: 0,
+        used_bytes: 0,
+        peak_bytes: 0,
+        free_bytes: heap_bytes,
+        largest_free: heap_bytes,
+    };
+    assert_eq!(heap.heap.stats(), empty);
+
+    let ptrs: Vec<_> = (0..3).map(|_| heap.alloc(40, 8)).collect();
+    let size = heap.blocks()[0].size;
+    assert_eq!(
+        heap.heap.stats(),
+        Stats {
+            allocations: 3,
+            used_bytes: 3 * size,
+            peak_bytes: 3 * size,
+            free_bytes: heap_bytes - 3 * size,
+            largest_free: heap_bytes - 3 * size,
+            ..empty
+        }
+    );
+    assert_eq!(heap.heap.stats().fragmentation_percent(), 0);
+
+    // a hole in the middle, the largest free block is still the rest at the end
+    heap.free(ptrs[1]);
+    let stats = heap.heap.stats();
+    assert_eq!(
+        stats,
+        Stats {
+            allocations: 2,
+            used_bytes: 2 * size,
+            peak_bytes: 3 * size,
+            free_bytes: heap_bytes - 2 * size,
+            largest_free: heap_bytes - 3 * size,
+            ..empty
+        }
+    );
+    assert!(stats.fragmentation_percent() > 0);
+    assert_eq!(
+        stats.to_string(),
+        format!(
+            "2 allocations, {}/{} bytes used (peak {}), {} free, largest {} ({}% fragmented)",
+            2 * size,
+            heap_bytes,
+            3 * size,
+            heap_bytes - 2 * size,
+            heap_bytes - 3 * size,
+            stats.fragmentation_percent()
+        )
+    );
+
+    heap.free(ptrs[0]);
+    heap.free(ptrs[2]);
+    assert_eq!(
+        heap.heap.stats(),
+        Stats {
+            peak_bytes: 3 * size,
+            ..empty
+        }
+    );
+    assert_eq!(heap.heap.check_leaks(), 0);
+}
+
+#[test]
+fn fragmentation_is_the_free_memory_outside_the_largest_block() {
+    let stats = |free_bytes, largest_free| Stats {
+        free_bytes,
+        largest_free,
+        ..Stats::default()
+    };
+    assert_eq!(stats(0, 0).fragmentation_percent(), 0);
+    assert_eq!(stats(400, 400).fragmentation_percent(), 0);
+    assert_eq!(stats(400, 100).fragmentation_percent(), 75);
+    assert_eq!(stats(400, 0).fragmentation_percent(), 100);
+}
+
+#[test]
+fn walk_sees_every_allocation_where_it_was_handed_out() {
+    let heap = TestHeap::new(4096);
+    let ptrs = [heap.alloc(8, 8), heap.alloc(100, 8), heap.alloc(24, 64)];
+
+    let blocks = heap.blocks();
+    let used: Vec<_> = blocks.iter().filter(|block| block.used).collect();
+    assert_eq!(used.len(), heap.heap.stats().allocations);
+    for (block, ptr) in used.iter().zip(ptrs) {
+        assert_eq!(block.addr, ptr as usize);
+        // callers aren't tracked
+        assert_eq!(block.caller, None);
+    }
+    let used_bytes: usize = used.iter().map(|block| block.size).sum();
+    assert_eq!(used_bytes, heap.heap.stats().used_bytes);
+    // blocks follow each other without gaps
+    for pair in blocks.windows(2) {
+        assert_eq!(pair[0].addr + pair[0].size, pair[1].addr);
+    }
+
+    for ptr in ptrs {
+        heap.free(ptr);
+    }
+    assert_eq!(heap.heap.check_leaks(), 0);
+}
+
+//----------------------------------------------------------------
+
+thread_local! {
+    static FAILED: Cell<Option<OutOfMemory>> = const { Cell::new(None) };
+}
+
+fn record_oom(oom: &OutOfMemory) {
+    FAILED.set(Some(*oom));
+}
+
+#[test]
+fn running_out_calls_the_oom_handler_with_the_request_and_stats() {
+    let heap = TestHeap::new(1024);
+    heap.heap.set_oom_handler(record_oom);
+    let kept = heap.alloc(200, 8);
+
+    let layout = Layout::from_size_align(2000, 16).unwrap();
+    let ptr = unsafe { heap.heap.alloc(layout) };
+    assert!(ptr.is_null());
+    let oom = FAILED.take().expect("the handler ran");
+    assert_eq!(oom.layout, layout);
+    // no `$ra` to read off the mips target
+    assert_eq!(oom.caller, None);
+    assert_eq!(oom.stats, heap.heap.stats());
+    assert_eq!(oom.stats.allocations, 1);
+
+    // a failed realloc goes through it too and leaves the old allocation alone
+    let layout = Layout::from_size_align(200, 8).unwrap();
+    assert!(unsafe { heap.heap.realloc(kept, layout, 4000) }.is_null());
+    assert_eq!(FAILED.take().map(|oom| oom.layout.size()), Some(4000));
+    assert_eq!(heap.heap.stats().allocations, 1);
+
+    // nothing when allocations succeed
+    heap.free(kept);
+    assert!(!heap.alloc(200, 8).is_null());
+    assert!(FAILED.take().is_none());
+}
@@ -0,0 +1,223 @@
+//! `interface::mem::intrinsics` against the host's libc, every length up to a few words at every
+//! alignment of both pointers, and the word merging for both byte orders.
+
+use interface::mem::intrinsics;
+
+mod libc {
+    extern "C" {
+        pub fn memcpy(dest: *mut u8, src: *const u8, count: usize) -> *mut u8;
+        pub fn memmove(dest: *mut u8, src: *const u8, count: usize) -> *mut u8;
+        pub fn memset(dest: *mut u8, value: i32, count: usize) -> *mut u8;
+        pub fn memcmp(a: *const u8, b: *const u8, count: usize) -> i32;
+        pub fn strlen(str: *const u8) -> usize;
+    }
+}
+
+const WORD: usize = core::mem::size_of::<usize>();
+const MAX_LEN: usize = 8 * WORD + 3;
+const BUF: usize = MAX_LEN + 4 * WORD;
+
+/// Deterministic bytes that differ from one position to the next.
+fn pattern(seed: usize) -> [u8; BUF] {
+    let mut buf = [0u8; BUF];
+    let mut state = seed as u32 ^ 0x9e37_79b9;
+    for byte in buf.iter_mut() {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        *byte = state as u8;
+    }
+    buf
+}
+
+/// Word aligned so offsets into it are the alignments under test.
+#[repr(C, align(16))]
+struct Aligned([u8; BUF]);
+
+fn cases() -> impl Iterator<Item = (usize, usize, usize)> {
+    (0..WORD).flat_map(|dest| {
+        (0..WORD).flat_map(move |src| (0..=MAX_LEN).map(move |len| (dest, src, len)))
+    })
+}
+
+#[test]
+fn memcpy_matches_libc() {
+    for (dest, src, len) in cases() {
+        let source = Aligned(pattern(len));
+        let mut ours = Aligned([0xaa; BUF]);
+        let mut theirs = Aligned([0xaa; BUF]);
+        unsafe {
+            let ret = intrinsics::memcpy(
+                ours.0.as_mut_ptr().add(dest),
+                source.0.as_ptr().add(src),
+                len,
+            );
+            assert_eq!(ret, ours.0.as_mut_ptr().add(dest));
+            libc::memcpy(
+                theirs.0.as_mut_ptr().add(dest),
+                source.0.as_ptr().add(src),
+                len,
+            );
+        }
+        assert_eq!(ours.0, theirs.0, "dest +{} src +{} len {}", dest, src, len);
+    }
+}
+
+#[test]
+fn memmove_matches_libc_for_every_overlap() {
+    for len in 0..=MAX_LEN {
+        for from in 0..BUF - len {
+            for to in 0..BUF - len {
+                let mut ours = Aligned(pattern(len));
+                let mut theirs = Aligned(pattern(len));
+                unsafe {
+                    let base = ours.0.as_mut_ptr();
+                    let ret = intrinsics::memmove(base.add(to), base.add(from), len);
+                    assert_eq!(ret, base.add(to));
+                    let base = theirs.0.as_mut_ptr();
+                    libc::memmove(base.add(to), base.add(from), len);
+                }
+                assert_eq!(ours.0, theirs.0, "from +{} to +{} len {}", from, to, len);
+            }
+        }
+    }
+}
+
+#[test]
+fn memset_matches_libc() {
+    for (dest, _, len) in cases().filter(|(_, src, _)| *src == 0) {
+        for value in [0, 0x5a, 0xff, 0x1234] {
+            let mut ours = Aligned(pattern(len));
+            let mut theirs = Aligned(pattern(len));
+            unsafe {
+                intrinsics::memset(ours.0.as_mut_ptr().add(dest), value, len);
+                libc::memset(theirs.0.as_mut_ptr().add(dest), value, len);
+            }
+            assert_eq!(
+                ours.0, theirs.0,
+                "dest +{} value {:#x} len {}",
+                dest, value, len
+            );
+        }
+    }
+}
+
+#[test]
+fn memcmp_matches_libc_in_sign() {
+    for (a_at, b_at, len) in cases() {
+        let a = Aligned(pattern(len));
+        let mut b = Aligned([0; BUF]);
+        b.0[b_at..b_at + len].copy_from_slice(&a.0[a_at..a_at + len]);
+        let compare = |b: &Aligned| unsafe {
+            let ours = intrinsics::memcmp(a.0.as_ptr().add(a_at), b.0.as_ptr().add(b_at), len);
+            let theirs = libc::memcmp(a.0.as_ptr().add(a_at), b.0.as_ptr().add(b_at), len);
+            let equal = intrinsics::bcmp(a.0.as_ptr().add(a_at), b.0.as_ptr().add(b_at), len);
+            assert_eq!(
+                ours.signum(),
+                theirs.signum(),
+                "a +{} b +{} len {}",
+                a_at,
+                b_at,
+                len
+            );
+            assert_eq!(equal != 0, theirs != 0);
+            ours
+        };
+        assert_eq!(compare(&b), 0);
+
+        // one byte off in either direction at every position
+        for at in 0..len {
+            for delta in [1u8, 0x80, 0xff] {
+                let mut changed = Aligned(b.0);
+                changed.0[b_at + at] = changed.0[b_at + at].wrapping_add(delta);
+                assert_ne!(compare(&changed), 0);
+            }
+        }
+    }
+}
+
+#[test]
+fn strlen_matches_libc() {
+    for start in 0..WORD {
+        for len in 0..=MAX_LEN {
+            let mut buf = Aligned([0x80; BUF]);
+            for (i, byte) in buf.0[start..start + len].iter_mut().enumerate() {
+                // high bits and 0x01 bytes trip up the zero byte check when it is wrong
+                *byte = [0x01, 0x80, 0xff, 0x7f][i % 4];
+            }
+            buf.0[start + len] = 0;
+            let str = unsafe { buf.0.as_ptr().add(start) };
+            assert_eq!(unsafe { intrinsics::strlen(str) }, len, "start +{}", start);
+            assert_eq!(unsafe { libc::strlen(str) }, len);
+        }
+    }
+}
+
+//----------------------------------------------------------------
+
+fn word(bytes: &[u8], big_endian: bool) -> usize {
+    let bytes = bytes.try_into().unwrap();
+    if big_endian {
+        usize::from_be_bytes(bytes)
+    } else {
+        usize::from_le_bytes(bytes)
+    }
+}
+
+#[test]
+fn merge_puts_misaligned_words_together_in_both_byte_orders() {
+    let bytes = pattern(0);
+    for big_endian in [false, true] {
+        for offset in 1..WORD {
+            let low = word(&bytes[..WORD], big_endian);
+            let high = word(&bytes[WORD..2 * WORD], big_endian);
+            assert_eq!(
+                intrinsics::merge(low, high, offset, big_endian),
+                word(&bytes[offset..offset + WORD], big_endian),
+                "offset {} big endian {}",
+                offset,
+                big_endian
+            );
+        }
+    }
+}
+
+#[test]
+fn head_and_tail_loads_only_fill_their_bytes_in_both_byte_orders() {
+    let bytes = Aligned(pattern(1));
+    for big_endian in [false, true] {
+        for split in 1..WORD {
+            let mut head = [0; WORD];
+            head[split..].copy_from_slice(&bytes.0[split..WORD]);
+            let loaded =
+                unsafe { intrinsics::load_head(bytes.0.as_ptr().add(split), split, big_endian) };
+            assert_eq!(
+                loaded,
+                word(&head, big_endian),
+                "head {} big endian {}",
+                split,
+                big_endian
+            );
+
+            let mut tail = [0; WORD];
+            tail[..split].copy_from_slice(&bytes.0[..split]);
+            let loaded = unsafe { intrinsics::load_tail(bytes.0.as_ptr(), split, big_endian) };
+            assert_eq!(
+                loaded,
+                word(&tail, big_endian),
+                "tail {} big endian {}",
+                split,
+                big_endian
+            );
+
+            // a misaligned source read through the head and the aligned word after it
+            let next = word(&bytes.0[WORD..2 * WORD], big_endian);
+            let head =
+                unsafe { intrinsics::load_head(bytes.0.as_ptr().add(split), split, big_endian) };
+            assert_eq!(
+                intrinsics::merge(head, next, split, big_endian),
+                word(&bytes.0[split..split + WORD], big_endian)
+            );
+        }
+    }
+}
@@ -0,0 +1,176 @@
+//! The `interface::mem` allocators: `Arena`, `Bump` and `Pool`.
+
+#![feature(allocator_api)]
+
+use std::{cell::Cell, rc::Rc};
+
+use interface::mem::{Arena, Bump, Pool};
+
+#[test]
+fn bump_hands_out_aligned_memory_until_full() {
+    let mut buf = [0u8; 64];
+    let bump = Bump::new(&mut buf);
+
+    let a = Box::new_in(1u8, &bump);
+    let b = Box::new_in(2u32, &bump);
+    assert_eq!(&*b as *const u32 as usize % 4, 0);
+    assert!(bump.used() >= 5);
+    assert_eq!((*a, *b), (1, 2));
+
+    assert!(Box::try_new_in([0u8; 128], &bump).is_err());
+    drop((a, b));
+}
+
+#[test]
+fn bump_grows_the_last_vec_in_place() {
+    let mut buf = [0u8; 256];
+    let bump = Bump::new(&mut buf);
+
+    let mut vec = Vec::new_in(&bump);
+    vec.push(0u32);
+    let first = vec.as_ptr();
+    for i in 1..32 {
+        vec.push(i);
+    }
+    assert_eq!(vec.as_ptr(), first);
+    assert_eq!(bump.used(), 32 * 4);
+    assert_eq!(vec.iter().sum::<u32>(), (0..32).sum());
+}
+
+#[test]
+fn bump_takes_back_the_last_allocation_and_resets() {
+    let mut buf = [0u8; 64];
+    let mut bump = Bump::new(&mut buf);
+
+    let a = Box::new_in(1u64, &bump);
+    let b = Box::new_in(2u64, &bump);
+    drop(b);
+    assert_eq!(bump.used(), 8);
+    drop(a);
+    assert_eq!(bump.used(), 0);
+
+    let c = Box::new_in(3u64, &bump);
+    let d = Box::new_in(4u64, &bump);
+    drop(c);
+    assert_eq!(bump.used(), 16);
+    drop(d);
+    bump.reset();
+    assert_eq!(bump.remaining(), 64);
+}
+
+#[test]
+fn bump_from_the_global_heap() {
+    let bump = Bump::with_capacity(1024);
+    let vec: Vec<u16, _> = (0..100).collect_in(&bump);
+    assert_eq!(vec.len(), 100);
+    assert_eq!(bump.capacity(), 1024);
+}
+
+trait CollectIn<T> {
+    fn collect_in<A: std::alloc::Allocator>(self, alloc: A) -> Vec<T, A>;
+}
+
+impl<T, I: Iterator<Item = T>> CollectIn<T> for I {
+    fn collect_in<A: std::alloc::Allocator>(self, alloc: A) -> Vec<T, A> {
+        let mut vec = Vec::new_in(alloc);
+        vec.extend(self);
+        vec
+    }
+}
+
+struct Counted<'a>(&'a Cell<u32>);
+
+impl Drop for Counted<'_> {
+    fn drop(&mut self) {
+        self.0.set(self.0.get() + 1);
+    }
+}
+
+#[test]
+fn arena_drops_values_on_reset() {
+    let drops = Cell::new(0);
+    let mut buf = [0u8; 256];
+    let mut arena = Arena::new(&mut buf);
+
+    for _ in 0..3 {
+        assert!(arena.alloc(Counted(&drops)).is_ok());
+    }
+    let number = arena.alloc(7u32).unwrap();
+    *number += 1;
+    assert_eq!(*number, 8);
+    assert_eq!(drops.get(), 0);
+
+    arena.reset();
+    assert_eq!(drops.get(), 3);
+    assert_eq!(arena.used(), 0);
+
+    assert!(arena.alloc(Counted(&drops)).is_ok());
+    drop(arena);
+    assert_eq!(drops.get(), 4);
+}
+
+#[test]
+fn arena_gives_values_back_when_full() {
+    let mut buf = [0u8; 16];
+    let arena = Arena::new(&mut buf);
+
+    assert_eq!(arena.alloc([1u8; 32]).unwrap_err(), [1u8; 32]);
+    let rc = Rc::new(5);
+    let Err(back) = arena.alloc([rc.clone(), rc.clone(), rc.clone()]) else {
+        panic!("fit in 16 bytes");
+    };
+    drop(back);
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
+
+#[test]
+fn arena_copies_slices_and_strs() {
+    let arena = Arena::with_capacity(64);
+    let name = arena.alloc_str("tetris").unwrap();
+    name.make_ascii_uppercase();
+    let numbers = arena.alloc_slice_copy(&[1u16, 2, 3]).unwrap();
+    numbers[0] = 9;
+    assert_eq!(name, "TETRIS");
+    assert_eq!(numbers, [9, 2, 3]);
+    assert!(arena.alloc_str(&"x".repeat(128)).is_none());
+}
+
+#[test]
+fn pool_hands_out_every_slot_once() {
+    let pool: Pool<u64, 4> = Pool::new();
+    let boxes: Vec<_> = (0..4).map(|i| Box::new_in(i, &pool)).collect();
+    assert!(pool.is_full());
+    assert!(Box::try_new_in(4, &pool).is_err());
+
+    let mut addrs: Vec<_> = boxes.iter().map(|b| &**b as *const u64 as usize).collect();
+    addrs.sort();
+    addrs.dedup();
+    assert_eq!(addrs.len(), 4);
+    assert_eq!(boxes.iter().map(|b| **b).sum::<u64>(), 6);
+}
+
+#[test]
+fn pool_reuses_freed_slots() {
+    let pool: Pool<[u32; 3], 2> = Pool::new();
+    let a = Box::new_in([1u32; 3], &pool);
+    let b = Box::new_in([2u32; 3], &pool);
+    let b_addr = &*b as *const [u32; 3];
+    drop(b);
+    assert_eq!(pool.len(), 1);
+
+    let c = Box::new_in([3u32; 3], &pool);
+    assert_eq!(&*c as *const [u32; 3], b_addr);
+    assert_eq!((a[0], c[0]), (1, 3));
+    drop((a, c));
+    assert!(pool.is_empty());
+}
+
+#[test]
+fn pool_rejects_what_does_not_fit_a_slot() {
+    let pool: Pool<u16, 8> = Pool::new();
+    assert!(Box::try_new_in([0u64; 2], &pool).is_err());
+    let mut vec = Vec::new_in(&pool);
+    vec.push(1u16);
+    assert!(vec.try_reserve(16).is_err());
+    assert_eq!(pool.len(), 1);
+}
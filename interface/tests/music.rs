@@ -0,0 +1,256 @@
+//! FamiTracker text exports through `interface::host::fami` and played back by
+//! `interface::audio::music::MusicPlayer`, checking the channel writes of each tick.
+
+use interface::{
+    audio::{
+        music::{MusicPlayer, Song},
+        note_frequency, Channel, ChannelRegs, Duty,
+    },
+    host::fami::{compile, parse_text},
+};
+
+/// One row a tick at speed 1, macros step once a row.
+///
+/// Order 0 plays a lead with volume, arpeggio and duty macros over a plain triangle and noise
+/// note, then `D02` skips to the third row of order 1. That one drops to speed 2, `B02` jumps to
+/// order 2 and the `C00` there halts.
+const SONG: &str = r#"
+# FamiTracker text export 0.4.2
+
+MACRO       0   0  -1  -1   0 : 15 10 5
+MACRO       1   0   1  -1   0 : 0 12
+MACRO       4   0  -1  -1   0 : 1
+MACRO       0   1  -1   1   0 : 15 10 5
+MACRO       4   1  -1  -1   0 : 1
+
+INST2A03   0     0   0  -1  -1   0 "Lead"
+INST2A03   1    -1  -1  -1  -1  -1 "Plain"
+INST2A03   2     1  -1  -1  -1   1 "Released"
+
+TRACK   4   1 150 "Test"
+COLUMNS : 1 1 1 1 1
+
+ORDER 00 : 00 00 00 00 00
+ORDER 01 : 01 01 01 01 01
+ORDER 02 : 02 02 02 02 02
+
+PATTERN 00
+ROW 00 : A-4 00 . ... : ... .. . ... : A-3 01 . ... : C-# 01 . ... : ... .. . ...
+ROW 01 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+ROW 02 : --- .. . ... : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+ROW 03 : ... .. . D02 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+
+PATTERN 01
+ROW 00 : C-4 01 . ... : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+ROW 01 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+ROW 02 : E-4 01 8 F02 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+ROW 03 : ... .. . B02 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+
+PATTERN 02
+ROW 00 : ... .. . C00 : ... .. . ... : ... .. . ... : ... .. . ... : ... .. . ...
+"#;
+
+/// Track 0 with a single pattern of `rows`, instrument 2 is the one with a release point.
+fn song_with(rows: &[&str]) -> Vec<u8> {
+    let mut text = String::from(
+        "MACRO 0 1 -1 1 0 : 15 10 5\n\
+         MACRO 4 1 -1 -1 0 : 1\n\
+         INST2A03 2 1 -1 -1 -1 1 \"Released\"\n",
+    );
+    text += &format!(
+        "TRACK {} 1 150 \"Test\"\nORDER 00 : 00 00 00 00 00\nPATTERN 00\n",
+        rows.len()
+    );
+    for (i, row) in rows.iter().enumerate() {
+        text += &format!("ROW {i:02X} : {row}\n");
+    }
+    compile(&parse_text(&text).unwrap()).unwrap()
+}
+
+const EMPTY: &str = "... .. . ...";
+
+fn tick(player: &mut MusicPlayer<'_>) -> Vec<(Channel, ChannelRegs)> {
+    let mut writes = Vec::new();
+    player.tick_with(&mut |channel, regs| writes.push((channel, *regs)));
+    writes
+}
+
+/// The noise frequency for the period index of a `N-#` note.
+fn noise(index: u32) -> u32 {
+    const PERIODS: [u32; 16] = [
+        4, 8, 16, 32, 64, 96, 128, 160, 202, 254, 380, 508, 762, 1016, 2034, 4068,
+    ];
+    (1789773 / PERIODS[15 - index as usize]) << 8
+}
+
+#[test]
+fn plays_a_text_export_through_its_effects() {
+    let data = compile(&parse_text(SONG).unwrap()).unwrap();
+    let song = Song::parse(&data).unwrap();
+    assert_eq!(song.num_tracks(), 1);
+    assert_eq!(song.num_channels(), 4);
+    let mut player = MusicPlayer::new(song);
+    player.play(0).unwrap();
+
+    // instrument 0 starts at full volume on a quarter duty, the others have no macros
+    assert_eq!(
+        tick(&mut player),
+        [
+            (
+                Channel::Pulse1,
+                ChannelRegs::tone(note_frequency(69), 15).with_duty(Duty::Quarter)
+            ),
+            (
+                Channel::Triangle,
+                ChannelRegs::tone(note_frequency(57) >> 1, 15)
+            ),
+            (Channel::Noise, ChannelRegs::tone(noise(0xC), 15)),
+        ]
+    );
+    // the volume macro goes down, the arpeggio macro an octave up
+    assert_eq!(
+        tick(&mut player),
+        [(
+            Channel::Pulse1,
+            ChannelRegs::tone(note_frequency(81), 10).with_duty(Duty::Quarter)
+        )]
+    );
+    assert_eq!(tick(&mut player), [(Channel::Pulse1, ChannelRegs::OFF)]);
+    // `D02`
+    assert_eq!(tick(&mut player), []);
+
+    // the `C-4` of the first two rows is skipped, `E-4` comes in at volume 8 on the plain
+    // instrument, which keeps the duty the last one left
+    assert_eq!(
+        tick(&mut player),
+        [(
+            Channel::Pulse1,
+            ChannelRegs::tone(note_frequency(64), 8).with_duty(Duty::Quarter)
+        )]
+    );
+    // `F02` made rows two ticks long, `B02` is on the next one
+    assert_eq!(tick(&mut player), []);
+    assert!(player.is_playing());
+    assert_eq!(tick(&mut player), []);
+    // order 2, `C00`
+    assert_eq!(tick(&mut player), []);
+    assert_eq!(tick(&mut player), []);
+    assert!(player.is_playing());
+
+    // halting silences every channel
+    assert_eq!(
+        tick(&mut player),
+        [
+            (Channel::Pulse1, ChannelRegs::OFF),
+            (Channel::Pulse2, ChannelRegs::OFF),
+            (Channel::Triangle, ChannelRegs::OFF),
+            (Channel::Noise, ChannelRegs::OFF),
+        ]
+    );
+    assert!(!player.is_playing());
+    assert_eq!(tick(&mut player), []);
+}
+
+#[test]
+fn the_end_of_the_track_loops_or_stops() {
+    let data = song_with(&[
+        &format!("A-4 02 . ... : {EMPTY} : {EMPTY} : {EMPTY} : {EMPTY}"),
+        &format!("{EMPTY} : {EMPTY} : {EMPTY} : {EMPTY} : {EMPTY}"),
+    ]);
+    let mut player = MusicPlayer::new(Song::parse(&data).unwrap());
+    player.play(0).unwrap();
+    let first = tick(&mut player);
+    assert_eq!(first.len(), 1);
+    let second = tick(&mut player);
+    // back at row 0 of order 0, the note starts over
+    assert_eq!(tick(&mut player), first);
+
+    player.looping = false;
+    assert_eq!(tick(&mut player), second);
+    assert!(tick(&mut player).contains(&(Channel::Pulse1, ChannelRegs::OFF)));
+    assert!(!player.is_playing());
+}
+
+#[test]
+fn release_moves_the_macro_past_its_release_point() {
+    let pulse = |note: &str| format!("{note} : {EMPTY} : {EMPTY} : {EMPTY} : {EMPTY}");
+    let data = song_with(&[
+        &pulse("A-4 02 . ..."),
+        &pulse("... .. . ..."),
+        &pulse("... .. . ..."),
+        &pulse("=== .. . ..."),
+        &pulse("... .. . ..."),
+    ]);
+    let mut player = MusicPlayer::new(Song::parse(&data).unwrap());
+    player.play(0).unwrap();
+
+    let volume = |writes: Vec<(Channel, ChannelRegs)>| writes.first().map(|(_, regs)| regs.volume);
+    assert_eq!(volume(tick(&mut player)), Some(15));
+    assert_eq!(volume(tick(&mut player)), Some(10));
+    // held at the release point until `===`, which lets it go on after one more step
+    assert_eq!(volume(tick(&mut player)), None);
+    assert_eq!(volume(tick(&mut player)), None);
+    assert_eq!(volume(tick(&mut player)), Some(5));
+}
+
+#[test]
+fn noise_duty_picks_the_short_mode() {
+    let data = song_with(&[&format!(
+        "{EMPTY} : {EMPTY} : {EMPTY} : 3-# 02 . ... : {EMPTY}"
+    )]);
+    let mut player = MusicPlayer::new(Song::parse(&data).unwrap());
+    player.play(0).unwrap();
+    assert_eq!(
+        tick(&mut player),
+        [(
+            Channel::Noise,
+            ChannelRegs::tone(noise(3), 15).with_short_noise(true)
+        )]
+    );
+}
+
+#[test]
+fn muted_channels_are_left_alone_until_unmuted() {
+    let data = compile(&parse_text(SONG).unwrap()).unwrap();
+    let mut player = MusicPlayer::new(Song::parse(&data).unwrap());
+    player.play(0).unwrap();
+    player.set_muted(Channel::Pulse1, true);
+    let writes = tick(&mut player);
+    assert!(writes
+        .iter()
+        .all(|(channel, _)| *channel != Channel::Pulse1));
+    assert_eq!(writes.len(), 2);
+
+    // the unchanged registers are written again
+    player.set_muted(Channel::Pulse1, false);
+    assert_eq!(
+        tick(&mut player),
+        [(
+            Channel::Pulse1,
+            ChannelRegs::tone(note_frequency(81), 10).with_duty(Duty::Quarter)
+        )]
+    );
+    assert_eq!(tick(&mut player), [(Channel::Pulse1, ChannelRegs::OFF)]);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn notes_above_g9_are_rejected() {
+    let text = format!(
+        "TRACK 1 1 150 \"Test\"\nORDER 00 : 00 00 00 00 00\nPATTERN 00\n\
+         ROW 00 : G-9 00 . ... : G#9 00 . ... : {EMPTY} : {EMPTY} : {EMPTY}\n"
+    );
+    let err = parse_text(&text).err().unwrap();
+    assert_eq!(err, "line 4: note `G#9` is above G9");
+}
+
+#[test]
+fn parse_errors_name_the_line() {
+    let err = parse_text("TRACK 1 1 150 \"Test\"\nROW 00 : C-4")
+        .err()
+        .unwrap();
+    assert_eq!(err, "line 2: ROW outside of a PATTERN");
+    let err = parse_text("\nINST2A03 0 x").err().unwrap();
+    assert_eq!(err, "line 2: bad number `x`");
+}
@@ -0,0 +1,213 @@
+//! `interface::rand` against the reference sequences of its generators, and the ranges and
+//! picks `Rng` builds on them.
+
+use interface::rand::{Pcg32, Rng, SplitMix64, Xorshift32};
+
+#[test]
+fn pcg32_matches_the_reference_implementation() {
+    // `pcg32_srandom(42, 54)` in the pcg-c-basic demo
+    let mut rng = Pcg32::with_stream(42, 54);
+    let expected = [
+        0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b, 0xcbed606e,
+    ];
+    for value in expected {
+        assert_eq!(rng.next_u32(), value);
+    }
+}
+
+#[test]
+fn pcg32_streams_differ() {
+    let mut a = Pcg32::with_stream(42, 54);
+    let mut b = Pcg32::with_stream(42, 55);
+    assert_ne!(
+        (0..4).map(|_| a.next_u32()).collect::<Vec<_>>(),
+        (0..4).map(|_| b.next_u32()).collect::<Vec<_>>()
+    );
+    assert_eq!(Pcg32::new(7), Pcg32::new(7));
+}
+
+#[test]
+fn splitmix64_matches_the_reference_implementation() {
+    let mut rng = SplitMix64::new(1234567);
+    let expected = [
+        6457827717110365317,
+        3203168211198807973,
+        9817491932198370423,
+        4593380528125082431,
+        16408922859458223821,
+    ];
+    for value in expected {
+        assert_eq!(rng.next_u64(), value);
+    }
+    // the high half
+    assert_eq!(
+        SplitMix64::new(1234567).next_u32(),
+        (6457827717110365317u64 >> 32) as u32
+    );
+}
+
+#[test]
+fn xorshift32_matches_the_reference_implementation() {
+    let mut rng = Xorshift32::new(1);
+    for value in [270369, 67634689, 2647435461, 307599695, 2398689233] {
+        assert_eq!(rng.next_u32(), value);
+    }
+    // a zero seed would be stuck at zero
+    let mut rng = Xorshift32::new(0);
+    assert!((0..100).all(|_| rng.next_u32() != 0));
+}
+
+#[test]
+fn next_u64_puts_the_first_u32_on_top() {
+    let mut a = Xorshift32::new(1);
+    assert_eq!(a.next_u64(), 270369 << 32 | 67634689);
+
+    let mut bytes = [0; 6];
+    Xorshift32::new(1).fill_bytes(&mut bytes);
+    assert_eq!(bytes[..4], 270369u32.to_le_bytes());
+    assert_eq!(bytes[4..], 67634689u32.to_le_bytes()[..2]);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn ranges_stay_inside_and_reach_both_ends() {
+    let mut rng = Pcg32::new(1);
+    let mut seen = [false; 7];
+    for _ in 0..1000 {
+        let value = rng.range(3..10u8);
+        assert!((3..10).contains(&value));
+        seen[value as usize - 3] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen));
+
+    for _ in 0..1000 {
+        let value = rng.range(-5..=5i32);
+        assert!((-5..=5).contains(&value));
+    }
+    assert_eq!(rng.range(9..=9u16), 9);
+    assert_eq!(rng.range(-2..-1i64), -2);
+}
+
+#[test]
+fn full_width_ranges_cover_every_value() {
+    let mut rng = Pcg32::new(2);
+    let mut seen = [false; 256];
+    for _ in 0..10000 {
+        seen[(rng.range(i8::MIN..=i8::MAX) as u8) as usize] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen));
+
+    let mut seen = [false; 256];
+    for _ in 0..10000 {
+        seen[rng.range(u8::MIN..=u8::MAX) as usize] = true;
+    }
+    assert!(seen.iter().all(|&seen| seen));
+
+    // all of `u64` is one `next_u64`, there is nothing to reject
+    let mut copy = rng.clone();
+    assert_eq!(rng.range(0..=u64::MAX), copy.next_u64());
+    // an offset from the bottom
+    assert_eq!(
+        rng.range(i64::MIN..=i64::MAX),
+        i64::MIN.wrapping_add(copy.next_u64() as i64)
+    );
+}
+
+#[test]
+fn below_is_uniform() {
+    let mut rng = Pcg32::new(3);
+    let mut counts = [0u32; 3];
+    for _ in 0..30000 {
+        counts[rng.below(3) as usize] += 1;
+    }
+    for count in counts {
+        assert!((9500..10500).contains(&count), "{counts:?}");
+    }
+
+    // the top of the range, where the rejection matters
+    let mut counts = [0u32; 2];
+    for _ in 0..20000 {
+        counts[(rng.below_u64(u64::MAX / 3 * 2) > u64::MAX / 3) as usize] += 1;
+    }
+    for count in counts {
+        assert!((9500..10500).contains(&count), "{counts:?}");
+    }
+}
+
+#[test]
+#[should_panic(expected = "empty range")]
+fn empty_range_panics() {
+    Pcg32::new(4).range(5..5u32);
+}
+
+#[test]
+#[should_panic(expected = "empty range")]
+#[allow(clippy::reversed_empty_ranges)]
+fn backwards_inclusive_range_panics() {
+    Pcg32::new(4).range(5..=4i8);
+}
+
+#[test]
+#[should_panic(expected = "below(0)")]
+fn below_zero_panics() {
+    Pcg32::new(4).below(0);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn shuffle_keeps_every_item() {
+    let mut rng = Pcg32::new(5);
+    let mut items: Vec<u32> = (0..50).collect();
+    rng.shuffle(&mut items);
+    assert_ne!(items, (0..50).collect::<Vec<_>>());
+    items.sort();
+    assert_eq!(items, (0..50).collect::<Vec<_>>());
+
+    rng.shuffle::<u8>(&mut []);
+    let mut one = [7];
+    rng.shuffle(&mut one);
+    assert_eq!(one, [7]);
+}
+
+#[test]
+fn choose_picks_from_the_items() {
+    let mut rng = Pcg32::new(6);
+    assert_eq!(rng.choose::<u8>(&[]), None);
+    assert_eq!(rng.choose(&[4]), Some(&4));
+    let items = ['a', 'b', 'c'];
+    for _ in 0..100 {
+        assert!(items.contains(rng.choose(&items).unwrap()));
+    }
+}
+
+#[test]
+fn choose_weighted_never_picks_zero_weights() {
+    let mut rng = Pcg32::new(7);
+    assert_eq!(rng.choose_weighted(&[]), None);
+    assert_eq!(rng.choose_weighted(&[0, 0, 0]), None);
+    assert_eq!(rng.choose_weighted(&[0, 5, 0]), Some(1));
+
+    let mut counts = [0u32; 4];
+    for _ in 0..10000 {
+        counts[rng.choose_weighted(&[0, 1, 0, 3]).unwrap()] += 1;
+    }
+    assert_eq!(counts[0] + counts[2], 0);
+    assert!((2300..2700).contains(&counts[1]), "{counts:?}");
+
+    // weights adding up past `u32::MAX`
+    let index = rng.choose_weighted(&[u32::MAX, 0, u32::MAX]).unwrap();
+    assert!(index == 0 || index == 2);
+}
+
+#[test]
+fn chance_and_unit_f32() {
+    let mut rng = Pcg32::new(8);
+    assert!((0..100).all(|_| !rng.chance(0, 5)));
+    assert!((0..100).all(|_| rng.chance(5, 5)));
+    for _ in 0..1000 {
+        let unit = rng.unit_f32();
+        assert!((0.0..1.0).contains(&unit));
+    }
+}
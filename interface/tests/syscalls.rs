@@ -0,0 +1,272 @@
+//! The syscall table in `interface::sys`: the register encoding of every return type,
+//! `SyscallHandler::dispatch` with a mock and with `host::devices::Devices`, and that the
+//! committed reference is up to date.
+
+mod common;
+
+use std::fmt::Debug;
+
+use common::TempDir;
+use interface::{
+    fs::{OPEN_CREATE, OPEN_READ, OPEN_WRITE},
+    host::{audio::Synth, devices::Devices, fs::Sandbox},
+    sys::{
+        id,
+        table::{write_json, write_markdown, SysRet},
+        SysError, SysResult, SyscallHandler, SYSCALLS,
+    },
+};
+
+/// Through the registers the way a call would go, `$2` and `$3` start zeroed like the guest
+/// leaves them.
+fn round_trip<T: SysRet + PartialEq + Debug + Clone>(value: T) {
+    let [v0, v1] = value.clone().into_regs();
+    assert_eq!(T::from_regs(v0.unwrap_or(0), v1.unwrap_or(0)), value);
+}
+
+#[test]
+fn return_types_round_trip_through_the_registers() {
+    for value in [0, 1, 0x8000_0000, u32::MAX] {
+        round_trip(value);
+        round_trip::<SysResult<u32>>(Ok(value.min(i32::MAX as u32)));
+        round_trip((value, !value));
+    }
+    for value in [
+        0,
+        1,
+        u32::MAX as u64,
+        1 << 32,
+        0x0123_4567_89ab_cdef,
+        u64::MAX,
+    ] {
+        round_trip(value);
+    }
+    for value in [0, -1, i32::MIN, i32::MAX] {
+        round_trip::<SysResult<i32>>(Ok(value));
+    }
+    round_trip(true);
+    round_trip(false);
+    round_trip::<SysResult<bool>>(Ok(true));
+    round_trip::<SysResult<bool>>(Ok(false));
+    round_trip::<SysResult<()>>(Ok(()));
+    for err in SysError::ALL {
+        round_trip::<SysResult<()>>(Err(err));
+        round_trip::<SysResult<u32>>(Err(err));
+        round_trip::<SysResult<bool>>(Err(err));
+        round_trip::<SysResult<i32>>(Err(err));
+    }
+}
+
+#[test]
+fn u64_is_split_low_then_high() {
+    assert_eq!(
+        0x0123_4567_89ab_cdefu64.into_regs(),
+        [Some(0x89ab_cdef), Some(0x0123_4567)]
+    );
+    assert_eq!(
+        u64::from_regs(0x89ab_cdef, 0x0123_4567),
+        0x0123_4567_89ab_cdef
+    );
+}
+
+#[test]
+fn hosts_that_leave_the_registers_alone_read_as_success() {
+    assert_eq!(SysResult::<()>::from_regs(0, 0), Ok(()));
+    // `read_i32` and `rand_range` keep their value in `$2` like before statuses
+    assert_eq!(SysResult::<i32>::from_regs(-5i32 as u32, 0), Ok(-5));
+    assert_eq!(
+        SysResult::<i32>::from_regs(0, SysError::InvalidData.code() as u32),
+        Err(SysError::InvalidData)
+    );
+}
+
+//----------------------------------------------------------------
+
+#[derive(Default)]
+struct Mock {
+    printed: Vec<i32>,
+}
+
+impl SyscallHandler for Mock {
+    fn print_i32(&mut self, num: i32) {
+        self.printed.push(num);
+    }
+
+    fn read_i32(&mut self) -> SysResult<i32> {
+        Ok(-42)
+    }
+
+    fn rand_range(&mut self, min: i32, max: i32) -> SysResult<i32> {
+        if min > max {
+            return Err(SysError::InvalidArgument);
+        }
+        Ok(min)
+    }
+
+    fn get_nanos(&mut self) -> u64 {
+        0x0000_0002_0000_0001
+    }
+
+    fn is_key_pressed(&mut self, key: u32) -> bool {
+        key == 'a' as u32
+    }
+
+    fn mouse_position(&mut self) -> (u32, u32) {
+        (-3i32 as u32, 7)
+    }
+}
+
+fn call(handler: &mut impl SyscallHandler, call_id: u32, args: &[u32]) -> [u32; 32] {
+    // garbage in the return registers, `dispatch` has to overwrite what it returns
+    let mut regs = [0xdead_beef; 32];
+    regs[4..4 + args.len()].copy_from_slice(args);
+    assert!(handler.dispatch(call_id, &mut regs));
+    regs
+}
+
+#[test]
+fn dispatch_decodes_arguments_and_encodes_results() {
+    let mut mock = Mock::default();
+
+    let regs = call(&mut mock, id::print_i32, &[-7i32 as u32]);
+    assert_eq!(mock.printed, [-7]);
+    // nothing returned, nothing written
+    assert_eq!(regs[2..4], [0xdead_beef; 2]);
+
+    let regs = call(&mut mock, id::read_i32, &[]);
+    assert_eq!(regs[2..4], [-42i32 as u32, 0]);
+
+    let regs = call(&mut mock, id::rand_range, &[10, 20]);
+    assert_eq!(regs[2..4], [10, 0]);
+    let regs = call(&mut mock, id::rand_range, &[20, 10]);
+    assert_eq!(regs[3], SysError::InvalidArgument.code() as u32);
+
+    let regs = call(&mut mock, id::get_nanos, &[]);
+    assert_eq!(regs[2..4], [1, 2]);
+    assert_eq!(u64::from_regs(regs[2], regs[3]), 0x0000_0002_0000_0001);
+
+    assert_eq!(call(&mut mock, id::is_key_pressed, &['a' as u32])[2], 1);
+    assert_eq!(call(&mut mock, id::is_key_pressed, &['b' as u32])[2], 0);
+
+    let regs = call(&mut mock, id::mouse_position, &[]);
+    assert_eq!(regs[2..4], [-3i32 as u32, 7]);
+}
+
+#[test]
+fn dispatch_reports_unsupported_and_unknown_calls() {
+    let mut mock = Mock::default();
+
+    let regs = call(&mut mock, id::init_screen, &[320, 240]);
+    assert_eq!(regs[2], SysError::Unsupported.code() as u32);
+    let regs = call(&mut mock, id::fs_open, &[0, 0, 0]);
+    assert_eq!(regs[2], SysError::Unsupported.code() as u32);
+
+    let mut regs = [0xdead_beef; 32];
+    assert!(!mock.dispatch(12345, &mut regs));
+    assert_eq!(regs, [0xdead_beef; 32]);
+}
+
+//----------------------------------------------------------------
+
+const PATH: u32 = 0x100;
+const DATA: u32 = 0x200;
+const STAT: u32 = 0x300;
+
+fn devices(name: &str) -> (Devices<Vec<u8>>, TempDir) {
+    let dir = TempDir::new(&format!("sys-{name}"));
+    let devices = Devices::new(vec![0; 0x1000], Sandbox::new(dir.path()), Synth::new());
+    (devices, dir)
+}
+
+fn status(regs: [u32; 32]) -> Result<u32, SysError> {
+    SysError::check(regs[2])
+}
+
+#[test]
+fn devices_serve_files_through_dispatch() {
+    let (mut devices, _dir) = devices("files");
+    let path = b"saves/../hi.txt";
+    devices.memory[PATH as usize..][..path.len()].copy_from_slice(path);
+    let open = call(
+        &mut devices,
+        id::fs_open,
+        &[PATH, path.len() as u32, OPEN_READ],
+    );
+    assert_eq!(status(open), Err(SysError::InvalidPath));
+
+    let path = b"hi.txt";
+    devices.memory[PATH as usize..][..path.len()].copy_from_slice(path);
+    devices.memory[DATA as usize..][..5].copy_from_slice(b"hello");
+
+    let flags = OPEN_WRITE | OPEN_CREATE;
+    let handle = status(call(&mut devices, id::fs_open, &[PATH, 6, flags])).unwrap();
+    assert_eq!(
+        status(call(&mut devices, id::fs_write, &[handle, DATA, 5])),
+        Ok(5)
+    );
+    assert_eq!(status(call(&mut devices, id::fs_close, &[handle])), Ok(0));
+    assert_eq!(
+        status(call(&mut devices, id::fs_close, &[handle])),
+        Err(SysError::BadHandle)
+    );
+
+    assert_eq!(
+        status(call(&mut devices, id::fs_stat, &[PATH, 6, STAT])),
+        Ok(0)
+    );
+    // big endian like the guest
+    assert_eq!(devices.memory[STAT as usize..][..4], [0, 0, 0, 5]);
+
+    let handle = status(call(&mut devices, id::fs_open, &[PATH, 6, OPEN_READ])).unwrap();
+    // a bad buffer fails before anything is read, the next read still gets all of it
+    let unmapped = call(&mut devices, id::fs_read, &[handle, 0x10_0000, 16]);
+    assert_eq!(status(unmapped), Err(SysError::InvalidArgument));
+    let partly = call(&mut devices, id::fs_read, &[handle, 0xff8, 16]);
+    assert_eq!(status(partly), Err(SysError::InvalidArgument));
+    let read = call(&mut devices, id::fs_read, &[handle, DATA + 0x10, 16]);
+    assert_eq!(status(read), Ok(5));
+    assert_eq!(&devices.memory[DATA as usize + 0x10..][..5], b"hello");
+}
+
+#[test]
+fn devices_check_audio_arguments() {
+    let (mut devices, _dir) = devices("audio");
+
+    let regs = call(&mut devices, id::audio_pcm_configure, &[0, 1]);
+    assert_eq!(status(regs), Err(SysError::InvalidArgument));
+    let regs = call(&mut devices, id::audio_pcm_configure, &[22050, 2]);
+    assert_eq!(status(regs), Ok(0));
+    let regs = call(&mut devices, id::audio_write_channel, &[99, DATA]);
+    assert_eq!(status(regs), Err(SysError::InvalidArgument));
+
+    let available = call(&mut devices, id::audio_pcm_available, &[])[2];
+    assert_eq!(call(&mut devices, id::audio_pcm_write, &[DATA, 8])[2], 8);
+    assert_eq!(
+        call(&mut devices, id::audio_pcm_available, &[])[2],
+        available - 8
+    );
+
+    // not a device call
+    let regs = call(&mut devices, id::init_screen, &[1, 1]);
+    assert_eq!(status(regs), Err(SysError::Unsupported));
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn committed_reference_is_up_to_date() {
+    let mut markdown = String::new();
+    write_markdown(&mut markdown, SYSCALLS).unwrap();
+    let mut json = String::new();
+    write_json(&mut json, SYSCALLS).unwrap();
+
+    let regenerate = "regenerate it with the `syscall_reference` example";
+    assert!(
+        markdown == include_str!("../syscalls.md"),
+        "syscalls.md is out of date, {regenerate}"
+    );
+    assert!(
+        json == include_str!("../syscalls.json"),
+        "syscalls.json is out of date, {regenerate}"
+    );
+}
@@ -0,0 +1,119 @@
+//! `FixedTimestep` and `FrameStats` in `interface::time`, stepped with made up frame times.
+
+use interface::time::{Duration, FixedTimestep, FrameStats, Instant};
+
+const STEP: Duration = Duration::from_millis(10);
+
+fn ms(millis: u64) -> Instant {
+    Instant::from_nanos(millis * 1_000_000)
+}
+
+fn assert_alpha(timestep: &FixedTimestep, alpha: f32) {
+    assert!(
+        (timestep.alpha() - alpha).abs() < 1e-4,
+        "alpha {} instead of {alpha}",
+        timestep.alpha()
+    );
+}
+
+#[test]
+fn advance_steps_through_the_time_that_passed() {
+    let mut timestep = FixedTimestep::from_hz(100);
+    assert_eq!(timestep.step(), STEP);
+    assert_eq!(timestep.advance_to(ms(1000)), 1);
+
+    assert_eq!(timestep.advance_to(ms(1010)), 1);
+    assert_alpha(&timestep, 0.0);
+    // too early for a step, the time is kept for the next frame
+    assert_eq!(timestep.advance_to(ms(1014)), 0);
+    assert_alpha(&timestep, 0.4);
+    assert_eq!(timestep.advance_to(ms(1020)), 1);
+    assert_alpha(&timestep, 0.0);
+}
+
+#[test]
+fn slow_frames_catch_up_and_keep_the_remainder() {
+    let mut timestep = FixedTimestep::from_hz(100);
+    timestep.advance_to(ms(0));
+
+    assert_eq!(timestep.advance_to(ms(25)), 2);
+    assert_alpha(&timestep, 0.5);
+    // with the 5 ms left over from before
+    assert_eq!(timestep.advance_to(ms(40)), 2);
+    assert_alpha(&timestep, 0.0);
+}
+
+#[test]
+fn frames_too_far_behind_drop_the_rest() {
+    let mut timestep = FixedTimestep::from_hz(100);
+    timestep.advance_to(ms(0));
+
+    // 10 steps due, 4 run, the other 6 are dropped and only the part of a step stays
+    assert_eq!(timestep.advance_to(ms(103)), 4);
+    assert_alpha(&timestep, 0.3);
+    assert_eq!(timestep.advance_to(ms(110)), 1);
+    assert_alpha(&timestep, 0.0);
+
+    let mut timestep = FixedTimestep::from_hz(100).with_max_steps(2);
+    timestep.advance_to(ms(0));
+    assert_eq!(timestep.advance_to(ms(50)), 2);
+    assert_alpha(&timestep, 0.0);
+
+    // 0 would never catch up at all
+    let mut timestep = FixedTimestep::from_hz(100).with_max_steps(0);
+    timestep.advance_to(ms(0));
+    assert_eq!(timestep.advance_to(ms(50)), 1);
+}
+
+#[test]
+fn advance_records_the_frames() {
+    let mut timestep = FixedTimestep::from_hz(100);
+    timestep.advance_to(ms(0));
+    assert_eq!(timestep.stats().frames(), 0);
+    for frame in 1..=5 {
+        timestep.advance_to(ms(frame * 20));
+    }
+    let stats = timestep.stats();
+    assert_eq!(stats.frames(), 5);
+    assert_eq!(stats.frame_time(), Duration::from_millis(20));
+    assert_eq!(stats.fps(), 50);
+    // never slept
+    assert_eq!(stats.cpu_usage(), 10000);
+}
+
+#[test]
+#[should_panic(expected = "from_hz(0)")]
+fn zero_hz_is_rejected() {
+    FixedTimestep::from_hz(0);
+}
+
+//----------------------------------------------------------------
+
+#[test]
+fn frame_stats_average_fps_and_cpu() {
+    let mut stats = FrameStats::new();
+    assert_eq!(stats.fps(), 0);
+    assert_eq!(stats.cpu_usage(), 0);
+
+    // the first frame is taken as it is
+    stats.record(Duration::from_millis(10), Duration::from_micros(2500));
+    assert_eq!(stats.fps(), 100);
+    assert_eq!(stats.cpu_usage(), 2500);
+    assert_eq!(stats.to_string(), "100 fps, 10000 us a frame, cpu 25.00%");
+
+    // then each one weighs 1/16th
+    stats.record(Duration::from_millis(20), Duration::from_millis(20));
+    assert_eq!(stats.frame_time(), Duration::from_nanos(10_625_000));
+    assert_eq!(stats.busy_time(), Duration::from_nanos(3_593_750));
+    assert_eq!(stats.fps(), 94);
+    assert_eq!(stats.cpu_usage(), 3382);
+    assert_eq!(stats.frames(), 2);
+}
+
+#[test]
+fn frame_stats_cap_busy_at_the_frame() {
+    let mut stats = FrameStats::new();
+    stats.record(Duration::from_millis(10), Duration::from_millis(30));
+    assert_eq!(stats.cpu_usage(), 10000);
+    assert_eq!(stats.busy_time(), stats.frame_time());
+}
@@ -0,0 +1,5 @@
+# runs interface/tests on the host, extra arguments go to cargo test (like a test name to filter by)
+# from outside of the repo so .cargo/config.toml doesn't build for mips, and only the tests in
+# interface/tests since the library itself is no_main
+repo=$(cd "$(dirname "$0")" && pwd)
+cd / && cargo +nightly test --manifest-path "$repo/interface/Cargo.toml" --features host,alloc --target-dir "$repo/target/host" --test '*' "$@"